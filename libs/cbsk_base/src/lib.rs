@@ -10,6 +10,8 @@ pub use serde;
 pub use serde_json;
 #[cfg(feature = "log")]
 pub use log;
+#[cfg(feature = "tracing")]
+pub use tracing;
 #[cfg(feature = "async-trait")]
 pub use async_trait;
 #[cfg(feature = "async-recursion")]
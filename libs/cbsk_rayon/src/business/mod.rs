@@ -1 +1,13 @@
-pub mod cbsk_write_trait;
\ No newline at end of file
+use std::io;
+use cbsk::business;
+use cbsk::data::len_encoding::LenEncoding;
+
+pub mod cbsk_write_trait;
+
+/// encode data to cbsk frame with the length encoding<br />
+/// return Err with [io::ErrorKind::InvalidInput] if the data is too long for the length encoding
+pub(crate) fn frame(bytes: Vec<u8>, header: &[u8], len_encoding: LenEncoding) -> io::Result<Vec<u8>> {
+    business::frame_with_len(bytes, header, len_encoding).map_err(|bytes| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("data length {} exceeds the length encoding {:?}", bytes.len(), len_encoding))
+    })
+}
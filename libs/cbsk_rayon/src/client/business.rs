@@ -1,5 +1,7 @@
 use std::sync::Arc;
 use cbsk::{business, data};
+use cbsk::data::decoded::Decoded;
+use cbsk::data::len_encoding::LenEncoding;
 #[cfg(feature = "debug_mode")]
 use cbsk_base::log;
 use cbsk_socket_rayon::tcp::client::callback::TcpClientCallBack;
@@ -10,6 +12,8 @@ pub struct CbskClientBusines<C: CbskClientCallBack> {
     /// the cbsk first frame<br />
     /// Used to determine if it is cbsk data
     pub header: Arc<Vec<u8>>,
+    /// the encoding of the data length after the header frame
+    pub len_encoding: LenEncoding,
     /// business callback
     pub cb: Arc<C>,
     /// internal log name, used for log printing
@@ -20,7 +24,7 @@ pub struct CbskClientBusines<C: CbskClientCallBack> {
 impl<C: CbskClientCallBack> CbskClientBusines<C> {
     /// new business
     pub fn new(cb: Arc<C>) -> Self {
-        Self { cb, header: data::default_header().into(), log_head: String::new() }
+        Self { len_encoding: cb.len_encoding(), cb, header: data::default_header().into(), log_head: String::new() }
     }
 
     /// new business, custom header frame
@@ -29,7 +33,7 @@ impl<C: CbskClientCallBack> CbskClientBusines<C> {
         if header.is_empty() {
             header = data::default_header()
         }
-        Self { cb, header: header.into(), log_head: String::new() }
+        Self { len_encoding: cb.len_encoding(), cb, header: header.into(), log_head: String::new() }
     }
 }
 
@@ -47,73 +51,24 @@ impl<C: CbskClientCallBack> TcpClientCallBack for CbskClientBusines<C> {
         self.cb.re_conn(num)
     }
 
-    fn recv(&self, mut bytes: Vec<u8>) -> Vec<u8> {
-        // TODO can the following code be optimized? There are too many if and loop
+    fn recv(&self, bytes: Vec<u8>) -> Vec<u8> {
         #[cfg(feature = "debug_mode")]
         log::info!("{} start recv loop", self.log_head);
-        loop {
-            let mut verify_data = business::verify(bytes, &self.header);
-            #[cfg(feature = "debug_mode")] {
-                log::info!("{} cbsk recv loop", self.log_head);
-                log::info!("{} error_frame len is {}", self.log_head, verify_data.error_frame.len());
-                log::info!("{} too_short_frame len is {}", self.log_head, verify_data.too_short_frame.len());
-                log::info!("{} data_frame len is {}", self.log_head, verify_data.data_frame.len());
-                log::info!("{} next_verify_frame len is {}", self.log_head, verify_data.next_verify_frame.len());
-            }
-
-            if !verify_data.error_frame.is_empty() {
-                self.cb.error_frame(verify_data.error_frame);
-            }
-
-            // if has too short frame, wait next tcp read
-            if !verify_data.too_short_frame.is_empty() {
-                return verify_data.too_short_frame;
-            }
-
-            // verify success, perform data analysis
-            if !verify_data.data_frame.is_empty() {
-                loop {
-                    let analysis_data = business::analysis(verify_data.data_frame, &self.header);
-
-                    if let Some(too_long) = analysis_data.too_long_byte {
-                        self.cb.too_long_frame(too_long);
-                    }
-
-                    // analysis success, call cb.recv
-                    if !analysis_data.data_frame.is_empty() {
-                        self.cb.recv(analysis_data.data_frame);
-                    }
-
-                    // if has next verify, change verify_data.next_verify_frame and break current loop
-                    if !analysis_data.next_verify_frame.is_empty() {
-                        verify_data.next_verify_frame = analysis_data.next_verify_frame;
-                        break;
-                    }
-
-                    // if has too short frame, wait next tcp read
-                    if !analysis_data.too_short_frame.is_empty() {
-                        return analysis_data.too_short_frame;
-                    }
-
-                    // analysis logic over, break current loop
-                    break;
-                }
-            }
-
-            // if has next verify, change bytes and go to next loop
-            if !verify_data.next_verify_frame.is_empty() {
-                bytes = verify_data.next_verify_frame;
-                continue;
+        let (decoded, too_short_frame) = business::decode(bytes, &self.header, self.len_encoding);
+        #[cfg(feature = "debug_mode")]
+        log::info!("{} decoded len is {}, too_short_frame len is {}", self.log_head, decoded.len(), too_short_frame.len());
+        for decoded in decoded {
+            match decoded {
+                Decoded::ErrorFrame(error_frame) => { self.cb.error_frame(error_frame); }
+                Decoded::TooLong(byte) => { self.cb.too_long_frame(byte); }
+                Decoded::Data(frame) => { self.cb.recv(frame); }
             }
-
-            // verify logic over, break loop
-            break;
         }
 
         #[cfg(feature = "debug_mode")]
         log::info!("end recv loop");
 
-        // default return empty data
-        Vec::new()
+        // if has too short frame, wait next tcp read
+        too_short_frame
     }
 }
@@ -1,3 +1,4 @@
+use cbsk::data::len_encoding::LenEncoding;
 use cbsk_base::log;
 
 /// cbsk connect and read data callback
@@ -28,6 +29,13 @@ pub trait CbskClientCallBack: Send + Sync + 'static {
         log::warn!("received cbsk frame, but first byte[{byte}] is too long");
     }
 
+    /// the encoding of the data length after the header frame<br />
+    /// only be called once when the cbsk client is created, default is [LenEncoding::VarLe]<br />
+    /// the cbsk server must use the same length encoding
+    fn len_encoding(&self) -> LenEncoding {
+        LenEncoding::default()
+    }
+
     /// read tcp server data will call this method<br />
     /// bytes: cbsk server bytes<br />
     fn recv(&self, bytes: Vec<u8>);
@@ -1,6 +1,7 @@
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
+use cbsk::data::len_encoding::LenEncoding;
 use cbsk_socket_rayon::cbsk_socket::config::re_conn::SocketReConn;
 use cbsk_socket_rayon::cbsk_socket::tcp::client::config::TcpClientConfig;
 use cbsk_socket_rayon::cbsk_socket::tcp::common::time_trait::TimeTrait;
@@ -19,6 +20,8 @@ pub struct CbskClient {
     tcp_client: Arc<TcpClient>,
     /// cbsk header
     pub header: Arc<Vec<u8>>,
+    /// the encoding of the data length after the header frame
+    len_encoding: LenEncoding,
 }
 
 /// custom method
@@ -55,9 +58,10 @@ impl CbskClient {
     /// buf_len is tcp read data once lengle
     fn new_with_business<C: CbskClientCallBack>(mut cb: CbskClientBusines<C>, conf: Arc<TcpClientConfig>, buf_len: usize) -> Self {
         let header = cb.header.clone();
+        let len_encoding = cb.len_encoding;
         cb.log_head = conf.log_head.clone();
         let tcp_client = TcpClient::new_with_buf_len(conf.clone(), buf_len, cb).into();
-        Self { tcp_client, header }
+        Self { tcp_client, header, len_encoding }
     }
 
     /// get default tcp config
@@ -105,7 +109,7 @@ impl CbskWriteTrait for CbskClient {
     }
 
     fn try_send_bytes(&self, bytes: Vec<u8>) -> std::io::Result<()> {
-        let frame = cbsk::business::frame(bytes, self.header.as_slice());
+        let frame = crate::business::frame(bytes, self.header.as_slice(), self.len_encoding)?;
         self.tcp_client.try_send_bytes(frame.as_slice())
     }
 }
@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use cbsk::data::len_encoding::LenEncoding;
 use cbsk_base::log;
 use crate::business::cbsk_write_trait::CbskWriteTrait;
 use crate::server::client::CbskServerClient;
@@ -25,6 +26,13 @@ pub trait CbskServerCallBack: Send + Sync + 'static {
         log::warn!("{} received cbsk frame, but first byte[{byte}] is too long",client.get_log_head());
     }
 
+    /// the encoding of the data length after the header frame<br />
+    /// only be called once when the cbsk server is created, default is [LenEncoding::VarLe]<br />
+    /// the cbsk client must use the same length encoding
+    fn len_encoding(&self) -> LenEncoding {
+        LenEncoding::default()
+    }
+
     /// tcp server recv tcp client data will call this method<br />
     /// bytes: tcp client data<br />
     /// client: tcp client, you can use this send data to tcp client
@@ -1,9 +1,12 @@
 use std::io;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use cbsk::business;
+use cbsk::data::len_encoding::LenEncoding;
+use cbsk_socket_rayon::cbsk_socket::session::attr_trait::AttrTrait;
+use cbsk_socket_rayon::cbsk_socket::session::attrs::SessionAttrs;
 use cbsk_socket_rayon::tcp::common::tcp_write_trait::TcpWriteTrait;
 use cbsk_socket_rayon::tcp::server::client::TcpServerClient;
+use crate::business;
 use crate::business::cbsk_write_trait::CbskWriteTrait;
 
 /// cbsk server client
@@ -11,6 +14,8 @@ pub struct CbskServerClient {
     /// the cbsk first frame<br />
     /// Used to determine if it is cbsk data
     pub header: Arc<Vec<u8>>,
+    /// the encoding of the data length after the header frame
+    len_encoding: LenEncoding,
     /// tcp server client
     tcp_server_client: Arc<TcpServerClient>,
 }
@@ -18,8 +23,8 @@ pub struct CbskServerClient {
 /// custom method
 impl CbskServerClient {
     /// create cbsk server client
-    pub(crate) fn new(header: Arc<Vec<u8>>, tcp_server_client: Arc<TcpServerClient>) -> Self {
-        Self { header, tcp_server_client }
+    pub(crate) fn new(header: Arc<Vec<u8>>, len_encoding: LenEncoding, tcp_server_client: Arc<TcpServerClient>) -> Self {
+        Self { header, len_encoding, tcp_server_client }
     }
 
     /// get client addr
@@ -28,6 +33,17 @@ impl CbskServerClient {
     }
 }
 
+/// support session attr trait
+impl AttrTrait for CbskServerClient {
+    fn get_conn_id(&self) -> u64 {
+        self.tcp_server_client.get_conn_id()
+    }
+
+    fn get_attrs(&self) -> &SessionAttrs {
+        self.tcp_server_client.get_attrs()
+    }
+}
+
 /// support cbsk write trait
 impl CbskWriteTrait for CbskServerClient {
     fn get_log_head(&self) -> &str {
@@ -35,7 +51,7 @@ impl CbskWriteTrait for CbskServerClient {
     }
 
     fn try_send_bytes(&self, bytes: Vec<u8>) -> io::Result<()> {
-        let frame = business::frame(bytes, self.header.as_slice());
+        let frame = business::frame(bytes, self.header.as_slice(), self.len_encoding)?;
         self.tcp_server_client.try_send_bytes(frame.as_slice())
     }
 }
@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+/// ping/pong keepalive config<br />
+/// a ping is sent every interval, if the pong is not received within pong_time_out, the connection is considered dead
+#[derive(Clone, Copy, Debug)]
+pub struct KeepAlive {
+    /// the interval between two pings
+    pub interval: Duration,
+    /// the time to wait for the pong
+    pub pong_time_out: Duration,
+}
+
+/// support default
+impl Default for KeepAlive {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(30), Duration::from_secs(10))
+    }
+}
+
+/// custom method
+impl KeepAlive {
+    /// create keepalive config<br />
+    /// interval: the interval between two pings<br />
+    /// pong_time_out: the time to wait for the pong
+    pub fn new(interval: Duration, pong_time_out: Duration) -> Self {
+        Self { interval, pong_time_out }
+    }
+
+    /// set the interval between two pings
+    pub fn set_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// set the time to wait for the pong
+    pub fn set_pong_time_out(mut self, pong_time_out: Duration) -> Self {
+        self.pong_time_out = pong_time_out;
+        self
+    }
+
+    /// the max time between two keepalive checks, used as the read timeout
+    pub fn check_time(&self) -> Duration {
+        self.interval.min(self.pong_time_out)
+    }
+}
@@ -1 +1,3 @@
-pub mod re_conn;
\ No newline at end of file
+pub mod re_conn;
+pub mod rate_limit;
+pub mod keep_alive;
\ No newline at end of file
@@ -0,0 +1,52 @@
+/// what to do when the rate limit is exceeded
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LimitPolicy {
+    /// wait until enough tokens are available, reading will be delayed
+    #[default]
+    Delay,
+    /// discard the frame
+    Drop,
+    /// shutdown the client connection
+    DisConn,
+}
+
+/// which limiter is exceeded
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LimitScope {
+    /// the limiter of a single client
+    Client,
+    /// the limiter shared by all clients of the server
+    Server,
+}
+
+/// token bucket rate limit config<br />
+/// the bucket capacity is the number of tokens per second, so a burst of up to one second is allowed
+#[derive(Clone, Debug, Default)]
+pub struct RateLimit {
+    /// max frames per second, None is unlimited
+    pub frames_per_sec: Option<u64>,
+    /// max bytes per second, None is unlimited
+    pub bytes_per_sec: Option<u64>,
+    /// what to do when the rate limit is exceeded
+    pub policy: LimitPolicy,
+}
+
+/// custom method
+impl RateLimit {
+    /// create rate limit config, frames and bytes are unlimited by default
+    pub fn new(policy: LimitPolicy) -> Self {
+        Self { policy, ..Default::default() }
+    }
+
+    /// set max frames per second
+    pub fn set_frames_per_sec(mut self, frames_per_sec: u64) -> Self {
+        self.frames_per_sec = Some(frames_per_sec);
+        self
+    }
+
+    /// set max bytes per second
+    pub fn set_bytes_per_sec(mut self, bytes_per_sec: u64) -> Self {
+        self.bytes_per_sec = Some(bytes_per_sec);
+        self
+    }
+}
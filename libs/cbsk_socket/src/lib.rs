@@ -1,3 +1,7 @@
 pub mod config;
+pub mod limit;
+pub mod metrics;
+pub mod session;
+pub mod trace;
 #[cfg(any(feature = "tcp_client", feature = "tcp_server"))]
 pub mod tcp;
\ No newline at end of file
@@ -0,0 +1,2 @@
+pub mod token_bucket;
+pub mod rate_limiter;
\ No newline at end of file
@@ -0,0 +1,167 @@
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+use crate::config::rate_limit::{LimitPolicy, LimitScope, RateLimit};
+use crate::limit::token_bucket::TokenBucket;
+
+/// the result of checking the rate limiters for a frame
+#[derive(Debug, PartialEq, Eq)]
+pub enum LimitCheck {
+    /// the frame passes, carries the exceeded limiters with delay policy and the time to wait
+    Pass(Vec<(LimitScope, Duration)>),
+    /// the frame is rejected by the limiter with drop or disconn policy, no tokens are taken from any limiter
+    Reject(LimitScope, LimitPolicy),
+}
+
+/// check the rate limiters for a frame, such as the client limiter and the server limiter<br />
+/// the limiters with drop or disconn policy are checked first, tokens are taken from all limiters only if none of them rejects the frame<br />
+/// all limiters are locked in a fixed order for the whole check and take, so that concurrent frames never overdraw a shared limiter<br />
+/// len: frame length
+pub fn check_all(limiters: &[(LimitScope, Option<&RateLimiter>)], len: usize) -> LimitCheck {
+    let limiters: Vec<_> = limiters.iter().filter_map(|(scope, limiter)| Some((*scope, (*limiter)?))).collect();
+    let mut locked: Vec<_> = limiters.iter().map(|(_, limiter)| *limiter).collect();
+    locked.sort_by_key(|limiter| std::ptr::from_ref(*limiter).addr());
+    locked.dedup_by_key(|limiter| std::ptr::from_ref(*limiter).addr());
+    let mut guards: Vec<_> = locked.into_iter()
+        .map(|limiter| (std::ptr::from_ref(limiter), limiter.buckets.lock().unwrap_or_else(PoisonError::into_inner)))
+        .collect();
+
+    let len_u64 = u64::try_from(len).unwrap_or(u64::MAX);
+    let now = Instant::now();
+    for (scope, limiter) in limiters.iter() {
+        if limiter.limit.policy == LimitPolicy::Delay { continue; }
+        let (frames, bytes) = buckets_of(&mut guards, limiter);
+        if RateLimiter::check_with(frames, bytes, len_u64, now).is_err() { return LimitCheck::Reject(*scope, limiter.limit.policy); }
+    }
+
+    let mut delays = Vec::new();
+    for (scope, limiter) in limiters {
+        let (frames, bytes) = buckets_of(&mut guards, limiter);
+        let wait = RateLimiter::acquire_with(frames, bytes, len_u64, now);
+        if limiter.limit.policy == LimitPolicy::Delay && !wait.is_zero() { delays.push((scope, wait)); }
+    }
+    LimitCheck::Pass(delays)
+}
+
+/// frames and bytes token buckets
+type Buckets = (Option<TokenBucket>, Option<TokenBucket>);
+
+/// get the locked buckets of the limiter
+fn buckets_of<'a>(guards: &'a mut [(*const RateLimiter, MutexGuard<'_, Buckets>)], limiter: &RateLimiter) -> &'a mut Buckets {
+    let index = guards.iter().position(|(locked, _)| std::ptr::eq(*locked, limiter)).unwrap_or_default();
+    &mut guards[index].1
+}
+
+/// frames and bytes rate limiter
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// rate limit config
+    pub limit: RateLimit,
+    /// frames and bytes token bucket
+    buckets: Mutex<Buckets>,
+}
+
+/// custom method
+impl RateLimiter {
+    /// create rate limiter
+    pub fn new(limit: RateLimit) -> Self {
+        let frames = limit.frames_per_sec.map(TokenBucket::new);
+        let bytes = limit.bytes_per_sec.map(TokenBucket::new);
+        Self { limit, buckets: Mutex::new((frames, bytes)) }
+    }
+
+    /// try take tokens for a frame, tokens are taken only if both frames and bytes are sufficient<br />
+    /// len: frame length<br />
+    /// return Err: the time to wait until enough tokens are available
+    pub fn try_acquire(&self, len: usize) -> Result<(), Duration> {
+        let len = u64::try_from(len).unwrap_or(u64::MAX);
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        let (frames, bytes) = &mut *buckets;
+
+        Self::check_with(frames, bytes, len, now)?;
+        Self::acquire_with(frames, bytes, len, now);
+        Ok(())
+    }
+
+    /// check whether the tokens for a frame are sufficient, no tokens are taken<br />
+    /// len: frame length<br />
+    /// return Err: the time to wait until enough tokens are available
+    pub fn check(&self, len: usize) -> Result<(), Duration> {
+        let len = u64::try_from(len).unwrap_or(u64::MAX);
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        let (frames, bytes) = &mut *buckets;
+        Self::check_with(frames, bytes, len, now)
+    }
+
+    /// take tokens for a frame even if they are insufficient<br />
+    /// len: frame length<br />
+    /// return the time to wait until the taken tokens are actually available, zero if the limit is not exceeded
+    pub fn acquire(&self, len: usize) -> Duration {
+        let len = u64::try_from(len).unwrap_or(u64::MAX);
+        let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+        let (frames, bytes) = &mut *buckets;
+        Self::acquire_with(frames, bytes, len, Instant::now())
+    }
+
+    /// check frames and bytes tokens, return the max wait time if insufficient
+    fn check_with(frames: &mut Option<TokenBucket>, bytes: &mut Option<TokenBucket>, len: u64, now: Instant) -> Result<(), Duration> {
+        let frames_wait = frames.as_mut().map_or(Ok(()), |frames| frames.check(1, now));
+        let bytes_wait = bytes.as_mut().map_or(Ok(()), |bytes| bytes.check(len, now));
+        match (frames_wait, bytes_wait) {
+            (Ok(()), Ok(())) => { Ok(()) }
+            (Err(wait), Ok(())) | (Ok(()), Err(wait)) => { Err(wait) }
+            (Err(frames_wait), Err(bytes_wait)) => { Err(frames_wait.max(bytes_wait)) }
+        }
+    }
+
+    /// take frames and bytes tokens, return the max wait time
+    fn acquire_with(frames: &mut Option<TokenBucket>, bytes: &mut Option<TokenBucket>, len: u64, now: Instant) -> Duration {
+        let frames_wait = frames.as_mut().map_or(Duration::ZERO, |frames| frames.take(1, now));
+        let bytes_wait = bytes.as_mut().map_or(Duration::ZERO, |bytes| bytes.take(len, now));
+        frames_wait.max(bytes_wait)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(frames_per_sec: u64, policy: LimitPolicy) -> RateLimiter {
+        RateLimiter::new(RateLimit::new(policy).set_frames_per_sec(frames_per_sec))
+    }
+
+    #[test]
+    fn reject_takes_no_tokens() {
+        let client = limiter(2, LimitPolicy::Drop);
+        let server = limiter(1, LimitPolicy::Drop);
+        let limiters = [(LimitScope::Client, Some(&client)), (LimitScope::Server, Some(&server))];
+        assert_eq!(check_all(&limiters, 1), LimitCheck::Pass(Vec::new()));
+        // the server limiter rejects, the client tokens are not taken
+        assert_eq!(check_all(&limiters, 1), LimitCheck::Reject(LimitScope::Server, LimitPolicy::Drop));
+        assert_eq!(client.try_acquire(1), Ok(()));
+        assert!(client.try_acquire(1).is_err());
+    }
+
+    #[test]
+    fn reject_before_delay() {
+        let client = limiter(1, LimitPolicy::Delay);
+        let server = limiter(1, LimitPolicy::DisConn);
+        let limiters = [(LimitScope::Client, Some(&client)), (LimitScope::Server, Some(&server)), (LimitScope::Server, None)];
+        assert_eq!(check_all(&limiters, 1), LimitCheck::Pass(Vec::new()));
+        assert_eq!(check_all(&limiters, 1), LimitCheck::Reject(LimitScope::Server, LimitPolicy::DisConn));
+        // the delayed client limiter has not taken tokens for the rejected frame
+        let LimitCheck::Pass(delays) = check_all(&limiters[..1], 1) else { panic!("the delay limiter never rejects") };
+        assert_eq!(delays.len(), 1);
+        assert!(delays[0].1 <= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn same_limiter_locked_once() {
+        let shared = limiter(2, LimitPolicy::Drop);
+        let limiters = [(LimitScope::Client, Some(&shared)), (LimitScope::Server, Some(&shared))];
+        // both scopes take tokens from the same limiter
+        assert_eq!(check_all(&limiters, 1), LimitCheck::Pass(Vec::new()));
+        assert!(shared.try_acquire(1).is_err());
+    }
+}
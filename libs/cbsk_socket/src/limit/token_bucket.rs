@@ -0,0 +1,53 @@
+use std::time::{Duration, Instant};
+
+/// token bucket, refilled continuously at a fixed rate
+#[derive(Debug)]
+pub struct TokenBucket {
+    /// tokens added per second, also the bucket capacity
+    rate: f64,
+    /// current tokens, negative if tokens were reserved in advance
+    tokens: f64,
+    /// the last refill time
+    last_time: Instant,
+}
+
+/// custom method
+impl TokenBucket {
+    /// create a full token bucket<br />
+    /// rate: tokens added per second, also the bucket capacity
+    pub fn new(rate: u64) -> Self {
+        // a bucket with zero rate would never refill
+        let rate = rate.max(1) as f64;
+        Self { rate, tokens: rate, last_time: Instant::now() }
+    }
+
+    /// refill tokens according to the elapsed time
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_time).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.last_time = now;
+    }
+
+    /// get the tokens required to take n, no more than the bucket capacity
+    fn need(&self, n: u64) -> f64 {
+        (n as f64).min(self.rate)
+    }
+
+    /// check whether n tokens can be taken now<br />
+    /// return Err: the time to wait until enough tokens are available
+    pub fn check(&mut self, n: u64, now: Instant) -> Result<(), Duration> {
+        self.refill(now);
+        let lack = self.need(n) - self.tokens;
+        if lack <= 0.0 { return Ok(()); }
+        Err(Duration::from_secs_f64(lack / self.rate))
+    }
+
+    /// take n tokens, tokens may become negative<br />
+    /// return the time to wait until the taken tokens are actually available
+    pub fn take(&mut self, n: u64, now: Instant) -> Duration {
+        self.refill(now);
+        self.tokens -= self.need(n);
+        if self.tokens >= 0.0 { return Duration::ZERO; }
+        Duration::from_secs_f64(-self.tokens / self.rate)
+    }
+}
@@ -0,0 +1,66 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::metrics::server_metrics::ServerMetrics;
+
+/// per connection counters
+#[derive(Default)]
+pub struct ConnMetrics {
+    /// the number of bytes received
+    pub recv_bytes: AtomicU64,
+    /// the number of bytes sent
+    pub send_bytes: AtomicU64,
+    /// the number of times data was received
+    pub recv_count: AtomicU64,
+    /// the number of times data was sent
+    pub send_count: AtomicU64,
+    /// the server to which this connection belongs, server counters will be updated at the same time
+    server: Option<Arc<ServerMetrics>>,
+}
+
+/// custom method
+impl ConnMetrics {
+    /// create connection counters that belong to a server
+    pub fn new(server: Arc<ServerMetrics>) -> Self {
+        Self { server: Some(server), ..Default::default() }
+    }
+
+    /// record once received data
+    pub fn add_recv(&self, len: usize) {
+        let len = u64::try_from(len).unwrap_or_default();
+        self.recv_bytes.fetch_add(len, Ordering::Relaxed);
+        self.recv_count.fetch_add(1, Ordering::Relaxed);
+        if let Some(server) = self.server.as_ref() {
+            server.add_recv(len);
+        }
+    }
+
+    /// record once sent data
+    pub fn add_send(&self, len: usize) {
+        let len = u64::try_from(len).unwrap_or_default();
+        self.send_bytes.fetch_add(len, Ordering::Relaxed);
+        self.send_count.fetch_add(1, Ordering::Relaxed);
+        if let Some(server) = self.server.as_ref() {
+            server.add_send(len);
+        }
+    }
+
+    /// get the number of bytes received
+    pub fn get_recv_bytes(&self) -> u64 {
+        self.recv_bytes.load(Ordering::Relaxed)
+    }
+
+    /// get the number of bytes sent
+    pub fn get_send_bytes(&self) -> u64 {
+        self.send_bytes.load(Ordering::Relaxed)
+    }
+
+    /// get the number of times data was received
+    pub fn get_recv_count(&self) -> u64 {
+        self.recv_count.load(Ordering::Relaxed)
+    }
+
+    /// get the number of times data was sent
+    pub fn get_send_count(&self) -> u64 {
+        self.send_count.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,2 @@
+pub mod conn_metrics;
+pub mod server_metrics;
\ No newline at end of file
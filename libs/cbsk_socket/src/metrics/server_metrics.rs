@@ -0,0 +1,92 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+/// server counters, shared by all connections of the server
+#[derive(Default)]
+pub struct ServerMetrics {
+    /// is the server listener bound
+    pub listening: AtomicBool,
+    /// the number of clients currently connected
+    pub clients: AtomicUsize,
+    /// the number of clients accepted since the server started
+    pub accepted: AtomicU64,
+    /// the number of bytes received from all clients
+    pub recv_bytes: AtomicU64,
+    /// the number of bytes sent to all clients
+    pub send_bytes: AtomicU64,
+    /// the number of times data was received from all clients
+    pub recv_count: AtomicU64,
+    /// the number of times data was sent to all clients
+    pub send_count: AtomicU64,
+}
+
+/// custom method
+impl ServerMetrics {
+    /// set the server listener state
+    pub fn set_listening(&self, listening: bool) {
+        self.listening.store(listening, Ordering::Release)
+    }
+
+    /// get is the server listener bound
+    pub fn is_listening(&self) -> bool {
+        self.listening.load(Ordering::Acquire)
+    }
+
+    /// a new client come in
+    pub fn client_conn(&self) {
+        self.clients.fetch_add(1, Ordering::AcqRel);
+        self.accepted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// the client disconnected
+    pub fn client_dis_conn(&self) {
+        // never drop below zero, even if dis_conn is reported more than once
+        let _ = self.clients.fetch_update(Ordering::AcqRel, Ordering::Acquire, |clients| {
+            Some(clients.saturating_sub(1))
+        });
+    }
+
+    /// get the number of clients currently connected
+    pub fn get_clients(&self) -> usize {
+        self.clients.load(Ordering::Acquire)
+    }
+
+    /// record once received data
+    pub fn add_recv(&self, len: u64) {
+        self.recv_bytes.fetch_add(len, Ordering::Relaxed);
+        self.recv_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// record once sent data
+    pub fn add_send(&self, len: u64) {
+        self.send_bytes.fetch_add(len, Ordering::Relaxed);
+        self.send_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// render counters in prometheus text exposition format<br />
+    /// name: server name, used as the name label of each sample
+    pub fn to_prometheus(&self, name: &str) -> String {
+        let name = escape_label(name);
+        let mut text = String::new();
+        let mut sample = |metric: &str, kind: &str, help: &str, value: u64| {
+            let _ = writeln!(text, "# HELP {metric} {help}");
+            let _ = writeln!(text, "# TYPE {metric} {kind}");
+            let _ = writeln!(text, "{metric}{{name=\"{name}\"}} {value}");
+        };
+
+        sample("cbsk_listening", "gauge", "whether the server listener is bound", u64::from(self.is_listening()));
+        sample("cbsk_clients", "gauge", "number of clients currently connected",
+               u64::try_from(self.get_clients()).unwrap_or_default());
+        sample("cbsk_accepted_total", "counter", "number of clients accepted", self.accepted.load(Ordering::Relaxed));
+        sample("cbsk_recv_bytes_total", "counter", "number of bytes received", self.recv_bytes.load(Ordering::Relaxed));
+        sample("cbsk_send_bytes_total", "counter", "number of bytes sent", self.send_bytes.load(Ordering::Relaxed));
+        sample("cbsk_recv_total", "counter", "number of times data was received", self.recv_count.load(Ordering::Relaxed));
+        sample("cbsk_send_total", "counter", "number of times data was sent", self.send_count.load(Ordering::Relaxed));
+        text
+    }
+}
+
+/// escape prometheus label value
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
@@ -0,0 +1,32 @@
+use std::any::Any;
+use std::sync::Arc;
+use crate::session::attrs::SessionAttrs;
+
+/// session connection id and attributes related trait
+pub trait AttrTrait {
+    /// get connection id, see [crate::session::next_conn_id]
+    fn get_conn_id(&self) -> u64;
+
+    /// get session attributes
+    fn get_attrs(&self) -> &SessionAttrs;
+
+    /// set session attribute, one value per type, return the old value of the same type
+    fn set_attr<T: Any + Send + Sync>(&self, value: T) -> Option<Arc<T>> {
+        self.get_attrs().set(value)
+    }
+
+    /// get session attribute
+    fn get_attr<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        self.get_attrs().get()
+    }
+
+    /// remove session attribute, return the removed value
+    fn remove_attr<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        self.get_attrs().remove()
+    }
+
+    /// get is the session attribute exists
+    fn has_attr<T: Any + Send + Sync>(&self) -> bool {
+        self.get_attrs().contains::<T>()
+    }
+}
@@ -0,0 +1,47 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// session attributes, one value per type<br />
+/// used to stash per-session state, example: authenticated device id
+#[derive(Default)]
+pub struct SessionAttrs {
+    /// attribute values, key is the value type
+    attrs: RwLock<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>,
+}
+
+/// custom method
+impl SessionAttrs {
+    /// set attribute, return the old value of the same type
+    pub fn set<T: Any + Send + Sync>(&self, value: T) -> Option<Arc<T>> {
+        self.set_arc(Arc::new(value))
+    }
+
+    /// set shared attribute, return the old value of the same type
+    pub fn set_arc<T: Any + Send + Sync>(&self, value: Arc<T>) -> Option<Arc<T>> {
+        let old = self.attrs.write().unwrap_or_else(PoisonError::into_inner).insert(TypeId::of::<T>(), value);
+        old.and_then(|old| old.downcast().ok())
+    }
+
+    /// get attribute
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        let attr = self.attrs.read().unwrap_or_else(PoisonError::into_inner).get(&TypeId::of::<T>()).cloned();
+        attr.and_then(|attr| attr.downcast().ok())
+    }
+
+    /// remove attribute, return the removed value
+    pub fn remove<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        let old = self.attrs.write().unwrap_or_else(PoisonError::into_inner).remove(&TypeId::of::<T>());
+        old.and_then(|old| old.downcast().ok())
+    }
+
+    /// get is the attribute exists
+    pub fn contains<T: Any + Send + Sync>(&self) -> bool {
+        self.attrs.read().unwrap_or_else(PoisonError::into_inner).contains_key(&TypeId::of::<T>())
+    }
+
+    /// remove all attributes
+    pub fn clear(&self) {
+        self.attrs.write().unwrap_or_else(PoisonError::into_inner).clear()
+    }
+}
@@ -0,0 +1,14 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub mod attrs;
+pub mod attr_trait;
+pub mod ping;
+
+/// the last connection id
+static CONN_ID: AtomicU64 = AtomicU64::new(0);
+
+/// get next connection id<br />
+/// connection ids increase monotonically from 1 and are unique within the process
+pub fn next_conn_id() -> u64 {
+    CONN_ID.fetch_add(1, Ordering::Relaxed) + 1
+}
@@ -0,0 +1,94 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::config::keep_alive::KeepAlive;
+
+/// what to do after the keepalive check
+#[derive(Debug, PartialEq, Eq)]
+pub enum PingTick {
+    /// nothing to do
+    Idle,
+    /// send a ping with this payload
+    Ping(Vec<u8>),
+    /// the pong is not received in time, the connection should be closed
+    TimeOut,
+}
+
+/// the keepalive ping of one connection
+#[derive(Debug)]
+struct PingInner {
+    /// the sequence of the last ping, used as the ping payload
+    seq: u64,
+    /// the ping waiting for the pong
+    pending: Option<(u64, Instant)>,
+    /// the time the last ping was sent, or the time the connection started
+    last_ping: Instant,
+    /// the last measured round trip time
+    latency: Option<Duration>,
+}
+
+/// custom method
+impl PingInner {
+    /// create the state of a new connection
+    fn new() -> Self {
+        Self { seq: 0, pending: None, last_ping: Instant::now(), latency: None }
+    }
+}
+
+/// per connection ping/pong state, see [KeepAlive]
+#[derive(Debug)]
+pub struct PingState {
+    /// the ping state
+    inner: Mutex<PingInner>,
+}
+
+/// support default
+impl Default for PingState {
+    fn default() -> Self {
+        Self { inner: Mutex::new(PingInner::new()) }
+    }
+}
+
+/// custom method
+impl PingState {
+    /// reset the state, used when a new connection is started
+    pub fn reset(&self) {
+        *self.lock() = PingInner::new();
+    }
+
+    /// check keepalive, should be called periodically, at least every [KeepAlive::check_time]
+    pub fn tick(&self, conf: &KeepAlive, now: Instant) -> PingTick {
+        let mut inner = self.lock();
+        if let Some((_, time)) = inner.pending {
+            if now.saturating_duration_since(time) >= conf.pong_time_out { return PingTick::TimeOut; }
+            return PingTick::Idle;
+        }
+        if now.saturating_duration_since(inner.last_ping) < conf.interval { return PingTick::Idle; }
+
+        inner.seq = inner.seq.wrapping_add(1);
+        inner.pending = Some((inner.seq, now));
+        inner.last_ping = now;
+        PingTick::Ping(inner.seq.to_be_bytes().to_vec())
+    }
+
+    /// a pong is received, record the latency if it is the reply of the keepalive ping<br />
+    /// return true if it is the reply of the keepalive ping
+    pub fn pong(&self, payload: &[u8], now: Instant) -> bool {
+        let mut inner = self.lock();
+        let Some((seq, time)) = inner.pending else { return false; };
+        if payload != seq.to_be_bytes() { return false; }
+
+        inner.pending = None;
+        inner.latency = Some(now.saturating_duration_since(time));
+        true
+    }
+
+    /// get the last measured round trip time, None if no pong has been received
+    pub fn get_latency(&self) -> Option<Duration> {
+        self.lock().latency
+    }
+
+    /// lock the inner state, the state is still usable if the lock is poisoned
+    fn lock(&self) -> std::sync::MutexGuard<'_, PingInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
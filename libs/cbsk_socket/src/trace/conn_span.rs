@@ -0,0 +1,97 @@
+use std::fmt::Display;
+use std::future::Future;
+use std::marker::PhantomData;
+#[cfg(feature = "tracing")]
+use cbsk_base::tracing;
+
+/// connection span<br />
+/// if the tracing feature is enabled, it is a tracing span carrying connection kind, name, peer addr and connection id,
+/// read loop and callbacks run in this span, connect/disconnect/reconnect are recorded as structured events<br />
+/// if the tracing feature is disabled, all methods do nothing
+#[derive(Clone)]
+pub struct ConnSpan {
+    /// tracing span
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+/// entered connection span guard, the span will be exited when the guard is dropped
+pub struct ConnSpanGuard<'a> {
+    /// tracing entered guard
+    #[cfg(feature = "tracing")]
+    _entered: tracing::span::Entered<'a>,
+    /// keep lifetime without tracing feature
+    _span: PhantomData<&'a ConnSpan>,
+}
+
+/// support default
+impl Default for ConnSpan {
+    fn default() -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            span: tracing::Span::none(),
+        }
+    }
+}
+
+/// custom method
+impl ConnSpan {
+    /// create connection span<br />
+    /// kind: connection kind, example: tcp client<br />
+    /// name: business name<br />
+    /// peer: peer addr or url<br />
+    /// conn_id: connection id, see [crate::session::next_conn_id]
+    pub fn new(kind: &'static str, name: &str, peer: impl Display, conn_id: u64) -> Self {
+        #[cfg(feature = "tracing")]
+        {
+            Self { span: tracing::info_span!("cbsk_conn", kind, name, peer = %peer, conn_id) }
+        }
+        #[cfg(not(feature = "tracing"))]
+        {
+            let _ = (kind, name, peer, conn_id);
+            Self::default()
+        }
+    }
+
+    /// run future in this span
+    pub fn instrument<F: Future>(&self, future: F) -> impl Future<Output=F::Output> + use<F> {
+        #[cfg(feature = "tracing")]
+        {
+            tracing::Instrument::instrument(future, self.span.clone())
+        }
+        #[cfg(not(feature = "tracing"))]
+        {
+            future
+        }
+    }
+
+    /// enter this span, used for blocking code
+    pub fn enter(&self) -> ConnSpanGuard<'_> {
+        ConnSpanGuard {
+            #[cfg(feature = "tracing")]
+            _entered: self.span.enter(),
+            _span: PhantomData,
+        }
+    }
+
+    /// record connected event
+    pub fn conn(&self) {
+        #[cfg(feature = "tracing")]
+        tracing::info!(parent: &self.span, event = "conn", "connected");
+    }
+
+    /// record disconnected event
+    pub fn dis_conn(&self) {
+        #[cfg(feature = "tracing")]
+        tracing::info!(parent: &self.span, event = "dis_conn", "disconnected");
+    }
+
+    /// record reconnect event<br />
+    /// num: number of try connect
+    pub fn re_conn(&self, num: i32) {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(parent: &self.span, event = "re_conn", num, "reconnect");
+        #[cfg(not(feature = "tracing"))]
+        let _ = num;
+    }
+}
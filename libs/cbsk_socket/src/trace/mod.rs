@@ -0,0 +1 @@
+pub mod conn_span;
\ No newline at end of file
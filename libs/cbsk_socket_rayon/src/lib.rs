@@ -1,5 +1,11 @@
 #[cfg(feature = "cbsk_socket")]
 pub use cbsk_socket;
+#[cfg(feature = "tungstenite")]
+pub use tungstenite;
 
 #[cfg(any(feature = "tcp_server", feature = "tcp_client"))]
-pub mod tcp;
\ No newline at end of file
+pub mod tcp;
+// tungstenite::Error is large, but it is returned as is, the same as the tokio websocket
+#[allow(clippy::result_large_err)]
+#[cfg(any(feature = "ws_server", feature = "ws_client"))]
+pub mod ws;
\ No newline at end of file
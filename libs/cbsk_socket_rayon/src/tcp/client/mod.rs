@@ -5,8 +5,10 @@ use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
 use cbsk_base::{anyhow, log};
 use cbsk_base::parking_lot::RwLock;
+use cbsk_socket::session;
 use cbsk_socket::tcp::client::config::TcpClientConfig;
 use cbsk_socket::tcp::common::time_trait::TimeTrait;
+use cbsk_socket::trace::conn_span::ConnSpan;
 use cbsk_timer::timer::Timer;
 use crate::tcp::client::callback::TcpClientCallBack;
 use crate::tcp::client::state::TcpState;
@@ -50,6 +52,8 @@ pub struct TcpClient {
     pub(crate) next_buf: Arc<RwLock<Vec<u8>>>,
     /// tcp client state
     pub(crate) state: Arc<RwLock<TcpState>>,
+    /// the last connection span, a new span will be created each time the connection is successful
+    span: Arc<RwLock<ConnSpan>>,
 }
 
 /// support tcp time trait
@@ -124,6 +128,9 @@ impl TcpClient {
 
         // as long as shutdown is called, tcp_client will be left blank directly
         if write.tcp_stream.is_some() {
+            let span = self.span.read().clone();
+            let _span = span.enter();
+            span.dis_conn();
             self.cb.dis_conn();
         }
         write.set_none();
@@ -152,6 +159,7 @@ impl TcpClient {
             buf: RwLock::new(Vec::with_capacity(1)).into(),
             next_buf: RwLock::new(Vec::with_capacity(buf_len)).into(),
             state: Arc::new(RwLock::default()),
+            span: Arc::new(RwLock::default()),
         }
     }
 
@@ -186,7 +194,13 @@ impl TcpClient {
         let diff = u128::try_from(Self::now() - state.last_re_time).unwrap_or_default();
         if diff < self.conf.reconn.time.as_millis() { return; }
         state.re_num = state.re_num.saturating_add(1);
-        self.cb.re_conn(state.re_num);
+        let span = self.span.read().clone();
+        span.re_conn(state.re_num);
+        // the span of the last connection is only entered for re_conn, the new connection has its own span
+        {
+            let _span = span.enter();
+            self.cb.re_conn(state.re_num);
+        }
         drop(state);
         self.conn_exec();
     }
@@ -209,6 +223,10 @@ impl TcpClient {
             };
 
         self.tcp_client.write().set_stream(ts);
+        let span = ConnSpan::new("tcp client", self.conf.name.as_str(), self.conf.addr, session::next_conn_id());
+        *self.span.write() = span.clone();
+        let _span = span.enter();
+        span.conn();
         self.cb.conn();
     }
 
@@ -230,6 +248,8 @@ impl TcpClient {
 
     /// read data from tcp server
     pub(crate) fn read(&self) {
+        let span = self.span.read().clone();
+        let _span = span.enter();
         let mut state = self.state.write();
         state.reading = true;
         if let Err(e) = self.try_read() {
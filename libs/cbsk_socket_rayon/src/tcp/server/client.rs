@@ -4,8 +4,12 @@ use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
 use cbsk_base::{anyhow, log};
 use cbsk_base::parking_lot::RwLock;
+use cbsk_socket::session;
+use cbsk_socket::session::attr_trait::AttrTrait;
+use cbsk_socket::session::attrs::SessionAttrs;
 use cbsk_socket::tcp::common::time_trait::TimeTrait;
 use cbsk_socket::tcp::server::config::TcpServerConfig;
+use cbsk_socket::trace::conn_span::ConnSpan;
 use crate::tcp::common::tcp_write_trait::TcpWriteTrait;
 use crate::tcp::server::callback::TcpServerCallBack;
 use crate::tcp::server::TcpServer;
@@ -14,8 +18,14 @@ use crate::tcp::server::TcpServer;
 pub struct TcpServerClient {
     /// tcp client addr
     pub addr: SocketAddr,
+    /// connection id, see [session::next_conn_id]
+    pub conn_id: u64,
     /// internal log name
     pub log_head: String,
+    /// connection span, read and callbacks run in this span
+    pub span: ConnSpan,
+    /// session attributes, see [AttrTrait]
+    pub attrs: SessionAttrs,
     /// tcp server business callback
     pub cb: Arc<Box<dyn TcpServerCallBack>>,
     /// tcp server config
@@ -44,6 +54,17 @@ pub struct TcpServerClient {
     pub(crate) connecting: Arc<AtomicBool>,
 }
 
+/// support session attr trait
+impl AttrTrait for TcpServerClient {
+    fn get_conn_id(&self) -> u64 {
+        self.conn_id
+    }
+
+    fn get_attrs(&self) -> &SessionAttrs {
+        &self.attrs
+    }
+}
+
 /// support tcp time trait
 impl TimeTrait for TcpServerClient {
     fn set_recv_time(&self, time: i64) {
@@ -90,9 +111,13 @@ impl TcpServerClient {
     /// create tcp server client
     pub fn new(addr: SocketAddr, ts: &TcpServer, tcp_client: TcpStream) -> Self {
         let log_head = format!("{} tcp client[{}]", ts.conf.name, addr);
+        let conn_id = session::next_conn_id();
         Self {
             addr,
+            conn_id,
             log_head,
+            span: ConnSpan::new("tcp server client", ts.conf.name.as_str(), addr, conn_id),
+            attrs: SessionAttrs::default(),
             cb: ts.cb.clone(),
             conf: ts.conf.clone(),
             recv_time: AtomicI64::new(Self::now()).into(),
@@ -122,6 +147,7 @@ impl TcpServerClient {
 
     /// read data from tcp client
     pub(crate) fn read(&self, tc: Arc<Self>) {
+        let _span = self.span.enter();
         self.reading.store(true, Ordering::Relaxed);
         if let Err(e) = self.try_read(tc.clone()) {
             if self.conf.log {
@@ -130,6 +156,7 @@ impl TcpServerClient {
             #[cfg(feature = "debug_mode")]
             log::warn!("{} read err", self.log_head);
             self.shutdown();
+            self.span.dis_conn();
             self.cb.dis_conn(tc);
         }
         self.reading.store(false, Ordering::Release);
@@ -199,6 +226,8 @@ impl TcpServerClient {
 
             // tcp read timeout, directly assuming that tcp has been disconnected
             self.shutdown();
+            let _span = self.span.enter();
+            self.span.dis_conn();
             self.cb.dis_conn(tc);
         }
     }
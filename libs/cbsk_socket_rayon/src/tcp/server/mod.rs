@@ -81,10 +81,12 @@ impl TcpServer {
             log::error!("set read time out fail: {e:?}");
         }
         let tc = Arc::new(TcpServerClient::new(addr, self, ts));
+        tc.span.conn();
         client_timer::TcpServerClientTimer::new(tc.clone()).start();
         #[cfg(feature = "debug_mode")]
         log::info!("{} add to tcp server client",tc.log_head);
-        self.cb.conn(tc);
+        let _span = tc.span.enter();
+        self.cb.conn(tc.clone());
         Ok(())
     }
 }
@@ -0,0 +1,68 @@
+use cbsk_base::log;
+use crate::ws::client::handshake::WsHandshake;
+pub use tungstenite::error::CapacityError;
+pub use tungstenite::protocol::frame::coding::CloseCode;
+pub use tungstenite::protocol::frame::Frame;
+pub use tungstenite::protocol::CloseFrame;
+pub use tungstenite::{Bytes, Utf8Bytes};
+
+/// websocket connect and read data callback
+pub trait WsClientCallBack: Send + Sync + 'static {
+    /// connect websocket server success will call this method<br />
+    /// handshake: the selected subprotocol and response headers of websocket server
+    fn conn(&self, handshake: WsHandshake) {
+        log::info!("connect websocket server success, subprotocol is {:?}", handshake.protocol);
+    }
+
+    /// this method will be called when the websocket service is disconnected<br />
+    /// close: the close code and reason received from the websocket server, None if the connection is closed without close frame
+    fn dis_conn(&self, close: Option<CloseFrame>) {
+        log::info!("disconnect websocket server, close: {close:?}");
+    }
+
+    /// connect websocket server fail and try connect server will call this method<br />
+    /// num: number of try connect
+    fn re_conn(&self, num: i32) {
+        log::info!("re connect to websocket server, re num is {num}");
+    }
+
+    /// websocket client recv websocket server text data will call this method<br />
+    /// text: websocket server text data
+    fn recv_text(&self, text: Utf8Bytes);
+
+    /// websocket client recv websocket server binary data will call this method<br />
+    /// in general, you can ignore this data, if server not send binary
+    fn recv_binary(&self, binary: Bytes) {
+        log::warn!("recv websocket server binary data: [{binary:?}]");
+    }
+
+    /// websocket client recv websocket server ping data will call this method<br />
+    /// the pong is replied automatically, in general, you can ignore this data
+    fn recv_ping(&self, ping: Bytes) {
+        log::warn!("recv websocket server ping data: [{ping:?}]");
+    }
+
+    /// websocket client recv websocket server pong data will call this method<br />
+    /// in general, you can ignore this data, if server not send pong
+    fn recv_pong(&self, pong: Bytes) {
+        log::warn!("recv websocket server pong data: [{pong:?}]");
+    }
+
+    /// websocket client recv websocket server close data will call this method<br />
+    /// in general, you can ignore this data, if server not send close
+    fn recv_close(&self, close: Option<CloseFrame>) {
+        log::warn!("recv websocket server close data: [{close:?}]");
+    }
+
+    /// websocket client recv websocket server frame data will call this method<br />
+    /// in general, you can ignore this data, if server not send frame
+    fn recv_frame(&self, frame: Frame) {
+        log::warn!("recv websocket server frame data: [{frame:?}]");
+    }
+
+    /// the websocket server sent a message or frame larger than the limits of [crate::ws::client::config::WsClientConfig::ws_conf]<br />
+    /// after this method, a close with code 1009 is sent and the connection will be closed
+    fn capacity_exceeded(&self, err: CapacityError) {
+        log::warn!("websocket server exceeded the capacity: {err}");
+    }
+}
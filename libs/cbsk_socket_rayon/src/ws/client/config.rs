@@ -0,0 +1,127 @@
+use std::time::Duration;
+use cbsk_socket::config::keep_alive::KeepAlive;
+use cbsk_socket::config::re_conn::SocketReConn;
+pub use tungstenite::protocol::WebSocketConfig;
+
+/// the function to refresh request headers before each connect attempt
+pub type RefreshHeadersFn = Box<dyn Fn() -> Vec<(String, String)> + Sync + Send>;
+
+/// websocket client config
+pub struct WsClientConfig {
+    /// name, used for log printing
+    pub name: String,
+    /// ws url, only ws url is supported<br />
+    /// example: ws://127.0.0.1:8080
+    pub ws_url: String,
+    /// internal log name, used for log printing
+    pub log_head: String,
+    /// websocket connect and handshake timeout
+    pub conn_time_out: Duration,
+    /// websocket sockets need to be reconnect
+    pub(crate) reconn: SocketReConn,
+    /// custom request headers, such as Authorization or Cookie
+    pub headers: Vec<(String, String)>,
+    /// the subprotocols requested in order of preference, sent by Sec-WebSocket-Protocol header
+    pub protocols: Vec<String>,
+    /// called before each connect attempt, the returned headers override the same name headers<br />
+    /// can be used to refresh the auth token before reconnect
+    pub refresh_headers: Option<RefreshHeadersFn>,
+    /// ping/pong keepalive, None is disabled
+    pub keep_alive: Option<KeepAlive>,
+    /// how long to wait for the close reply of the websocket server when closing the connection
+    pub close_time_out: Duration,
+    /// how long to wait for the tcp stream to be writable when sending to the websocket server
+    pub write_time_out: Duration,
+    /// websocket protocol config, such as max message size, max frame size and write buffer sizes
+    pub ws_conf: WebSocketConfig,
+}
+
+/// custom method
+impl WsClientConfig {
+    /// create a websocket client config<br />
+    /// conn_time_out default 10 secs<br />
+    /// close_time_out default 3 secs<br />
+    /// write_time_out default 10 secs
+    pub fn new(name: String, ws_url: String, reconn: SocketReConn) -> Self {
+        let log_head = format!("{}[{}]", name, ws_url);
+        Self {
+            name,
+            ws_url,
+            log_head,
+            conn_time_out: Duration::from_secs(10),
+            reconn,
+            headers: Vec::new(),
+            protocols: Vec::new(),
+            refresh_headers: None,
+            keep_alive: None,
+            close_time_out: Duration::from_secs(3),
+            write_time_out: Duration::from_secs(10),
+            ws_conf: WebSocketConfig::default(),
+        }
+    }
+
+    /// set websocket connect and handshake timeout
+    pub fn set_conn_time_out(mut self, time_out: Duration) -> Self {
+        self.conn_time_out = time_out;
+        self
+    }
+
+    /// add custom request header, such as Authorization or Cookie
+    pub fn set_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// set the subprotocols requested in order of preference<br />
+    /// the subprotocol selected by websocket server can be obtained in [crate::ws::client::callback::WsClientCallBack::conn]
+    pub fn set_protocols(mut self, protocols: Vec<String>) -> Self {
+        self.protocols = protocols;
+        self
+    }
+
+    /// set the function to refresh request headers before each connect attempt
+    pub fn set_refresh_headers(mut self, f: impl Fn() -> Vec<(String, String)> + Sync + Send + 'static) -> Self {
+        self.refresh_headers = Some(Box::new(f));
+        self
+    }
+
+    /// set ping/pong keepalive<br />
+    /// if the websocket server does not reply pong in time, the connection will be closed and reconnected
+    pub fn set_keep_alive(mut self, keep_alive: KeepAlive) -> Self {
+        self.keep_alive = Some(keep_alive);
+        self
+    }
+
+    /// set how long to wait for the close reply of the websocket server<br />
+    /// if the websocket server does not reply close in time, the connection is closed directly
+    pub fn set_close_time_out(mut self, close_time_out: Duration) -> Self {
+        self.close_time_out = close_time_out;
+        self
+    }
+
+    /// set how long to wait for the tcp stream to be writable when sending<br />
+    /// if the websocket server does not read in time, the send returns [std::io::ErrorKind::TimedOut]
+    pub fn set_write_time_out(mut self, write_time_out: Duration) -> Self {
+        self.write_time_out = write_time_out;
+        self
+    }
+
+    /// set websocket protocol config
+    pub fn set_ws_conf(mut self, ws_conf: WebSocketConfig) -> Self {
+        self.ws_conf = ws_conf;
+        self
+    }
+
+    /// set the max size of a message, None is unlimited, default is 64 MiB<br />
+    /// the connection will be closed if the websocket server sends a larger message, see [crate::ws::client::callback::WsClientCallBack::capacity_exceeded]
+    pub fn set_max_message_size(mut self, max_message_size: Option<usize>) -> Self {
+        self.ws_conf = self.ws_conf.max_message_size(max_message_size);
+        self
+    }
+
+    /// set the max size of a frame, None is unlimited, default is 16 MiB
+    pub fn set_max_frame_size(mut self, max_frame_size: Option<usize>) -> Self {
+        self.ws_conf = self.ws_conf.max_frame_size(max_frame_size);
+        self
+    }
+}
@@ -0,0 +1,343 @@
+use std::io;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use cbsk_base::{anyhow, log};
+use cbsk_base::parking_lot::RwLock;
+use cbsk_socket::session;
+use cbsk_socket::session::ping::{PingState, PingTick};
+use cbsk_socket::trace::conn_span::ConnSpan;
+use cbsk_timer::timer::Timer;
+use tungstenite::client::IntoClientRequest;
+use tungstenite::http::{HeaderName, HeaderValue};
+use tungstenite::protocol::frame::coding::CloseCode;
+use tungstenite::protocol::frame::Frame;
+use tungstenite::protocol::CloseFrame;
+use tungstenite::Message;
+use crate::ws::client::callback::WsClientCallBack;
+use crate::ws::client::config::WsClientConfig;
+use crate::ws::client::handshake::WsHandshake;
+use crate::ws::client::state::WsState;
+use crate::ws::close::WsClose;
+use crate::ws::ws_stream::{self, WsStream};
+use crate::ws::ws_write_trait::WsWriteTrait;
+
+pub mod callback;
+pub mod config;
+pub mod handshake;
+pub mod state;
+mod timer;
+pub mod timer_state;
+
+/// websocket client
+#[derive(Clone)]
+pub struct WsClient {
+    /// websocket config
+    pub conf: Arc<WsClientConfig>,
+    /// websocket client business callback
+    pub cb: Arc<Box<dyn WsClientCallBack>>,
+    /// websocket client read and write
+    ws: Arc<RwLock<Option<WsStream>>>,
+    /// websocket client state
+    pub(crate) state: Arc<RwLock<WsState>>,
+    /// the last connection span, a new span will be created each time the connection is successful
+    span: Arc<RwLock<ConnSpan>>,
+    /// keepalive ping state, reset each time the connection is successful
+    ping: Arc<PingState>,
+    /// closing handshake state, reset each time the connection is successful
+    ws_close: Arc<WsClose>,
+}
+
+/// support ws write trait
+impl WsWriteTrait for WsClient {
+    fn get_log_head(&self) -> &str {
+        self.conf.log_head.as_str()
+    }
+
+    fn get_ws_close(&self) -> Option<&WsClose> {
+        Some(self.ws_close.as_ref())
+    }
+
+    fn try_send(&self, msg: Message) -> tungstenite::Result<()> {
+        let mut ws = self.ws.write();
+        let ws = ws.as_mut().ok_or_else(|| {
+            io::Error::from(io::ErrorKind::NotConnected)
+        })?;
+        ws_stream::send(ws, msg, self.conf.write_time_out)
+    }
+
+    fn try_send_frames(&self, frames: impl IntoIterator<Item = Frame>) -> tungstenite::Result<()> {
+        let mut frames = frames.into_iter();
+        // the first frame is taken before holding the write
+        let Some(first) = frames.next() else { return Ok(()); };
+        let mut ws = self.ws.write();
+        let ws = ws.as_mut().ok_or_else(|| {
+            io::Error::from(io::ErrorKind::NotConnected)
+        })?;
+        ws_stream::write(ws, Message::Frame(first))?;
+        for frame in frames {
+            ws_stream::write(ws, Message::Frame(frame))?;
+        }
+        ws_stream::flush(ws, self.conf.write_time_out)
+    }
+}
+
+/// custom method
+impl WsClient {
+    /// stop websocket server connect<br />
+    /// will close websocket connection with close code 1000 and will not new connection, see [WsClient::shutdown]
+    pub fn stop(&self) {
+        self.conf.reconn.enable.store(false, Ordering::Release);
+        self.shutdown();
+    }
+
+    /// notify websocket to re connect<br />
+    /// will shutdown websocket connection, if [`WsClientConfig`] reconn is disable<br />
+    /// will shutdown and create new websocket connection,if [`WsClientConfig`] reconn is enable, see [WsClient::shutdown]
+    pub fn re_conn(&self) {
+        self.shutdown();
+    }
+
+    /// send close to websocket server with close code 1000 without waiting for the reply, so it can be called in the recv callbacks<br />
+    /// the read ends when the close reply is received, or close_time_out is reached, use [WsWriteTrait::try_close] to wait for the reply
+    fn shutdown(&self) {
+        if !self.is_connected() { return; }
+        match self.try_send_close(Some(CloseFrame { code: CloseCode::Normal, reason: "".into() })) {
+            Ok(()) => { self.ws_close.sent(); }
+            Err(e) => {
+                log::error!("{} close websocket error: {e:?}",self.conf.log_head);
+                self.dis_conn();
+            }
+        }
+    }
+
+    /// shutdown websocket server connect directly and call dis_conn
+    fn dis_conn(&self) {
+        // as long as dis_conn is called, the websocket will be left blank directly
+        let ws = cbsk_base::match_some_return!(self.ws.write().take());
+        if let Err(e) = ws_stream::shutdown(&ws) {
+            log::error!("shutdown websocket error: {e:?}");
+        }
+
+        let span = self.span.read().clone();
+        let _span = span.enter();
+        span.dis_conn();
+        self.cb.dis_conn(self.ws_close.get_recv());
+    }
+
+    /// get has the websocket server connection been success
+    pub fn is_connected(&self) -> bool {
+        self.ws.read().is_some()
+    }
+
+    /// get the last measured round trip time of keepalive ping<br />
+    /// None if keepalive is disabled or no pong has been received
+    pub fn get_latency(&self) -> Option<Duration> {
+        self.ping.get_latency()
+    }
+}
+
+/// websocket read logic
+impl WsClient {
+    /// create websocket client<br />
+    /// just create data, if you want to read data to recv method, you should be call start method
+    pub fn new<C: WsClientCallBack>(conf: Arc<WsClientConfig>, cb: C) -> Self {
+        let ws_close = Arc::new(WsClose::new(conf.close_time_out));
+        Self {
+            conf,
+            cb: Arc::new(Box::new(cb)),
+            ws: Arc::new(RwLock::default()),
+            state: Arc::new(RwLock::default()),
+            span: Arc::new(RwLock::default()),
+            ping: Arc::new(PingState::default()),
+            ws_close,
+        }
+    }
+
+    /// start websocket client
+    pub fn start(&self) {
+        timer::WsClientTimer::new(self.clone()).start();
+    }
+
+    /// conn websocket server
+    pub(crate) fn conn(&self) {
+        let mut state = self.state.write();
+        state.connecting = true;
+        if state.first {
+            state.first = false;
+            drop(state);
+            self.conn_exec();
+            return;
+        }
+
+        // not first conn, check is re conn
+        if !self.conf.reconn.enable.load(Ordering::Acquire) { return; }
+
+        // re conn
+        if state.last_re_time.elapsed() < self.conf.reconn.time { return; }
+        state.re_num = state.re_num.saturating_add(1);
+        let span = self.span.read().clone();
+        span.re_conn(state.re_num);
+        // the span of the last connection is only entered for re_conn, the new connection has its own span
+        {
+            let _span = span.enter();
+            self.cb.re_conn(state.re_num);
+        }
+        drop(state);
+        self.conn_exec();
+    }
+
+    /// exec connection to websocket server
+    fn conn_exec(&self) {
+        self.state.write().last_re_time = Instant::now();
+        let (ws, handshake) =
+            match self.try_conn() {
+                Ok(ws) => { ws }
+                Err(e) => {
+                    log::error!("{} websocket server connect error: {e:?}",self.conf.log_head);
+                    if self.conf.reconn.enable.load(Ordering::Acquire) {
+                        log::info!("{} websocket service will reconnect in {:?}",self.conf.log_head,self.conf.reconn.time);
+                    }
+                    return;
+                }
+            };
+
+        self.ws_close.reset();
+        *self.ws.write() = Some(ws);
+        self.ping.reset();
+        let span = ConnSpan::new("websocket client", self.conf.name.as_str(), self.conf.ws_url.as_str(), session::next_conn_id());
+        *self.span.write() = span.clone();
+        let _span = span.enter();
+        span.conn();
+        self.cb.conn(handshake);
+    }
+
+    /// try connect websocket server, only ws url is supported
+    fn try_conn(&self) -> anyhow::Result<(WsStream, WsHandshake)> {
+        log::info!("{} try connect to websocket server",self.conf.log_head);
+        let mut request = self.conf.ws_url.as_str().into_client_request()?;
+        let headers = request.headers_mut();
+        for (name, value) in self.conf.headers.iter() {
+            headers.append(HeaderName::from_bytes(name.as_bytes())?, HeaderValue::from_str(value)?);
+        }
+        if !self.conf.protocols.is_empty() {
+            headers.insert("Sec-WebSocket-Protocol", HeaderValue::from_str(self.conf.protocols.join(", ").as_str())?);
+        }
+        // refreshed headers override the same name headers
+        if let Some(refresh_headers) = self.conf.refresh_headers.as_ref() {
+            for (name, value) in refresh_headers() {
+                headers.insert(HeaderName::from_bytes(name.as_bytes())?, HeaderValue::from_str(value.as_str())?);
+            }
+        }
+
+        let uri = request.uri();
+        if uri.scheme_str() != Some("ws") { anyhow::bail!("only ws url is supported, but url is {uri}"); }
+        let host = uri.host().ok_or_else(|| anyhow::anyhow!("ws url {uri} has no host"))?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let addr = (host, uri.port_u16().unwrap_or(80)).to_socket_addrs()?.next()
+            .ok_or_else(|| anyhow::anyhow!("ws url {uri} host can not be resolved"))?;
+        let tcp_stream = TcpStream::connect_timeout(&addr, self.conf.conn_time_out)?;
+        tcp_stream.set_read_timeout(Some(self.conf.conn_time_out))?;
+        tcp_stream.set_write_timeout(Some(self.conf.conn_time_out))?;
+
+        let (ws, response) = tungstenite::client::client_with_config(request, tcp_stream, Some(self.conf.ws_conf))
+            .map_err(|e| anyhow::anyhow!("{e}"))?;
+        // after the handshake, read and write are nonblocking, so that the write is not blocked by the read
+        ws.get_ref().set_read_timeout(None)?;
+        ws.get_ref().set_write_timeout(None)?;
+        ws.get_ref().set_nonblocking(true)?;
+
+        self.state.write().re_num = 0;
+        log::info!("{} websocket server connect success",self.conf.log_head);
+        Ok((ws, WsHandshake::new(&response)))
+    }
+
+    /// read data from websocket server
+    pub(crate) fn read(&self) {
+        let span = self.span.read().clone();
+        let _span = span.enter();
+        self.state.write().reading = true;
+        let closed =
+            match self.try_read() {
+                Ok(closed) => { closed }
+                Err(e) => {
+                    // if the websocket is not closed, print the log.
+                    // otherwise, it is considered as actively closing the connection and there is no need to print the log
+                    if self.is_connected() {
+                        log::error!("{} websocket server read data error: {e:?}",self.conf.log_head);
+                    }
+                    true
+                }
+            };
+        if closed {
+            // read error or closed, directly assume that the websocket has been closed
+            self.dis_conn();
+            log::info!("{} websocket server shutdown",self.conf.log_head);
+        }
+        self.state.write().reading = false;
+    }
+
+    /// try read all received data from websocket server<br />
+    /// return true if the websocket connection has been closed
+    fn try_read(&self) -> anyhow::Result<bool> {
+        // the close is not replied in time, end the connection directly
+        if self.ws_close.is_ended() { return Ok(true); }
+        self.keep_alive()?;
+
+        loop {
+            // the lock must be released before calling back, so that data can be sent in the callback
+            let msg = {
+                let mut ws = self.ws.write();
+                let ws = ws.as_mut().ok_or_else(|| { anyhow::anyhow!("websocket server not connection") })?;
+                ws_stream::read(ws)
+            };
+            let msg =
+                match self.check_capacity(msg) {
+                    Ok(msg) => { cbsk_base::match_some_return!(msg,Ok(false)) }
+                    // the closing handshake is completed
+                    Err(e) if ws_stream::is_closed(&e) => { return Ok(true); }
+                    Err(e) => { return Err(e.into()); }
+                };
+            match msg {
+                Message::Text(text) => { self.cb.recv_text(text) }
+                Message::Binary(binary) => { self.cb.recv_binary(binary) }
+                Message::Ping(ping) => { self.cb.recv_ping(ping) }
+                Message::Pong(pong) => {
+                    // the reply of keepalive ping is not passed to recv_pong
+                    if self.ping.pong(pong.as_ref(), Instant::now()) { continue; }
+                    self.cb.recv_pong(pong)
+                }
+                Message::Close(close) => {
+                    // the close reply is sent automatically, the next read will return closed
+                    self.ws_close.recv(close.clone());
+                    self.cb.recv_close(close)
+                }
+                Message::Frame(frame) => { self.cb.recv_frame(frame) }
+            }
+        }
+    }
+
+    /// if the websocket server exceeds the message size limits, call capacity_exceeded and send close with 1009
+    fn check_capacity(&self, msg: tungstenite::Result<Option<Message>>) -> tungstenite::Result<Option<Message>> {
+        if let Err(tungstenite::Error::Capacity(e)) = msg.as_ref() {
+            self.cb.capacity_exceeded(*e);
+            let close = CloseFrame { code: CloseCode::Size, reason: e.to_string().into() };
+            if let Err(e) = self.try_send_close(Some(close)) {
+                log::warn!("{} send websocket server close error: {e:?}",self.conf.log_head);
+            }
+        }
+        msg
+    }
+
+    /// send keepalive ping, return Err if the pong is not received in time
+    fn keep_alive(&self) -> anyhow::Result<()> {
+        let keep_alive = cbsk_base::match_some_return!(self.conf.keep_alive.as_ref(),Ok(()));
+        match self.ping.tick(keep_alive, Instant::now()) {
+            PingTick::Idle => {}
+            PingTick::Ping(ping) => { self.try_send_ping(ping)? }
+            PingTick::TimeOut => { return Err(anyhow::anyhow!("websocket server pong time out {:?}", keep_alive.pong_time_out)); }
+        }
+        Ok(())
+    }
+}
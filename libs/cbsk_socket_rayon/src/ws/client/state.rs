@@ -0,0 +1,29 @@
+use std::time::Instant;
+
+/// websocket client state
+pub struct WsState {
+    /// is first conn to websocket server
+    /// default is true
+    pub first: bool,
+    /// re connection websocket server num
+    pub re_num: i32,
+    /// last re connection websocket server time
+    pub last_re_time: Instant,
+    /// the websocket client is reading
+    pub reading: bool,
+    /// the websocket client is connecting
+    pub connecting: bool,
+}
+
+/// support default
+impl Default for WsState {
+    fn default() -> Self {
+        Self {
+            first: true,
+            re_num: 0,
+            last_re_time: Instant::now(),
+            reading: false,
+            connecting: false,
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, Ordering};
+use cbsk_base::parking_lot::RwLock;
+use cbsk_timer::timer::Timer;
+use crate::ws::client::timer_state::TimerState;
+use crate::ws::ws_write_trait::WsWriteTrait;
+
+/// websocket client timer
+pub struct WsClientTimer {
+    /// websocket client
+    pub ws_client: super::WsClient,
+    /// websocket client timer is need end?
+    pub end: AtomicBool,
+    /// timer state
+    pub state: RwLock<TimerState>,
+}
+
+/// support timer
+impl Timer for WsClientTimer {
+    fn name(&self) -> &str {
+        self.ws_client.get_log_head()
+    }
+
+    fn run(&self) {
+        match self.state.read().deref() {
+            TimerState::Conn => {
+                self.ws_client.conn();
+                self.ws_client.state.write().connecting = false;
+            }
+            TimerState::Read => {
+                self.ws_client.read()
+            }
+        }
+    }
+
+    fn run_before(&self) -> bool {
+        let wc = &self.ws_client;
+        let state = wc.state.read();
+        if state.connecting || state.reading {
+            return false;
+        }
+
+        if !wc.is_connected() {
+            if !state.first && !wc.conf.reconn.enable.load(Ordering::Acquire) {
+                self.end.store(true, Ordering::Relaxed);
+                return false;
+            }
+
+            // diff lt reconn wait time, not need for conn
+            if !state.first && state.last_re_time.elapsed() < wc.conf.reconn.time {
+                return false;
+            }
+
+            // need conn
+            *self.state.write() = TimerState::Conn;
+            return true;
+        }
+
+        // just run read
+        *self.state.write() = TimerState::Read;
+        true
+    }
+
+    fn ended(&self) -> bool {
+        self.end.load(Ordering::Relaxed)
+    }
+}
+
+/// custom method
+impl WsClientTimer {
+    /// create websocket client timer
+    pub fn new(ws_client: super::WsClient) -> Self {
+        Self {
+            ws_client,
+            end: AtomicBool::default(),
+            state: RwLock::default(),
+        }
+    }
+}
@@ -0,0 +1,9 @@
+/// websocket client timer state
+#[derive(Default)]
+pub enum TimerState {
+    /// run conn
+    #[default]
+    Conn,
+    /// run read data
+    Read,
+}
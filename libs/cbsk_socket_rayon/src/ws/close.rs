@@ -0,0 +1,89 @@
+use std::time::{Duration, Instant};
+use cbsk_base::parking_lot::{Condvar, Mutex};
+use tungstenite::protocol::CloseFrame;
+
+/// websocket closing handshake data
+#[derive(Default)]
+struct CloseData {
+    /// the close frame received from the peer
+    frame: Option<CloseFrame>,
+    /// has the close been received from the peer
+    received: bool,
+    /// does the read need to end
+    ended: bool,
+    /// the close has been sent without waiting, the read ends at this time
+    deadline: Option<Instant>,
+}
+
+/// websocket closing handshake state of one connection<br />
+/// the read records the close received from the peer, [crate::ws::ws_write_trait::WsWriteTrait::try_close] waits for it
+pub struct WsClose {
+    /// how long to wait for the close reply of the peer
+    pub time_out: Duration,
+    /// closing handshake data
+    data: Mutex<CloseData>,
+    /// notify the waiting close that the close of the peer has been received
+    received_cond: Condvar,
+}
+
+/// custom method
+impl WsClose {
+    /// create websocket closing handshake state
+    pub(crate) fn new(time_out: Duration) -> Self {
+        Self { time_out, data: Mutex::default(), received_cond: Condvar::new() }
+    }
+
+    /// reset the state for a new connection, the websocket client reuses the state when reconnect
+    #[cfg(feature = "ws_client")]
+    pub(crate) fn reset(&self) {
+        *self.data.lock() = CloseData::default();
+    }
+
+    /// record the close received from the peer and wake up the waiting close
+    pub(crate) fn recv(&self, close: Option<CloseFrame>) {
+        let mut data = self.data.lock();
+        data.frame = close;
+        data.received = true;
+        self.received_cond.notify_all();
+    }
+
+    /// has the close been received from the peer
+    pub fn is_received(&self) -> bool {
+        self.data.lock().received
+    }
+
+    /// get the close code and reason received from the peer<br />
+    /// None if the close is not received, or the peer sent close without close frame
+    pub fn get_recv(&self) -> Option<CloseFrame> {
+        self.data.lock().frame.clone()
+    }
+
+    /// wait for the close of the peer, return false if it is not received in time
+    pub(crate) fn wait_recv(&self) -> bool {
+        self.wait_recv_until(Instant::now() + self.time_out)
+    }
+
+    /// wait for the close of the peer until the deadline, return false if it is not received in time
+    pub(crate) fn wait_recv_until(&self, deadline: Instant) -> bool {
+        let mut data = self.data.lock();
+        self.received_cond.wait_while_until(&mut data, |data| !data.received, deadline);
+        data.received
+    }
+
+    /// the close has been sent without waiting for the reply, the read ends if the peer does not reply in time
+    #[cfg(feature = "ws_client")]
+    pub(crate) fn sent(&self) {
+        self.data.lock().deadline = Some(Instant::now() + self.time_out);
+    }
+
+    /// notify the read to end, the connection is closed without waiting for the peer
+    pub(crate) fn end(&self) {
+        self.data.lock().ended = true;
+    }
+
+    /// does the read need to end, or the deadline of the sent close is reached
+    pub(crate) fn is_ended(&self) -> bool {
+        let data = self.data.lock();
+        data.ended || data.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
@@ -1 +1,7 @@
-// coming soon
\ No newline at end of file
+pub mod ws_write_trait;
+pub mod close;
+pub(crate) mod ws_stream;
+#[cfg(feature = "ws_client")]
+pub mod client;
+#[cfg(feature = "ws_server")]
+pub mod server;
\ No newline at end of file
@@ -0,0 +1,75 @@
+use std::sync::Arc;
+use cbsk_base::log;
+use cbsk_socket::session::attrs::SessionAttrs;
+use crate::ws::server::client::WsServerClient;
+use crate::ws::server::handshake::{ErrorResponse, Request, Response};
+pub use tungstenite::error::CapacityError;
+pub use tungstenite::protocol::frame::coding::CloseCode;
+pub use tungstenite::protocol::frame::Frame;
+pub use tungstenite::protocol::CloseFrame;
+pub use tungstenite::{Bytes, Utf8Bytes};
+
+/// websocket connect and read data callback
+pub trait WsServerCallBack: Send + Sync + 'static {
+    /// inspect the websocket handshake request, called before the websocket client is created<br />
+    /// request: the request path, query string and headers, such as Authorization and Sec-WebSocket-Protocol<br />
+    /// response: the response to be sent, a subprotocol can be selected by [crate::ws::server::handshake::select_protocol]<br />
+    /// attrs: the session attributes of the websocket client, can be obtained later by [WsServerClient::attrs]<br />
+    /// return Err to reject the websocket client, see [crate::ws::server::handshake::reject]
+    fn on_handshake(&self, request: &Request, response: Response, attrs: &SessionAttrs) -> Result<Response, ErrorResponse> {
+        let _ = (request, attrs);
+        Ok(response)
+    }
+
+    /// a new websocket client come in
+    fn conn(&self, client: Arc<WsServerClient>) {
+        log::info!("{} websocket client connected", client.log_head);
+    }
+
+    /// the websocket client disconnected<br />
+    /// close: the close code and reason received from the websocket client, None if the connection is closed without close frame
+    fn dis_conn(&self, close: Option<CloseFrame>, client: Arc<WsServerClient>) {
+        log::info!("{} websocket client disconnect, close: {close:?}", client.log_head);
+    }
+
+    /// websocket server recv websocket client text data will call this method<br />
+    /// text: websocket client text data<br />
+    /// client: websocket client, you can use this send data to websocket client
+    fn recv_text(&self, text: Utf8Bytes, client: Arc<WsServerClient>);
+
+    /// websocket server recv websocket client binary data will call this method<br />
+    /// in general, you can ignore this data, if client not send binary
+    fn recv_binary(&self, binary: Bytes, client: Arc<WsServerClient>) {
+        log::warn!("{} recv websocket client binary data: [{binary:?}]", client.log_head);
+    }
+
+    /// websocket server recv websocket client ping data will call this method<br />
+    /// the pong is replied automatically, in general, you can ignore this data
+    fn recv_ping(&self, ping: Bytes, client: Arc<WsServerClient>) {
+        log::warn!("{} recv websocket client ping data: [{ping:?}]", client.log_head);
+    }
+
+    /// websocket server recv websocket client pong data will call this method<br />
+    /// in general, you can ignore this data, if client not send pong
+    fn recv_pong(&self, pong: Bytes, client: Arc<WsServerClient>) {
+        log::warn!("{} recv websocket client pong data: [{pong:?}]", client.log_head);
+    }
+
+    /// websocket server recv websocket client close data will call this method<br />
+    /// in general, you can ignore this data, if client not send close
+    fn recv_close(&self, close: Option<CloseFrame>, client: Arc<WsServerClient>) {
+        log::warn!("{} recv websocket client close data: [{close:?}]", client.log_head);
+    }
+
+    /// websocket server recv websocket client frame data will call this method<br />
+    /// in general, you can ignore this data, if client not send frame
+    fn recv_frame(&self, frame: Frame, client: Arc<WsServerClient>) {
+        log::warn!("{} recv websocket client frame data: [{frame:?}]", client.log_head);
+    }
+
+    /// the websocket client sent a message or frame larger than the limits of [crate::ws::server::config::WsServerConfig::ws_conf]<br />
+    /// after this method, a close with code 1009 is sent and the websocket client will be closed
+    fn capacity_exceeded(&self, err: CapacityError, client: Arc<WsServerClient>) {
+        log::warn!("{} websocket client exceeded the capacity: {err}", client.log_head);
+    }
+}
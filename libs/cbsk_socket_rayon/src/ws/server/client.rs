@@ -0,0 +1,217 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use cbsk_base::{anyhow, log};
+use cbsk_base::parking_lot::RwLock;
+use cbsk_socket::session;
+use cbsk_socket::session::attr_trait::AttrTrait;
+use cbsk_socket::session::attrs::SessionAttrs;
+use cbsk_socket::session::ping::{PingState, PingTick};
+use cbsk_socket::trace::conn_span::ConnSpan;
+use tungstenite::protocol::frame::coding::CloseCode;
+use tungstenite::protocol::frame::Frame;
+use tungstenite::protocol::CloseFrame;
+use tungstenite::Message;
+use crate::ws::close::WsClose;
+use crate::ws::server::callback::WsServerCallBack;
+use crate::ws::server::config::WsServerConfig;
+use crate::ws::server::handshake::WsHandshake;
+use crate::ws::server::{WsClients, WsServer};
+use crate::ws::ws_stream::{self, WsStream};
+use crate::ws::ws_write_trait::WsWriteTrait;
+
+/// websocket client
+pub struct WsServerClient {
+    /// websocket client addr
+    pub addr: SocketAddr,
+    /// connection id, see [session::next_conn_id]
+    pub conn_id: u64,
+    /// internal log name
+    pub log_head: String,
+    /// connection span, read and callbacks run in this span
+    pub span: ConnSpan,
+    /// session attributes, see [AttrTrait]
+    pub attrs: SessionAttrs,
+    /// the handshake request uri, headers and selected subprotocol
+    pub handshake: WsHandshake,
+    /// websocket server business callback
+    pub cb: Arc<Box<dyn WsServerCallBack>>,
+    /// websocket server config
+    pub conf: Arc<WsServerConfig>,
+    /// the connected websocket clients of the websocket server, this client is removed after dis_conn
+    clients: WsClients,
+    /// keepalive ping state
+    pub(crate) ping: PingState,
+    /// closing handshake state, see [WsWriteTrait::try_close]
+    pub(crate) ws_close: WsClose,
+    /// the websocket client is reading
+    pub(crate) reading: AtomicBool,
+    /// the websocket client is keep connecting
+    pub(crate) connecting: AtomicBool,
+    /// websocket client read and write
+    ws: RwLock<WsStream>,
+}
+
+/// support session attr trait
+impl AttrTrait for WsServerClient {
+    fn get_conn_id(&self) -> u64 {
+        self.conn_id
+    }
+
+    fn get_attrs(&self) -> &SessionAttrs {
+        &self.attrs
+    }
+}
+
+/// support ws write trait
+impl WsWriteTrait for WsServerClient {
+    fn get_log_head(&self) -> &str {
+        self.log_head.as_str()
+    }
+
+    fn get_ws_close(&self) -> Option<&WsClose> {
+        Some(&self.ws_close)
+    }
+
+    fn try_send(&self, msg: Message) -> tungstenite::Result<()> {
+        ws_stream::send(&mut self.ws.write(), msg, self.conf.write_time_out)
+    }
+
+    fn try_send_frames(&self, frames: impl IntoIterator<Item = Frame>) -> tungstenite::Result<()> {
+        let mut frames = frames.into_iter();
+        // the first frame is taken before holding the write
+        let Some(first) = frames.next() else { return Ok(()); };
+        let mut ws = self.ws.write();
+        ws_stream::write(&mut ws, Message::Frame(first))?;
+        for frame in frames {
+            ws_stream::write(&mut ws, Message::Frame(frame))?;
+        }
+        ws_stream::flush(&mut ws, self.conf.write_time_out)
+    }
+}
+
+/// custom method
+impl WsServerClient {
+    /// create websocket server client
+    pub(crate) fn new(addr: SocketAddr, ws_server: &WsServer, ws: WsStream, attrs: SessionAttrs, handshake: WsHandshake) -> Self {
+        let log_head = format!("{} websocket client[{}]", ws_server.conf.name, addr);
+        let conn_id = session::next_conn_id();
+        Self {
+            addr,
+            conn_id,
+            log_head,
+            span: ConnSpan::new("websocket server client", ws_server.conf.name.as_str(), addr, conn_id),
+            attrs,
+            handshake,
+            cb: ws_server.cb.clone(),
+            conf: ws_server.conf.clone(),
+            clients: ws_server.clients.clone(),
+            ping: PingState::default(),
+            ws_close: WsClose::new(ws_server.conf.close_time_out),
+            reading: AtomicBool::default(),
+            connecting: AtomicBool::new(true),
+            ws: RwLock::new(ws),
+        }
+    }
+
+    /// notify websocket client shutdown connection directly without closing handshake<br />
+    /// dis_conn will be called in the next read, use [WsWriteTrait::try_close] to close gracefully
+    pub fn shutdown(&self) {
+        match ws_stream::shutdown(&self.ws.read()) {
+            Err(e) if self.conf.log => { log::error!("{} shutdown websocket error: {e:?}",self.log_head); }
+            _ => {}
+        }
+    }
+
+    /// get the last measured round trip time of keepalive ping<br />
+    /// None if keepalive is disabled or no pong has been received
+    pub fn get_latency(&self) -> Option<Duration> {
+        self.ping.get_latency()
+    }
+
+    /// read data from websocket client
+    pub(crate) fn read(&self, tc: Arc<Self>) {
+        let _span = self.span.enter();
+        self.reading.store(true, Ordering::Relaxed);
+        let closed =
+            match self.try_read(&tc) {
+                Ok(closed) => { closed }
+                Err(e) => {
+                    if self.conf.log {
+                        log::error!("{} read websocket client data error: {e:?}",self.log_head);
+                    }
+                    true
+                }
+            };
+        if closed {
+            // read error or closed, directly assume that the websocket client has been closed
+            self.shutdown();
+            self.connecting.store(false, Ordering::Release);
+            self.span.dis_conn();
+            self.cb.dis_conn(self.ws_close.get_recv(), tc);
+            self.clients.write().remove(&self.conn_id);
+        }
+        self.reading.store(false, Ordering::Release);
+    }
+
+    /// try read all received data from websocket client<br />
+    /// return true if the websocket connection has been closed
+    fn try_read(&self, tc: &Arc<Self>) -> anyhow::Result<bool> {
+        // the close is not replied in time, end the connection directly
+        if self.ws_close.is_ended() { return Ok(true); }
+        self.keep_alive()?;
+
+        loop {
+            // the lock must be released before calling back, so that data can be sent in the callback
+            let msg = ws_stream::read(&mut self.ws.write());
+            let msg =
+                match self.check_capacity(msg, tc) {
+                    Ok(msg) => { cbsk_base::match_some_return!(msg,Ok(false)) }
+                    // the closing handshake is completed
+                    Err(e) if ws_stream::is_closed(&e) => { return Ok(true); }
+                    Err(e) => { return Err(e.into()); }
+                };
+            match msg {
+                Message::Text(text) => { self.cb.recv_text(text, tc.clone()) }
+                Message::Binary(binary) => { self.cb.recv_binary(binary, tc.clone()) }
+                Message::Ping(ping) => { self.cb.recv_ping(ping, tc.clone()) }
+                Message::Pong(pong) => {
+                    // the reply of keepalive ping is not passed to recv_pong
+                    if self.ping.pong(pong.as_ref(), Instant::now()) { continue; }
+                    self.cb.recv_pong(pong, tc.clone())
+                }
+                Message::Close(close) => {
+                    // the close reply is sent automatically, the next read will return closed
+                    self.ws_close.recv(close.clone());
+                    self.cb.recv_close(close, tc.clone())
+                }
+                Message::Frame(frame) => { self.cb.recv_frame(frame, tc.clone()) }
+            }
+        }
+    }
+
+    /// if the websocket client exceeds the message size limits, call capacity_exceeded and send close with 1009
+    fn check_capacity(&self, msg: tungstenite::Result<Option<Message>>, tc: &Arc<Self>) -> tungstenite::Result<Option<Message>> {
+        if let Err(tungstenite::Error::Capacity(e)) = msg.as_ref() {
+            self.cb.capacity_exceeded(*e, tc.clone());
+            let close = CloseFrame { code: CloseCode::Size, reason: e.to_string().into() };
+            match self.try_send_close(Some(close)) {
+                Err(e) if self.conf.log => { log::warn!("{} send websocket client close error: {e:?}",self.log_head); }
+                _ => {}
+            }
+        }
+        msg
+    }
+
+    /// send keepalive ping, return Err if the pong is not received in time
+    fn keep_alive(&self) -> anyhow::Result<()> {
+        let keep_alive = cbsk_base::match_some_return!(self.conf.keep_alive.as_ref(),Ok(()));
+        match self.ping.tick(keep_alive, Instant::now()) {
+            PingTick::Idle => {}
+            PingTick::Ping(ping) => { self.try_send_ping(ping)? }
+            PingTick::TimeOut => { return Err(anyhow::anyhow!("websocket client pong time out {:?}", keep_alive.pong_time_out)); }
+        }
+        Ok(())
+    }
+}
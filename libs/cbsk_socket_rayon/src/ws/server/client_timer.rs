@@ -0,0 +1,49 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use cbsk_timer::timer::Timer;
+
+/// websocket server client timer
+pub struct WsServerClientTimer {
+    /// websocket client
+    pub ws_client: Arc<super::client::WsServerClient>,
+    /// websocket client timer is need end?
+    pub end: AtomicBool,
+}
+
+/// support timer
+impl Timer for WsServerClientTimer {
+    fn name(&self) -> &str {
+        self.ws_client.log_head.as_str()
+    }
+
+    fn run(&self) {
+        self.ws_client.read(self.ws_client.clone());
+    }
+
+    fn run_before(&self) -> bool {
+        let wc = self.ws_client.as_ref();
+
+        // if dis connection, remove and return
+        if !wc.connecting.load(Ordering::Acquire) {
+            self.end.store(true, Ordering::Relaxed);
+            return false;
+        }
+
+        !wc.reading.load(Ordering::Acquire)
+    }
+
+    fn ended(&self) -> bool {
+        self.end.load(Ordering::Relaxed)
+    }
+}
+
+/// custom method
+impl WsServerClientTimer {
+    /// create websocket server client timer
+    pub fn new(ws_client: Arc<super::client::WsServerClient>) -> Self {
+        Self {
+            ws_client,
+            end: AtomicBool::default(),
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+use cbsk_socket::config::keep_alive::KeepAlive;
+pub use tungstenite::protocol::WebSocketConfig;
+
+/// websocket server config
+pub struct WsServerConfig {
+    /// name, used for log printing
+    pub name: String,
+    /// websocket bind addr
+    pub addr: SocketAddr,
+    /// internal log name, used for log printing
+    pub log_head: String,
+    /// websocket handshake time out, the websocket client not finishing the handshake in time will be closed
+    pub handshake_time_out: Duration,
+    /// is enable log printing
+    pub log: bool,
+    /// ping/pong keepalive, None is disabled
+    pub keep_alive: Option<KeepAlive>,
+    /// how long to wait for the close reply of the websocket client when closing the connection
+    pub close_time_out: Duration,
+    /// how long to wait for the tcp stream to be writable when sending to the websocket client
+    pub write_time_out: Duration,
+    /// websocket protocol config, such as max message size, max frame size and write buffer sizes
+    pub ws_conf: WebSocketConfig,
+}
+
+/// custom method
+impl WsServerConfig {
+    /// create a new config<br />
+    /// name: business name, used for log printing<br />
+    /// addr: websocket bind addr<br />
+    /// log: is enable log printing
+    pub fn new(name: String, addr: SocketAddr, log: bool) -> Self {
+        let log_head = format!("{}[{}]", name, addr);
+        Self {
+            name,
+            addr,
+            log_head,
+            handshake_time_out: Duration::from_secs(10),
+            log,
+            keep_alive: None,
+            close_time_out: Duration::from_secs(3),
+            write_time_out: Duration::from_secs(10),
+            ws_conf: WebSocketConfig::default(),
+        }
+    }
+
+    /// set handshake time out
+    pub fn set_handshake_time_out(mut self, handshake_time_out: Duration) -> Self {
+        self.handshake_time_out = handshake_time_out;
+        self
+    }
+
+    /// set enable log printing
+    pub fn set_log(mut self, log: bool) -> Self {
+        self.log = log;
+        self
+    }
+
+    /// set ping/pong keepalive<br />
+    /// a client that does not reply pong in time will be closed
+    pub fn set_keep_alive(mut self, keep_alive: KeepAlive) -> Self {
+        self.keep_alive = Some(keep_alive);
+        self
+    }
+
+    /// set how long to wait for the close reply of the websocket client, default 3 secs<br />
+    /// if the websocket client does not reply close in time, the connection is closed directly
+    pub fn set_close_time_out(mut self, close_time_out: Duration) -> Self {
+        self.close_time_out = close_time_out;
+        self
+    }
+
+    /// set how long to wait for the tcp stream to be writable when sending, default 10 secs<br />
+    /// if the websocket client does not read in time, the send returns [std::io::ErrorKind::TimedOut]
+    pub fn set_write_time_out(mut self, write_time_out: Duration) -> Self {
+        self.write_time_out = write_time_out;
+        self
+    }
+
+    /// set websocket protocol config
+    pub fn set_ws_conf(mut self, ws_conf: WebSocketConfig) -> Self {
+        self.ws_conf = ws_conf;
+        self
+    }
+
+    /// set the max size of a message, None is unlimited, default is 64 MiB<br />
+    /// the websocket client sending a larger message will be closed, see [crate::ws::server::callback::WsServerCallBack::capacity_exceeded]
+    pub fn set_max_message_size(mut self, max_message_size: Option<usize>) -> Self {
+        self.ws_conf = self.ws_conf.max_message_size(max_message_size);
+        self
+    }
+
+    /// set the max size of a frame, None is unlimited, default is 16 MiB
+    pub fn set_max_frame_size(mut self, max_frame_size: Option<usize>) -> Self {
+        self.ws_conf = self.ws_conf.max_frame_size(max_frame_size);
+        self
+    }
+}
@@ -0,0 +1,140 @@
+use std::net::{SocketAddr, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+use cbsk_base::log;
+use cbsk_base::parking_lot::Mutex;
+use cbsk_socket::session::attrs::SessionAttrs;
+use cbsk_timer::timer::Timer;
+use tungstenite::HandshakeError;
+use tungstenite::handshake::MidHandshake;
+use tungstenite::handshake::server::{Callback, ServerHandshake};
+use crate::ws::server::callback::WsServerCallBack;
+use crate::ws::server::handshake::{ErrorResponse, Request, Response, WsHandshake};
+
+/// the handshake of the websocket client that is not finished
+type WsMidHandshake = MidHandshake<ServerHandshake<TcpStream, HandshakeCallback>>;
+
+/// the accepted handshake, session attributes and request info
+type Accepted = Arc<Mutex<Option<(SessionAttrs, WsHandshake)>>>;
+
+/// call on_handshake and keep the accepted handshake
+pub(crate) struct HandshakeCallback {
+    /// websocket server business callback
+    cb: Arc<Box<dyn WsServerCallBack>>,
+    /// session attributes, can be set by on_handshake
+    attrs: SessionAttrs,
+    /// the accepted handshake
+    accepted: Accepted,
+}
+
+/// support tungstenite handshake callback
+impl Callback for HandshakeCallback {
+    fn on_request(self, request: &Request, response: Response) -> Result<Response, ErrorResponse> {
+        let Self { cb, attrs, accepted } = self;
+        let response = cb.on_handshake(request, response, &attrs)?;
+        *accepted.lock() = Some((attrs, WsHandshake::new(request, &response)));
+        Ok(response)
+    }
+}
+
+/// websocket client handshake timer<br />
+/// the tcp stream is nonblocking, the handshake continues each time the timer runs,
+/// so that a slow websocket client does not hold a thread
+pub struct WsHandshakeTimer {
+    /// websocket server
+    ws_server: super::WsServer,
+    /// websocket client addr
+    addr: SocketAddr,
+    /// timer name
+    name: String,
+    /// the handshake that is not finished
+    mid: Mutex<Option<WsMidHandshake>>,
+    /// the accepted handshake
+    accepted: Accepted,
+    /// the handshake will be ended at this time
+    deadline: Instant,
+    /// websocket handshake timer is need end?
+    end: AtomicBool,
+}
+
+/// support timer
+impl Timer for WsHandshakeTimer {
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    fn run(&self) {
+        let Some(mid) = self.mid.lock().take() else { return; };
+        self.handshake(mid.handshake());
+    }
+
+    fn run_before(&self) -> bool {
+        if self.ended() { return false; }
+        if Instant::now() < self.deadline { return true; }
+
+        // the handshake is not finished in time, the tcp stream is closed by drop
+        self.mid.lock().take();
+        self.end.store(true, Ordering::Relaxed);
+        if self.ws_server.conf.log {
+            log::warn!("{} websocket client[{}] handshake time out {:?}",self.ws_server.conf.log_head,self.addr,self.ws_server.conf.handshake_time_out);
+        }
+        false
+    }
+
+    fn ended(&self) -> bool {
+        self.end.load(Ordering::Relaxed)
+    }
+}
+
+/// custom method
+impl WsHandshakeTimer {
+    /// start websocket handshake, if the handshake is not finished immediately, continue it in the timer
+    pub fn start_handshake(ws_server: super::WsServer, ts: TcpStream, addr: SocketAddr) {
+        let accepted = Accepted::default();
+        let callback = HandshakeCallback { cb: ws_server.cb.clone(), attrs: SessionAttrs::default(), accepted: accepted.clone() };
+        if let Err(e) = ts.set_nonblocking(true) {
+            if ws_server.conf.log { log::warn!("{} websocket client[{addr}] set nonblocking fail: {e:?}",ws_server.conf.log_head); }
+            return;
+        }
+
+        let timer = Self {
+            name: format!("{} websocket client[{addr}] handshake", ws_server.conf.log_head),
+            deadline: Instant::now() + ws_server.conf.handshake_time_out,
+            ws_server,
+            addr,
+            mid: Mutex::default(),
+            accepted,
+            end: AtomicBool::default(),
+        };
+        let result = tungstenite::accept_hdr_with_config(ts, callback, Some(timer.ws_server.conf.ws_conf));
+        if timer.handshake(result) { return; }
+        timer.start();
+    }
+
+    /// handle the handshake result<br />
+    /// return true if the handshake is finished or failed
+    fn handshake(&self, result: Result<tungstenite::WebSocket<TcpStream>, HandshakeError<ServerHandshake<TcpStream, HandshakeCallback>>>) -> bool {
+        let (ws_server, addr) = (&self.ws_server, self.addr);
+        let ws = match result {
+            Ok(ws) => { ws }
+            Err(HandshakeError::Interrupted(mid)) => {
+                *self.mid.lock() = Some(mid);
+                return false;
+            }
+            Err(HandshakeError::Failure(e)) => {
+                self.end.store(true, Ordering::Relaxed);
+                if ws_server.conf.log { log::warn!("{} websocket client[{addr}] handshake fail: {e:?}",ws_server.conf.log_head); }
+                return true;
+            }
+        };
+
+        self.end.store(true, Ordering::Relaxed);
+        let Some((attrs, handshake)) = self.accepted.lock().take() else {
+            if ws_server.conf.log { log::warn!("{} websocket client[{addr}] handshake is rejected",ws_server.conf.log_head); }
+            return true;
+        };
+        ws_server.accepted(ws, addr, attrs, handshake);
+        true
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use cbsk_base::{anyhow, log};
+use cbsk_base::parking_lot::RwLock;
+use cbsk_socket::session::attrs::SessionAttrs;
+use cbsk_timer::timer::Timer;
+use tungstenite::protocol::frame::coding::CloseCode;
+use tungstenite::protocol::CloseFrame;
+use tungstenite::WebSocket;
+use crate::ws::server::callback::WsServerCallBack;
+use crate::ws::server::client::WsServerClient;
+use crate::ws::server::config::WsServerConfig;
+use crate::ws::server::handshake::WsHandshake;
+use crate::ws::ws_write_trait::WsWriteTrait;
+
+pub mod callback;
+pub mod client;
+pub mod config;
+pub mod handshake;
+mod client_timer;
+mod handshake_timer;
+mod timer;
+
+/// the connected websocket clients, key is the connection id
+pub(crate) type WsClients = Arc<RwLock<HashMap<u64, Arc<WsServerClient>>>>;
+
+/// websocket server
+#[derive(Clone)]
+pub struct WsServer {
+    /// websocket config
+    pub conf: Arc<WsServerConfig>,
+    /// websocket server business callback
+    pub cb: Arc<Box<dyn WsServerCallBack>>,
+    /// the connected websocket clients
+    pub(crate) clients: WsClients,
+    /// websocket server listener, bound by the first listener
+    listener: Arc<RwLock<Option<TcpListener>>>,
+    /// stop websocket server
+    stopped: Arc<AtomicBool>,
+}
+
+/// custom method
+impl WsServer {
+    /// create a websocket server<br />
+    /// just create data, if you want to read data to recv method, you should be call start method
+    pub fn new<C: WsServerCallBack>(conf: Arc<WsServerConfig>, cb: C) -> Self {
+        Self { conf, cb: Arc::new(Box::new(cb)), clients: WsClients::default(), listener: Arc::new(RwLock::default()), stopped: Arc::new(AtomicBool::new(false)) }
+    }
+
+    /// start websocket server
+    pub fn start(&self) {
+        timer::WsServerTimer::new(self.clone()).start();
+    }
+
+    /// stop websocket server, no new websocket client will be accepted<br />
+    /// all connected websocket clients are closed with close code 1001 (going away), see [WsWriteTrait::try_close]
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Release);
+        self.wake_listener();
+
+        // send close to all clients first, then wait for the close replies until the same deadline,
+        // so that the total wait time is no more than close_time_out
+        let deadline = Instant::now() + self.conf.close_time_out;
+        let clients: Vec<_> = self.get_clients().into_iter().filter(|client| {
+            let close = CloseFrame { code: CloseCode::Away, reason: "websocket server shutdown".into() };
+            match client.try_send_close(Some(close)) {
+                Ok(()) => { true }
+                Err(e) => {
+                    if self.conf.log { log::warn!("{} close websocket client error: {e:?}",client.log_head); }
+                    client.ws_close.end();
+                    false
+                }
+            }
+        }).collect();
+        for client in clients {
+            if client.ws_close.wait_recv_until(deadline) { continue; }
+
+            // the peer does not reply, end the connection directly
+            client.ws_close.end();
+            if self.conf.log { log::warn!("{} close reply is not received in {:?}",client.log_head,self.conf.close_time_out); }
+        }
+    }
+
+    /// is the websocket server stopped
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Acquire)
+    }
+
+    /// get the connected websocket clients
+    pub fn get_clients(&self) -> Vec<Arc<WsServerClient>> {
+        self.clients.read().values().cloned().collect()
+    }
+
+    /// listener server
+    pub(crate) fn listener(&self) {
+        if let Err(e) = self.try_listener() {
+            // bind again in the next listener
+            self.listener.write().take();
+            log::error!("{} listener websocket[{}] error. wait for the next listener in three seconds. error: {e:?}",self.conf.log_head,self.conf.addr);
+            thread::sleep(Duration::from_secs(3));
+        }
+    }
+
+    /// try listener server, accept one websocket client and start the handshake timer
+    fn try_listener(&self) -> anyhow::Result<()> {
+        if self.listener.read().is_none() {
+            let tl = TcpListener::bind(self.conf.addr)?;
+            log::info!("{} listener WebSocket[{}] success",self.conf.log_head,self.conf.addr);
+            *self.listener.write() = Some(tl);
+        }
+
+        // the server may be stopped before the listener is bound
+        if self.is_stopped() { return Ok(()); }
+        let listener = self.listener.read();
+        let tl = listener.as_ref().ok_or_else(|| anyhow::anyhow!("get listener fail"))?;
+        let (ts, addr) = tl.accept()?;
+        drop(listener);
+        // the stop wakes the accept by connecting to the listener
+        if self.is_stopped() {
+            self.listener.write().take();
+            log::info!("{} websocket server stopped",self.conf.log_head);
+            return Ok(());
+        }
+
+        handshake_timer::WsHandshakeTimer::start_handshake(self.clone(), ts, addr);
+        Ok(())
+    }
+
+    /// wake the accept waiting for the websocket client, so that the listener can check the stop
+    fn wake_listener(&self) {
+        let Some(mut addr) = self.listener.read().as_ref().and_then(|tl| tl.local_addr().ok()) else { return; };
+        if addr.ip().is_unspecified() {
+            addr.set_ip(if addr.is_ipv4() { Ipv4Addr::LOCALHOST.into() } else { Ipv6Addr::LOCALHOST.into() });
+        }
+        if let Err(e) = TcpStream::connect_timeout(&addr, self.conf.close_time_out) {
+            log::warn!("{} wake websocket listener[{addr}] error: {e:?}",self.conf.log_head);
+        }
+    }
+
+    /// the websocket handshake is successful, start read websocket client data
+    pub(crate) fn accepted(&self, ws: WebSocket<TcpStream>, addr: SocketAddr, attrs: SessionAttrs, handshake: WsHandshake) {
+        // after the handshake, read and write are nonblocking, so that the write is not blocked by the read
+        if let Err(e) = ws.get_ref().set_nonblocking(true) {
+            if self.conf.log { log::warn!("{} websocket client[{addr}] set nonblocking fail: {e:?}",self.conf.log_head); }
+            return;
+        }
+        let client = Arc::new(WsServerClient::new(addr, self, ws, attrs, handshake));
+        self.clients.write().insert(client.conn_id, client.clone());
+        client.span.conn();
+        client_timer::WsServerClientTimer::new(client.clone()).start();
+        let _span = client.span.enter();
+        self.cb.conn(client.clone());
+    }
+}
@@ -0,0 +1,23 @@
+/// websocket server timer
+pub struct WsServerTimer {
+    /// websocket server
+    pub ws_server: super::WsServer,
+}
+
+/// custom method
+impl WsServerTimer {
+    /// create websocket server timer
+    pub fn new(ws_server: super::WsServer) -> Self {
+        Self { ws_server }
+    }
+
+    /// start timer
+    pub fn start(self) {
+        cbsk_timer::push_once_with_name(format!("{}listener", self.ws_server.conf.log_head), move || {
+            while !self.ws_server.is_stopped() {
+                self.ws_server.listener();
+            }
+        });
+        cbsk_timer::run();
+    }
+}
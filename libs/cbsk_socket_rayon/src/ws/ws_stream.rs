@@ -0,0 +1,68 @@
+use std::io;
+use std::io::ErrorKind;
+use std::net::{Shutdown, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+use tungstenite::{Error, Message, WebSocket};
+
+/// websocket on the nonblocking tcp stream
+pub(crate) type WsStream = WebSocket<TcpStream>;
+
+/// is the error returned because the nonblocking tcp stream is not ready
+pub(crate) fn is_would_block(e: &Error) -> bool {
+    match e {
+        Error::Io(e) => { matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) }
+        _ => { false }
+    }
+}
+
+/// read a message, return None if there is no data to read
+pub(crate) fn read(ws: &mut WsStream) -> tungstenite::Result<Option<Message>> {
+    match ws.read() {
+        Ok(msg) => { Ok(Some(msg)) }
+        Err(e) if is_would_block(&e) => { Ok(None) }
+        Err(e) => { Err(e) }
+    }
+}
+
+/// write a message to the write buffer<br />
+/// if the nonblocking tcp stream is not ready, the message is still queued and will be sent by flush
+pub(crate) fn write(ws: &mut WsStream, msg: Message) -> tungstenite::Result<()> {
+    match ws.write(msg) {
+        Err(e) if is_would_block(&e) => { Ok(()) }
+        result => { result }
+    }
+}
+
+/// flush the write buffer, wait until the nonblocking tcp stream is ready<br />
+/// return Err with [ErrorKind::TimedOut] if the write buffer is not flushed in time_out
+pub(crate) fn flush(ws: &mut WsStream, time_out: Duration) -> tungstenite::Result<()> {
+    let start = Instant::now();
+    loop {
+        match ws.flush() {
+            Err(e) if is_would_block(&e) => {
+                if start.elapsed() >= time_out {
+                    return Err(io::Error::new(ErrorKind::TimedOut, format!("websocket write is not flushed in {time_out:?}")).into());
+                }
+                thread::sleep(Duration::from_millis(1))
+            }
+            result => { return result; }
+        }
+    }
+}
+
+/// send a message and flush, see [flush]
+pub(crate) fn send(ws: &mut WsStream, msg: Message, time_out: Duration) -> tungstenite::Result<()> {
+    write(ws, msg)?;
+    flush(ws, time_out)
+}
+
+/// is the error returned because the closing handshake is completed or the websocket has been closed
+pub(crate) fn is_closed(e: &Error) -> bool {
+    matches!(e, Error::ConnectionClosed | Error::AlreadyClosed)
+}
+
+/// shutdown the tcp stream directly, the closing handshake is done by [crate::ws::ws_write_trait::WsWriteTrait::try_close]
+pub(crate) fn shutdown(ws: &WsStream) -> std::io::Result<()> {
+    ws.get_ref().shutdown(Shutdown::Both)
+}
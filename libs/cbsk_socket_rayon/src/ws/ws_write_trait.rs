@@ -0,0 +1,164 @@
+use std::fmt::Debug;
+use std::io;
+use cbsk_base::{anyhow, log};
+use cbsk_base::json::to_json::ToJson;
+use cbsk_base::serde::Serialize;
+use tungstenite::protocol::frame::coding::{CloseCode, Data, OpCode};
+use tungstenite::protocol::frame::Frame;
+use tungstenite::protocol::CloseFrame;
+use tungstenite::{Bytes, Message, Utf8Bytes};
+use crate::ws::close::WsClose;
+
+/// send data and print log
+macro_rules! send_ws_log {
+    ($result:expr,$log_head:expr,$name:expr,$data:expr) => {
+        let msg = format!("send {} data [{:?}] to WebSocket", $name,$data);
+        if let Err(e) = $result {
+            log::error!("{} try {msg} error : {e:?}",$log_head);
+            return;
+        }
+        log::debug!("{} {msg} success",$log_head);
+    };
+}
+
+/// websocket write trait
+pub trait WsWriteTrait {
+    /// get internal log name
+    fn get_log_head(&self) -> &str;
+
+    /// get the closing handshake state of the connection<br />
+    /// the default is None, [WsWriteTrait::try_close] only sends the close without waiting for the reply
+    fn get_ws_close(&self) -> Option<&WsClose> {
+        None
+    }
+
+    /// send text to websocket
+    fn send_text(&self, text: impl Into<Utf8Bytes> + Debug) {
+        send_ws_log!(self.try_send_text(text),self.get_log_head(),"text",text);
+    }
+
+    /// try send text to websocket
+    fn try_send_text(&self, text: impl Into<Utf8Bytes>) -> tungstenite::Result<()> {
+        self.try_send(Message::Text(text.into()))
+    }
+
+    /// send json to websocket as text
+    fn send_json(&self, json: &(impl Serialize + Sync)) {
+        send_ws_log!(self.try_send_json(json),self.get_log_head(),"json",json.to_json());
+    }
+
+    /// try send json to websocket as text
+    fn try_send_json(&self, json: &(impl Serialize + Sync)) -> anyhow::Result<()> {
+        let text = json.to_json()?.to_string();
+        self.try_send_text(text)?;
+        Ok(())
+    }
+
+    /// send binary to websocket
+    fn send_binary(&self, binary: impl Into<Bytes> + Debug) {
+        send_ws_log!(self.try_send_binary(binary),self.get_log_head(),"binary",binary);
+    }
+
+    /// try send binary to websocket
+    fn try_send_binary(&self, binary: impl Into<Bytes>) -> tungstenite::Result<()> {
+        self.try_send(Message::Binary(binary.into()))
+    }
+
+    /// send large binary to websocket as explicit fragments, each item is sent as one frame<br />
+    /// the peer receives all fragments as one binary message, empty fragments send nothing
+    fn send_binary_fragments(&self, fragments: impl IntoIterator<Item = Bytes>) {
+        if let Err(e) = self.try_send_binary_fragments(fragments) {
+            log::error!("{} try send binary fragments to WebSocket error : {e:?}",self.get_log_head());
+            return;
+        }
+        log::debug!("{} send binary fragments to WebSocket success",self.get_log_head());
+    }
+
+    /// try send large binary to websocket as explicit fragments, each item is sent as one frame<br />
+    /// the peer receives all fragments as one binary message, empty fragments send nothing
+    fn try_send_binary_fragments(&self, fragments: impl IntoIterator<Item = Bytes>) -> tungstenite::Result<()> {
+        let mut fragments = fragments.into_iter().peekable();
+        let mut opcode = OpCode::Data(Data::Binary);
+        let frames = std::iter::from_fn(move || {
+            let fragment = fragments.next()?;
+            let frame = Frame::message(fragment, opcode, fragments.peek().is_none());
+            opcode = OpCode::Data(Data::Continue);
+            Some(frame)
+        });
+        self.try_send_frames(frames)
+    }
+
+    /// send ping to websocket
+    fn send_ping(&self, ping: impl Into<Bytes> + Debug) {
+        send_ws_log!(self.try_send_ping(ping),self.get_log_head(),"ping",ping);
+    }
+
+    /// try send ping to websocket
+    fn try_send_ping(&self, ping: impl Into<Bytes>) -> tungstenite::Result<()> {
+        self.try_send(Message::Ping(ping.into()))
+    }
+
+    /// send pong to websocket
+    fn send_pong(&self, pong: impl Into<Bytes> + Debug) {
+        send_ws_log!(self.try_send_pong(pong),self.get_log_head(),"pong",pong);
+    }
+
+    /// try send pong to websocket
+    fn try_send_pong(&self, pong: impl Into<Bytes>) -> tungstenite::Result<()> {
+        self.try_send(Message::Pong(pong.into()))
+    }
+
+    /// send close to websocket
+    fn send_close(&self, close: Option<CloseFrame>) {
+        send_ws_log!(self.try_send_close(close),self.get_log_head(),"close",close);
+    }
+
+    /// try send close to websocket
+    fn try_send_close(&self, close: Option<CloseFrame>) -> tungstenite::Result<()> {
+        self.try_send(Message::Close(close))
+    }
+
+    /// close websocket gracefully, see [WsWriteTrait::try_close]
+    fn close(&self, code: CloseCode, reason: impl Into<Utf8Bytes> + Debug) {
+        send_ws_log!(self.try_close(code, reason),self.get_log_head(),"close",(code, &reason));
+    }
+
+    /// try close websocket gracefully, send close with the code and reason, then wait for the close reply of the peer<br />
+    /// the received close is passed to dis_conn, if the peer does not reply in time, the connection is closed directly and Err is returned<br />
+    /// the close reply is read by the read of this connection, so in the recv callbacks of this connection, use [WsWriteTrait::try_send_close] instead
+    fn try_close(&self, code: CloseCode, reason: impl Into<Utf8Bytes>) -> tungstenite::Result<()> {
+        let result = self.try_send_close(Some(CloseFrame { code, reason: reason.into() }));
+        let ws_close = cbsk_base::match_some_return!(self.get_ws_close(), result);
+        if result.is_ok() && ws_close.wait_recv() { return Ok(()); }
+
+        // the close is not sent or the peer does not reply, end the connection directly
+        ws_close.end();
+        result?;
+        Err(io::Error::new(io::ErrorKind::TimedOut, format!("close reply is not received in {:?}", ws_close.time_out)).into())
+    }
+
+    /// send frame to websocket
+    fn send_frame(&self, frame: Frame) {
+        send_ws_log!(self.try_send_frame(frame),self.get_log_head(),"frame",frame);
+    }
+
+    /// try send frame to websocket
+    fn try_send_frame(&self, frame: Frame) -> tungstenite::Result<()> {
+        self.try_send(Message::Frame(frame))
+    }
+
+    /// try send message to websocket
+    fn try_send(&self, msg: Message) -> tungstenite::Result<()>;
+
+    /// try send frames to websocket in order<br />
+    /// the default sends each frame by [WsWriteTrait::try_send], other messages may be sent between these frames<br />
+    /// the websocket client and server send no other message between these frames,
+    /// the first frame is taken before writing, then the write is held until the iterator ends,
+    /// so the next frames should be ready, otherwise the other sends of this connection wait for them
+    fn try_send_frames(&self, frames: impl IntoIterator<Item = Frame>) -> tungstenite::Result<()> {
+        for frame in frames {
+            self.try_send(Message::Frame(frame))?;
+        }
+        Ok(())
+    }
+}
@@ -12,3 +12,5 @@ pub(crate) mod r#macro;
 pub mod tcp;
 #[cfg(any(feature = "ws_server", feature = "ws_client"))]
 pub mod ws;
+#[cfg(feature = "metrics")]
+pub mod metrics;
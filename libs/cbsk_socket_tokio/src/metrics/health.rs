@@ -0,0 +1,22 @@
+use cbsk_base::serde::Serialize;
+use cbsk_socket::metrics::server_metrics::ServerMetrics;
+
+/// server health data, returned by /health
+#[derive(Serialize)]
+#[serde(crate = "cbsk_base::serde")]
+pub struct Health {
+    /// server name
+    pub name: String,
+    /// is the server listener bound
+    pub listening: bool,
+    /// the number of clients currently connected
+    pub clients: usize,
+}
+
+/// custom method
+impl Health {
+    /// get health data from server counters
+    pub fn new(name: impl Into<String>, metrics: &ServerMetrics) -> Self {
+        Self { name: name.into(), listening: metrics.is_listening(), clients: metrics.get_clients() }
+    }
+}
@@ -0,0 +1,133 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use cbsk_base::{anyhow, log, tokio};
+use cbsk_base::json::to_json::ToJson;
+use cbsk_base::tokio::io::{AsyncReadExt, AsyncWriteExt};
+use cbsk_base::tokio::net::{TcpListener, TcpStream};
+use cbsk_base::tokio::task::JoinHandle;
+use cbsk_socket::metrics::server_metrics::ServerMetrics;
+use crate::metrics::health::Health;
+
+pub mod health;
+
+/// the max length of the http request head that will be read
+const MAX_REQUEST_LEN: usize = 4096;
+
+/// prometheus metrics http server<br />
+/// GET /metrics: server counters in prometheus text exposition format<br />
+/// GET /health: listener state and connected client count in json
+#[derive(Clone)]
+pub struct MetricsServer {
+    /// server name, used as the name label and for log printing
+    pub name: String,
+    /// metrics http server bind addr
+    pub addr: SocketAddr,
+    /// counters of the server being observed
+    pub metrics: Arc<ServerMetrics>,
+    /// read http request time out
+    pub read_time_out: Duration,
+    /// internal log name, used for log printing
+    log_head: String,
+}
+
+/// data init etc
+impl MetricsServer {
+    /// create metrics http server<br />
+    /// just create data, if you want to serve metrics, you should be call start method
+    pub fn new(name: impl Into<String>, addr: SocketAddr, metrics: Arc<ServerMetrics>) -> Self {
+        let name = name.into();
+        let log_head = format!("{name} metrics[{addr}]");
+        Self { name, addr, metrics, read_time_out: Duration::from_secs(3), log_head }
+    }
+
+    /// set read http request time out
+    pub fn set_read_time_out(mut self, read_time_out: Duration) -> Self {
+        self.read_time_out = read_time_out;
+        self
+    }
+}
+
+/// http logic
+impl MetricsServer {
+    /// start metrics http server
+    pub async fn start(&self) {
+        if let Err(e) = self.try_start().await {
+            log::error!("{} metrics http bind [{}] error: {e:?}",self.log_head,self.addr);
+        }
+    }
+
+    /// start metrics http server in join handle
+    pub fn start_in_handle(&self) -> JoinHandle<()> {
+        let metrics_server = self.clone();
+        tokio::spawn(async move { metrics_server.start().await; })
+    }
+
+    /// try start metrics http server
+    async fn try_start(&self) -> anyhow::Result<()> {
+        let listener = TcpListener::bind(self.addr).await?;
+        log::info!("{} listener metrics http[{}] success",self.log_head,self.addr);
+
+        loop {
+            let (tcp_stream, addr) = match listener.accept().await {
+                Ok(accept) => { accept }
+                Err(e) => {
+                    log::error!("{} wait metrics http accept error. wait for the next accept in three seconds. error: {e:?}",self.log_head);
+                    tokio::time::sleep(Duration::from_secs(3)).await;
+                    continue;
+                }
+            };
+
+            let metrics_server = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = metrics_server.try_response(tcp_stream).await {
+                    log::warn!("{} response metrics http client[{addr}] error: {e:?}",metrics_server.log_head);
+                }
+            });
+        }
+    }
+
+    /// read the http request and write the response
+    async fn try_response(&self, mut tcp_stream: TcpStream) -> anyhow::Result<()> {
+        let request = tokio::time::timeout(self.read_time_out, Self::read_request(&mut tcp_stream)).await??;
+        let mut request_line = request.lines().next().unwrap_or_default().split_whitespace();
+        let method = request_line.next().unwrap_or_default();
+        // ignore query string
+        let path = request_line.next().unwrap_or_default().split('?').next().unwrap_or_default();
+
+        let (status, content_type, body) =
+            match (method, path) {
+                ("GET", "/metrics") => {
+                    ("200 OK", "text/plain; version=0.0.4; charset=utf-8", self.metrics.to_prometheus(&self.name))
+                }
+                ("GET", "/health") => {
+                    let health = Health::new(self.name.as_str(), self.metrics.as_ref());
+                    let status = if health.listening { "200 OK" } else { "503 Service Unavailable" };
+                    (status, "application/json", health.to_json()?.to_string())
+                }
+                ("GET", _) => { ("404 Not Found", "text/plain; charset=utf-8", "not found".to_string()) }
+                _ => { ("405 Method Not Allowed", "text/plain; charset=utf-8", "method not allowed".to_string()) }
+            };
+
+        let response = format!("HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}", body.len());
+        tcp_stream.write_all(response.as_bytes()).await?;
+        tcp_stream.shutdown().await?;
+        Ok(())
+    }
+
+    /// read the http request head, the request body is not needed
+    async fn read_request(tcp_stream: &mut TcpStream) -> anyhow::Result<String> {
+        let mut request = Vec::with_capacity(1024);
+        let mut buf = [0; 1024];
+
+        loop {
+            let len = tcp_stream.read(&mut buf).await?;
+            if len == 0 { return Err(anyhow::anyhow!("read data length is 0, metrics http client is disconnected")); }
+
+            request.extend_from_slice(&buf[..len]);
+            if request.windows(4).any(|w| w == b"\r\n\r\n") || request.len() >= MAX_REQUEST_LEN {
+                return Ok(String::from_utf8_lossy(&request).into_owned());
+            }
+        }
+    }
+}
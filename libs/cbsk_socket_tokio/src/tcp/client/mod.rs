@@ -7,8 +7,10 @@ use cbsk_base::tokio::net::tcp::OwnedReadHalf;
 use cbsk_base::tokio::net::TcpStream;
 use cbsk_base::tokio::sync::RwLock;
 use cbsk_base::tokio::task::JoinHandle;
+use cbsk_socket::session;
 use cbsk_socket::tcp::client::config::TcpClientConfig;
 use cbsk_socket::tcp::common::time_trait::TimeTrait;
+use cbsk_socket::trace::conn_span::ConnSpan;
 use crate::tcp::client::callback::TcpClientCallBack;
 use crate::tcp::client::tcp_write::TcpWrite;
 use crate::tcp::common::read_trait::ReadTrait;
@@ -45,6 +47,8 @@ pub struct TcpClient {
     wait_callback: Arc<AtomicBool>,
     /// tcp read data len
     buf_len: usize,
+    /// the last connection span, a new span will be created each time the connection is successful
+    span: Arc<RwLock<ConnSpan>>,
 }
 
 /// support writer trait
@@ -118,6 +122,7 @@ impl TcpClient {
             write: Arc::new(RwLock::new(TcpWrite::default())),
             wait_callback: Arc::new(Default::default()),
             buf_len,
+            span: Arc::new(RwLock::default()),
         }
     }
 
@@ -195,7 +200,9 @@ impl TcpClient {
             if !self.conf.reconn.enable.load(Ordering::Acquire) { return; }
 
             // reconn
-            self.cb.re_conn(re_num).await;
+            let span = self.span.read().await.clone();
+            span.re_conn(re_num);
+            span.instrument(self.cb.re_conn(re_num)).await;
             log::info!("{} tcp service will reconnect in {:?}",self.conf.log_head,self.conf.reconn.time);
             tokio::time::sleep(self.conf.reconn.time).await;
         }
@@ -207,24 +214,28 @@ impl TcpClient {
         self.write.write().await.set_write(write);
 
         log::info!("{} started tcp server read data async success",self.conf.log_head);
-        self.cb.conn().await;
+        let span = ConnSpan::new("tcp client", self.conf.name.as_str(), self.conf.addr, session::next_conn_id());
+        *self.span.write().await = span.clone();
+        span.conn();
+        span.instrument(self.cb.conn()).await;
 
-        let read_handle = self.try_read_spawn(read);
+        let read_handle = self.try_read_spawn(read, span.clone());
         self.wait_read_handle_finished(read_handle, self.conf.read_time_out, || async {}).await;
 
         // tcp read disabled, directly assume that tcp has been closed, simultaneously close read
         self.shutdown().await;
-        self.cb.dis_conn().await;
+        span.dis_conn();
+        span.instrument(self.cb.dis_conn()).await;
         log::info!("{} tcp server read data async is shutdown",self.conf.log_head);
     }
 
     /// read data handle
-    fn try_read_spawn(&self, read: OwnedReadHalf) -> JoinHandle<()> {
+    fn try_read_spawn(&self, read: OwnedReadHalf, span: ConnSpan) -> JoinHandle<()> {
         // start read headle, set recvtime and timeouttime is now
         self.set_now();
 
         let tcp_client = self.clone();
-        tokio::spawn(async move {
+        tokio::spawn(span.instrument(async move {
             let result =
                 tcp_client.try_read_data_tokio(read, tcp_client.buf_len, tcp_client.conf.read_time_out, "server", || async {
                     tcp_client.write.read().await.write.is_none()
@@ -237,7 +248,7 @@ impl TcpClient {
                     log::error!("{} tcp server read data error: {e:?}",tcp_client.conf.log_head);
                 }
             }
-        })
+        }))
     }
 
     /// try connect tcp server
@@ -12,6 +12,24 @@ pub trait ReadTrait: TimeTrait {
     /// get internal log name
     fn get_log_head(&self) -> &str;
 
+    /// record the length of the data read once, used for connection counters
+    fn record_recv(&self, len: usize) {
+        // default do nothing
+        let _ = len;
+    }
+
+    /// get is the connection closed by business, if true, the read loop will exit
+    fn is_closed(&self) -> bool {
+        // default is false
+        false
+    }
+
+    /// wait until the connection is closed by business, the waiting read will be cancelled
+    fn wait_closed(&self) -> impl Future<Output=()> + Send {
+        // default never closed by business
+        std::future::pending()
+    }
+
     /// wait read data finished
     async fn wait_read_handle_finished<F, R>(&self, read_handle: JoinHandle<()>, read_time_out: Duration, abort_fn: F)
     where
@@ -83,12 +101,20 @@ pub trait ReadTrait: TimeTrait {
         let mut buf_tmp = Vec::with_capacity(buf_len);
 
         loop {
+            // the connection is closed by business, exit the loop directly
+            if self.is_closed() { return Ok(()); }
+
             let read = read.read(buf.as_mut_slice());
             #[cfg(feature = "debug_mode")]
             log::info!("{} start read",self.get_log_head());
+            // the timeout of tokio_runtime may be an issue, which may cause the CPU to idle. It needs to be fixed here
+            let read = tokio::select! {
+                read = tokio::time::timeout(read_time_out, read) => { read }
+                // the connection is closed by business, cancel the read and exit the loop directly
+                _ = self.wait_closed() => { return Ok(()); }
+            };
             let len =
-                // the timeout of tokio_runtime may be an issue, which may cause the CPU to idle. It needs to be fixed here
-                match tokio::time::timeout(read_time_out, read).await {
+                match read {
                     Ok(read) => {
                         match read {
                             Ok(len) => { len }
@@ -134,6 +160,7 @@ pub trait ReadTrait: TimeTrait {
 
             // set recv time
             self.set_recv_time_now();
+            self.record_recv(len);
             #[cfg(feature = "debug_mode")]
             log::info!("{} recv time",self.get_log_head());
             // non zero length, execution logic, etc
@@ -1,11 +1,18 @@
 use std::sync::Arc;
 use cbsk_base::async_trait::async_trait;
 use cbsk_base::log;
+use cbsk_socket::config::rate_limit::{LimitPolicy, LimitScope, RateLimit};
 use crate::tcp::server::client::TcpServerClient;
 
 /// tcp connect and read data callback
 #[async_trait]
 pub trait TcpServerCallBack: Send + Sync + 'static {
+    /// a new tcp client is accepted, called before the read starts<br />
+    /// set the session attributes needed by recv here, recv is never called before this method returns
+    async fn setup(&self, client: Arc<TcpServerClient>) {
+        let _ = client;
+    }
+
     /// a new tcp client come in
     async fn conn(&self, client: Arc<TcpServerClient>) {
         log::info!("{} tcp client connected",client.log_head);
@@ -16,6 +23,25 @@ pub trait TcpServerCallBack: Send + Sync + 'static {
         log::info!("{} tcp client disconnect", client.log_head);
     }
 
+    /// the rate limit of each client, each read data passed to recv is counted as one frame<br />
+    /// default is None, unlimited
+    fn client_rate_limit(&self) -> Option<RateLimit> {
+        None
+    }
+
+    /// the rate limit shared by all clients, each read data passed to recv is counted as one frame<br />
+    /// only be called once when the tcp server is created, default is None, unlimited
+    fn server_rate_limit(&self) -> Option<RateLimit> {
+        None
+    }
+
+    /// the tcp client exceeded the rate limit<br />
+    /// scope: the client limiter or the server limiter is exceeded<br />
+    /// policy: the policy that will be applied, the read data will be delayed, dropped, or the client will be shutdown<br />
+    /// len: the length of the limited read data
+    async fn rate_limited(&self, scope: LimitScope, policy: LimitPolicy, len: usize, client: Arc<TcpServerClient>) {
+        log::warn!("{} tcp client exceeded the {scope:?} rate limit, read data length is {len}, policy is {policy:?}",client.log_head);
+    }
 
     /// tcp server recv tcp client data will call this method<br />
     /// bytes: tcp client data<br />
@@ -1,11 +1,18 @@
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use cbsk_base::log;
 use cbsk_base::tokio::io::AsyncWriteExt;
 use cbsk_base::tokio::net::tcp::OwnedWriteHalf;
-use cbsk_base::tokio::sync::RwLock;
+use cbsk_base::tokio::sync::{Notify, RwLock};
+use cbsk_socket::metrics::conn_metrics::ConnMetrics;
+use cbsk_socket::metrics::server_metrics::ServerMetrics;
+use cbsk_socket::session;
+use cbsk_socket::session::attr_trait::AttrTrait;
+use cbsk_socket::session::attrs::SessionAttrs;
 use cbsk_socket::tcp::common::time_trait::TimeTrait;
 use cbsk_socket::tcp::server::config::TcpServerConfig;
+use cbsk_socket::trace::conn_span::ConnSpan;
 use crate::tcp::common::read_trait::ReadTrait;
 use crate::tcp::common::tcp_write_trait::TcpWriteTrait;
 
@@ -13,8 +20,14 @@ use crate::tcp::common::tcp_write_trait::TcpWriteTrait;
 pub struct TcpServerClient {
     /// tcp client addr
     pub addr: SocketAddr,
+    /// connection id, see [session::next_conn_id]
+    pub conn_id: u64,
     /// internal log name
     pub log_head: String,
+    /// connection span, read loop and callbacks run in this span
+    pub span: ConnSpan,
+    /// session attributes, see [AttrTrait]
+    pub attrs: SessionAttrs,
     /// the last time the data was received<br />
     /// time see [cbsk_base::fastdate::DateTime::unix_timestamp_millis]
     pub recv_time: Arc<AtomicI64>,
@@ -25,27 +38,80 @@ pub struct TcpServerClient {
     pub ignore_once: Arc<AtomicBool>,
     /// tcp client write
     pub write: Arc<RwLock<OwnedWriteHalf>>,
+    /// tcp client counters
+    pub metrics: Arc<ConnMetrics>,
     /// is wait callback
     wait_callback: Arc<AtomicBool>,
+    /// is the connection closed by business
+    closed: Arc<AtomicBool>,
+    /// notify the read loop that the connection is closed by business
+    closed_notify: Arc<Notify>,
+    /// is the read loop finished
+    read_finished: Arc<AtomicBool>,
 }
 
 /// custom method
 impl TcpServerClient {
     /// create tcp server client
     pub fn new(addr: SocketAddr, conf: &TcpServerConfig, write: OwnedWriteHalf) -> Self {
+        Self::new_with_metrics(addr, conf, write, ServerMetrics::default().into())
+    }
+
+    /// create tcp server client, client counters will be added to server counters
+    pub fn new_with_metrics(addr: SocketAddr, conf: &TcpServerConfig, write: OwnedWriteHalf, server_metrics: Arc<ServerMetrics>) -> Self {
         let log_head = format!("{} tcp client[{}]", conf.name, addr);
+        let conn_id = session::next_conn_id();
         Self {
             addr,
+            conn_id,
             log_head,
+            span: ConnSpan::new("tcp server client", conf.name.as_str(), addr, conn_id),
+            attrs: SessionAttrs::default(),
             recv_time: AtomicI64::new(Self::now()).into(),
             timeout_time: AtomicI64::new(Self::now()).into(),
             ignore_once: AtomicBool::default().into(),
             write: Arc::new(RwLock::new(write)),
+            metrics: ConnMetrics::new(server_metrics).into(),
             wait_callback: Arc::new(Default::default()),
+            closed: Arc::new(AtomicBool::default()),
+            closed_notify: Arc::new(Notify::new()),
+            read_finished: Arc::new(AtomicBool::default()),
+        }
+    }
+
+    /// get is the tcp client connected<br />
+    /// false as soon as the read loop is finished, dis_conn may be called later
+    pub fn is_connected(&self) -> bool {
+        !self.read_finished.load(Ordering::Acquire)
+    }
+
+    /// the read loop is finished, the tcp client is disconnected
+    pub(crate) fn finish_read(&self) {
+        self.read_finished.store(true, Ordering::Release);
+    }
+
+    /// shutdown tcp client connection<br />
+    /// the waiting read is cancelled, the read loop will exit and dis_conn will be called
+    pub async fn shutdown(&self) {
+        self.closed.store(true, Ordering::Release);
+        self.closed_notify.notify_waiters();
+        if let Err(e) = self.write.write().await.shutdown().await {
+            log::error!("{} shutdown tcp error: {e:?}",self.log_head);
         }
     }
 }
 
+/// support session attr trait
+impl AttrTrait for TcpServerClient {
+    fn get_conn_id(&self) -> u64 {
+        self.conn_id
+    }
+
+    fn get_attrs(&self) -> &SessionAttrs {
+        &self.attrs
+    }
+}
+
 /// support tcp time trait
 impl TimeTrait for TcpServerClient {
     fn set_recv_time(&self, time: i64) {
@@ -83,7 +149,9 @@ impl TcpWriteTrait for TcpServerClient {
     async fn try_send_bytes(&self, bytes: &[u8]) -> std::io::Result<()> {
         let mut write = self.write.write().await;
         write.write_all(bytes).await?;
-        write.flush().await
+        write.flush().await?;
+        self.metrics.add_send(bytes.len());
+        Ok(())
     }
 }
 
@@ -92,4 +160,19 @@ impl ReadTrait for TcpServerClient {
     fn get_log_head(&self) -> &str {
         self.log_head.as_str()
     }
+
+    fn record_recv(&self, len: usize) {
+        self.metrics.add_recv(len)
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+
+    async fn wait_closed(&self) {
+        // the notified is created before checking, so that the notify between them will not be lost
+        let notified = self.closed_notify.notified();
+        if self.is_closed() { return; }
+        notified.await
+    }
 }
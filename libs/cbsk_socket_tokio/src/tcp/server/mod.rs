@@ -1,4 +1,6 @@
 use std::io;
+#[cfg(feature = "metrics")]
+use std::net::SocketAddr;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
@@ -7,6 +9,9 @@ use cbsk_base::tokio::io::AsyncWriteExt;
 use cbsk_base::tokio::net::tcp::OwnedReadHalf;
 use cbsk_base::tokio::net::TcpListener;
 use cbsk_base::tokio::task::JoinHandle;
+use cbsk_socket::config::rate_limit::{LimitPolicy, LimitScope};
+use cbsk_socket::limit::rate_limiter::{self, LimitCheck, RateLimiter};
+use cbsk_socket::metrics::server_metrics::ServerMetrics;
 use cbsk_socket::tcp::server::config::TcpServerConfig;
 use crate::tcp::common::read_trait::ReadTrait;
 use crate::tcp::server::callback::TcpServerCallBack;
@@ -22,6 +27,10 @@ pub struct TcpServer {
     pub conf: Arc<TcpServerConfig>,
     /// tcp server business callback
     pub cb: Arc<Box<dyn TcpServerCallBack>>,
+    /// tcp server counters
+    pub metrics: Arc<ServerMetrics>,
+    /// the rate limiter shared by all clients, see [TcpServerCallBack::server_rate_limit]
+    server_limiter: Option<Arc<RateLimiter>>,
     /// tcp read data len
     buf_len: usize,
     /// stop tcp server
@@ -37,7 +46,15 @@ impl TcpServer {
     }
 
     pub fn new_with_buf_len<C: TcpServerCallBack>(conf: Arc<TcpServerConfig>, cb: C, buf_len: usize) -> Self {
-        Self { conf, cb: Arc::new(Box::new(cb)), buf_len, stopped: Arc::new(AtomicBool::new(false)) }
+        let server_limiter = cb.server_rate_limit().map(|limit| RateLimiter::new(limit).into());
+        Self { conf, cb: Arc::new(Box::new(cb)), metrics: Arc::new(ServerMetrics::default()), server_limiter, buf_len, stopped: Arc::new(AtomicBool::new(false)) }
+    }
+
+    /// start prometheus metrics http server in join handle<br />
+    /// addr: metrics http server bind addr, serve /metrics and /health
+    #[cfg(feature = "metrics")]
+    pub fn start_metrics_in_handle(&self, addr: SocketAddr) -> JoinHandle<()> {
+        crate::metrics::MetricsServer::new(self.conf.name.as_str(), addr, self.metrics.clone()).start_in_handle()
     }
 }
 
@@ -68,10 +85,14 @@ impl TcpServer {
         let conf = self.conf.as_ref();
 
         log::info!("{} listener TCP[{}] success",conf.log_head,conf.addr);
+        self.metrics.set_listening(true);
         // loop waiting for client to connect
         loop {
             // if stop the server, return function
-            if self.stopped.load(Ordering::Acquire) { return Ok(()); }
+            if self.stopped.load(Ordering::Acquire) {
+                self.metrics.set_listening(false);
+                return Ok(());
+            }
             if let Err(e) = self.try_accept(&listener).await {
                 log::error!("{} wait tcp accept error. wait for the next accept in three seconds. error: {:?}",conf.log_head,e);
                 tokio::time::sleep(Duration::from_secs(3)).await;
@@ -86,9 +107,13 @@ impl TcpServer {
         let (read, write) = tcp_stream.into_split();
 
         // start read data
-        let client = Arc::new(client::TcpServerClient::new(addr, self.conf.as_ref(), write.into()));
+        let client = Arc::new(client::TcpServerClient::new_with_metrics(addr, self.conf.as_ref(), write, self.metrics.clone()));
+        self.metrics.client_conn();
+        client.span.conn();
+        let span = client.span.clone();
+        span.instrument(self.cb.setup(client.clone())).await;
         self.read_spawn(client.clone(), read);
-        self.cb.conn(client).await;
+        span.instrument(self.cb.conn(client)).await;
 
         Ok(())
     }
@@ -96,7 +121,8 @@ impl TcpServer {
     /// start read async
     fn read_spawn(&self, client: Arc<TcpServerClient>, read: OwnedReadHalf) {
         let tcp_server = self.clone();
-        tokio::spawn(async move {
+        let span = client.span.clone();
+        tokio::spawn(span.instrument(async move {
             let read_handle = tcp_server.try_read_spawn(client.clone(), read);
 
             client.wait_read_handle_finished(read_handle, tcp_server.conf.read_time_out, || async {
@@ -106,27 +132,58 @@ impl TcpServer {
             }).await;
 
             // if TCP read is closed, it is considered that TCP has been closed
+            client.finish_read();
+            tcp_server.metrics.client_dis_conn();
+            client.span.dis_conn();
             tcp_server.cb.dis_conn(client.clone()).await;
             if tcp_server.conf.log { log::info!("{} tcp client read async closed",client.log_head); }
-        });
+        }));
     }
 
     /// try read tcp client data
     fn try_read_spawn(&self, client: Arc<TcpServerClient>, read: OwnedReadHalf) -> JoinHandle<()> {
         if self.conf.log { log::info!("{} start tcp client read async success",client.log_head); }
         let tcp_server = self.clone();
+        let span = client.span.clone();
+        let client_limiter = self.cb.client_rate_limit().map(RateLimiter::new);
 
-        tokio::spawn(async move {
+        tokio::spawn(span.instrument(async move {
             let result =
                 client.try_read_data_tokio(read, tcp_server.buf_len, tcp_server.conf.read_time_out, "client", || async {
                     tcp_server.stopped.load(Ordering::Acquire)
                 }, |data| async {
+                    // the read data is discarded if the rate limit is exceeded
+                    if !tcp_server.check_rate_limit(data.len(), client_limiter.as_ref(), &client).await { return Vec::new(); }
                     tcp_server.cb.recv(data, client.clone()).await
                 }).await;
+            client.finish_read();
 
             if let Err(e) = result {
                 if tcp_server.conf.log { log::error!("{} read tcp client data error: {e:?}",client.log_head); }
             }
-        })
+        }))
+    }
+
+    /// check the client and server rate limit for the read data, see [rate_limiter::check_all]<br />
+    /// if the policy is delay, wait here until the tokens are available, so the reading is delayed<br />
+    /// return false if the read data should be discarded
+    async fn check_rate_limit(&self, len: usize, client_limiter: Option<&RateLimiter>, client: &Arc<TcpServerClient>) -> bool {
+        let limiters = [(LimitScope::Client, client_limiter), (LimitScope::Server, self.server_limiter.as_deref())];
+        match rate_limiter::check_all(&limiters, len) {
+            LimitCheck::Pass(delays) => {
+                let mut wait = Duration::ZERO;
+                for (scope, delay) in delays {
+                    self.cb.rate_limited(scope, LimitPolicy::Delay, len, client.clone()).await;
+                    wait = wait.max(delay);
+                }
+                if !wait.is_zero() { tokio::time::sleep(wait).await; }
+                true
+            }
+            LimitCheck::Reject(scope, policy) => {
+                self.cb.rate_limited(scope, policy, len, client.clone()).await;
+                if policy == LimitPolicy::DisConn { client.shutdown().await; }
+                false
+            }
+        }
     }
 }
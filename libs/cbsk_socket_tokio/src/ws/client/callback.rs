@@ -1,20 +1,25 @@
+use crate::ws::client::handshake::WsHandshake;
 use cbsk_base::log;
 use std::future::Future;
+pub use tokio_tungstenite::tungstenite::error::CapacityError;
+pub use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
 pub use tokio_tungstenite::tungstenite::protocol::frame::Frame;
 pub use tokio_tungstenite::tungstenite::protocol::CloseFrame;
 pub use tokio_tungstenite::tungstenite::{Bytes, Utf8Bytes};
 
 /// websocket connect and read data callback
 pub trait WsClientCallBack: Send + Sync + 'static {
-    /// connect websocket server success will call this method
-    fn conn(&self) -> impl Future<Output = ()> + Send {
-        log::info!("connect websocket server success");
+    /// connect websocket server success will call this method<br />
+    /// handshake: the selected subprotocol and response headers of websocket server
+    fn conn(&self, handshake: WsHandshake) -> impl Future<Output = ()> + Send {
+        log::info!("connect websocket server success, subprotocol is {:?}", handshake.protocol);
         async {}
     }
 
-    /// this method will be called when the websocket service is disconnected
-    fn dis_conn(&self) -> impl Future<Output = ()> + Send {
-        log::info!("disconnect websocket server");
+    /// this method will be called when the websocket service is disconnected<br />
+    /// close: the close code and reason received from the websocket server, None if the connection is closed without close frame
+    fn dis_conn(&self, close: Option<CloseFrame>) -> impl Future<Output = ()> + Send {
+        log::info!("disconnect websocket server, close: {close:?}");
         async {}
     }
 
@@ -63,4 +68,11 @@ pub trait WsClientCallBack: Send + Sync + 'static {
         log::warn!("recv websocket server frame data: [{frame:?}]");
         async {}
     }
+
+    /// the websocket server sent a message or frame larger than the limits of [crate::ws::client::config::WsClientConfig::ws_conf]<br />
+    /// after this method, a close with code 1009 is sent and the connection will be closed
+    fn capacity_exceeded(&self, err: CapacityError) -> impl Future<Output = ()> + Send {
+        log::warn!("websocket server exceeded the capacity: {err}");
+        async {}
+    }
 }
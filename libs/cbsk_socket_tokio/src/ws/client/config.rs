@@ -1,5 +1,14 @@
+use std::future::Future;
+use std::pin::Pin;
 use std::time::Duration;
+use cbsk_socket::config::keep_alive::KeepAlive;
 use cbsk_socket::config::re_conn::SocketReConn;
+pub use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+#[cfg(feature = "ws_deflate")]
+use crate::ws::deflate::config::WsDeflate;
+
+/// the function to refresh request headers before each connect attempt
+pub type RefreshHeadersFn = Box<dyn Fn() -> Pin<Box<dyn Future<Output=Vec<(String, String)>> + Send>> + Sync + Send>;
 
 /// websocket client config
 pub struct WsClientConfig {
@@ -16,13 +25,30 @@ pub struct WsClientConfig {
     pub read_time_out: Duration,
     /// websocket sockets need to be reconnect
     pub(crate) reconn: SocketReConn,
+    /// custom request headers, such as Authorization or Cookie
+    pub headers: Vec<(String, String)>,
+    /// the subprotocols requested in order of preference, sent by Sec-WebSocket-Protocol header
+    pub protocols: Vec<String>,
+    /// called before each connect attempt, the returned headers override the same name headers<br />
+    /// can be used to refresh the auth token before reconnect
+    pub refresh_headers: Option<RefreshHeadersFn>,
+    /// ping/pong keepalive, None is disabled
+    pub keep_alive: Option<KeepAlive>,
+    /// how long to wait for the close reply of the websocket server when closing the connection
+    pub close_time_out: Duration,
+    /// websocket protocol config, such as max message size, max frame size and write buffer sizes
+    pub ws_conf: WebSocketConfig,
+    /// permessage-deflate compression, None is disabled
+    #[cfg(feature = "ws_deflate")]
+    pub deflate: Option<WsDeflate>,
 }
 
 /// custom method
 impl WsClientConfig {
     /// create a websocket client config<br />
     /// conn_time_out default 10 secs<br />
-    /// read_time_out default 1 secs
+    /// read_time_out default 1 secs<br />
+    /// close_time_out default 3 secs
     pub fn new(name: String, ws_url: String, reconn: SocketReConn) -> Self {
         let log_head = format!("{}[{}]", name, ws_url);
         Self {
@@ -32,6 +58,14 @@ impl WsClientConfig {
             conn_time_out: Duration::from_secs(10),
             read_time_out: Duration::from_secs(1),
             reconn: reconn,
+            headers: Vec::new(),
+            protocols: Vec::new(),
+            refresh_headers: None,
+            keep_alive: None,
+            close_time_out: Duration::from_secs(3),
+            ws_conf: WebSocketConfig::default(),
+            #[cfg(feature = "ws_deflate")]
+            deflate: None,
         }
     }
 
@@ -46,4 +80,83 @@ impl WsClientConfig {
         self.read_time_out = time_out;
         self
     }
+
+    /// add custom request header, such as Authorization or Cookie
+    pub fn set_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// set the subprotocols requested in order of preference<br />
+    /// the subprotocol selected by websocket server can be obtained in [crate::ws::client::callback::WsClientCallBack::conn]
+    pub fn set_protocols(mut self, protocols: Vec<String>) -> Self {
+        self.protocols = protocols;
+        self
+    }
+
+    /// set the function to refresh request headers before each connect attempt
+    pub fn set_refresh_headers(mut self, f: impl Fn() -> Pin<Box<dyn Future<Output=Vec<(String, String)>> + Send>> + Sync + Send + 'static) -> Self {
+        self.refresh_headers = Some(Box::new(f));
+        self
+    }
+
+    /// set ping/pong keepalive<br />
+    /// if the websocket server does not reply pong in time, the connection will be closed and reconnected
+    pub fn set_keep_alive(mut self, keep_alive: KeepAlive) -> Self {
+        self.keep_alive = Some(keep_alive);
+        self
+    }
+
+    /// set how long to wait for the close reply of the websocket server<br />
+    /// if the websocket server does not reply close in time, the connection is closed directly
+    pub fn set_close_time_out(mut self, close_time_out: Duration) -> Self {
+        self.close_time_out = close_time_out;
+        self
+    }
+
+    /// set websocket protocol config
+    pub fn set_ws_conf(mut self, ws_conf: WebSocketConfig) -> Self {
+        self.ws_conf = ws_conf;
+        self
+    }
+
+    /// set the max size of a message, None is unlimited, default is 64 MiB<br />
+    /// the connection will be closed if the websocket server sends a larger message, see [crate::ws::client::callback::WsClientCallBack::capacity_exceeded]
+    pub fn set_max_message_size(mut self, max_message_size: Option<usize>) -> Self {
+        self.ws_conf = self.ws_conf.max_message_size(max_message_size);
+        self
+    }
+
+    /// set the max size of a frame, None is unlimited, default is 16 MiB
+    pub fn set_max_frame_size(mut self, max_frame_size: Option<usize>) -> Self {
+        self.ws_conf = self.ws_conf.max_frame_size(max_frame_size);
+        self
+    }
+
+    /// set the write buffer size, default is 128 KiB<br />
+    /// data is written to the stream once the buffer is larger than this
+    pub fn set_write_buffer_size(mut self, write_buffer_size: usize) -> Self {
+        self.ws_conf = self.ws_conf.write_buffer_size(write_buffer_size);
+        self
+    }
+
+    /// set the max write buffer size, default is unlimited<br />
+    /// sending fails if the buffer is larger than this, must be larger than write buffer size
+    pub fn set_max_write_buffer_size(mut self, max_write_buffer_size: usize) -> Self {
+        self.ws_conf = self.ws_conf.max_write_buffer_size(max_write_buffer_size);
+        self
+    }
+
+    /// set permessage-deflate compression, offered to the websocket server during the handshake<br />
+    /// only ws url is supported
+    #[cfg(feature = "ws_deflate")]
+    pub fn set_deflate(mut self, deflate: WsDeflate) -> Self {
+        self.deflate = Some(deflate);
+        self
+    }
+
+    /// get the read timeout, no more than the keepalive check time
+    pub(crate) fn get_read_time_out(&self) -> Duration {
+        self.keep_alive.map_or(self.read_time_out, |keep_alive| self.read_time_out.min(keep_alive.check_time()))
+    }
 }
@@ -0,0 +1,30 @@
+use tokio_tungstenite::tungstenite::handshake::client::Response;
+use tokio_tungstenite::tungstenite::http::{HeaderMap, StatusCode};
+
+/// the subprotocol header name
+const PROTOCOL: &str = "Sec-WebSocket-Protocol";
+
+/// websocket handshake response info of websocket server
+#[derive(Clone, Debug)]
+pub struct WsHandshake {
+    /// the response status
+    pub status: StatusCode,
+    /// the response headers
+    pub headers: HeaderMap,
+    /// the subprotocol selected by websocket server
+    pub protocol: Option<String>,
+}
+
+/// custom method
+impl WsHandshake {
+    /// create handshake info from the websocket server response
+    pub(crate) fn new(response: &Response) -> Self {
+        let protocol = response.headers().get(PROTOCOL).and_then(|protocol| protocol.to_str().ok()).map(str::to_string);
+        Self { status: response.status(), headers: response.headers().clone(), protocol }
+    }
+
+    /// get the response header value, return None if the header does not exist or is not visible ascii
+    pub fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+}
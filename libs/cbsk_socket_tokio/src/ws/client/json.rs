@@ -0,0 +1,88 @@
+use std::future::Future;
+use cbsk_base::{log, serde_json};
+use cbsk_base::serde::de::DeserializeOwned;
+use crate::ws::client::callback::{Bytes, CapacityError, CloseFrame, Frame, Utf8Bytes, WsClientCallBack};
+use crate::ws::client::handshake::WsHandshake;
+
+/// typed json websocket client callback, the text data is decoded into [WsClientJsonCallBack::Msg]<br />
+/// use [WsClientJson] to start websocket client with this callback
+pub trait WsClientJsonCallBack: Send + Sync + 'static {
+    /// the json message type, such as an enum tagged by the type field: #[serde(tag = "type")]
+    type Msg: DeserializeOwned + Send;
+
+    /// websocket client recv websocket server text data and decode success will call this method<br />
+    /// msg: the decoded websocket server data
+    fn recv_json(&self, msg: Self::Msg) -> impl Future<Output = ()> + Send;
+
+    /// the websocket server text data can not be decoded into [WsClientJsonCallBack::Msg]<br />
+    /// err: decode error<br />
+    /// text: the websocket server text data
+    fn decode_error(&self, err: serde_json::Error, text: Utf8Bytes) -> impl Future<Output = ()> + Send {
+        log::warn!("decode websocket server json data [{text}] error: {err:?}");
+        async {}
+    }
+}
+
+/// typed json websocket client callback adapter, the text data is passed to json,
+/// the other callbacks are passed to cb, the recv_text of cb is not called<br />
+/// example: WsClient::new(conf, WsClientJson::new(Chat, ChatConn).into())
+pub struct WsClientJson<J: WsClientJsonCallBack, C: WsClientCallBack> {
+    /// the typed json callback
+    pub json: J,
+    /// the websocket client callback of the other data
+    pub cb: C,
+}
+
+/// custom method
+impl<J: WsClientJsonCallBack, C: WsClientCallBack> WsClientJson<J, C> {
+    /// create a typed json callback adapter
+    pub fn new(json: J, cb: C) -> Self {
+        Self { json, cb }
+    }
+}
+
+/// support websocket client callback
+impl<J: WsClientJsonCallBack, C: WsClientCallBack> WsClientCallBack for WsClientJson<J, C> {
+    async fn conn(&self, handshake: WsHandshake) {
+        self.cb.conn(handshake).await
+    }
+
+    async fn dis_conn(&self, close: Option<CloseFrame>) {
+        self.cb.dis_conn(close).await
+    }
+
+    async fn re_conn(&self, num: i32) {
+        self.cb.re_conn(num).await
+    }
+
+    async fn recv_text(&self, text: Utf8Bytes) {
+        match serde_json::from_str(text.as_str()) {
+            Ok(msg) => { self.json.recv_json(msg).await }
+            Err(e) => { self.json.decode_error(e, text).await }
+        }
+    }
+
+    async fn recv_binary(&self, binary: Bytes) {
+        self.cb.recv_binary(binary).await
+    }
+
+    async fn recv_ping(&self, ping: Bytes) {
+        self.cb.recv_ping(ping).await
+    }
+
+    async fn recv_pong(&self, pong: Bytes) {
+        self.cb.recv_pong(pong).await
+    }
+
+    async fn recv_close(&self, close: Option<CloseFrame>) {
+        self.cb.recv_close(close).await
+    }
+
+    async fn recv_frame(&self, frame: Frame) {
+        self.cb.recv_frame(frame).await
+    }
+
+    async fn capacity_exceeded(&self, err: CapacityError) {
+        self.cb.capacity_exceeded(err).await
+    }
+}
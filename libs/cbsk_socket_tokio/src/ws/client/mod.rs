@@ -1,23 +1,49 @@
 use std::io;
+use std::pin::pin;
 use std::sync::Arc;
 use std::sync::atomic::Ordering;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use cbsk_base::{anyhow, log, tokio};
 use cbsk_base::tokio::net::TcpStream;
 use cbsk_base::tokio::sync::RwLock;
 use cbsk_base::tokio::task::JoinHandle;
-use futures_util::{SinkExt, StreamExt};
+use cbsk_socket::session;
+use cbsk_socket::session::ping::{PingState, PingTick};
+use cbsk_socket::trace::conn_span::ConnSpan;
+use futures_util::{SinkExt, Stream, StreamExt};
 use futures_util::stream::SplitStream;
-use tokio_tungstenite::{MaybeTlsStream, tungstenite, WebSocketStream};
+use tokio_tungstenite::tungstenite;
+#[cfg(not(feature = "ws_deflate"))]
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::handshake::client::{Request, Response};
+use tokio_tungstenite::tungstenite::http::{HeaderName, HeaderValue};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::frame::Frame;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
 use tokio_tungstenite::tungstenite::Message;
 use crate::ws::client::callback::WsClientCallBack;
 use crate::ws::client::config::WsClientConfig;
+use crate::ws::client::handshake::WsHandshake;
+use crate::ws::close::WsClose;
+#[cfg(feature = "ws_deflate")]
+use crate::ws::client::stream::WsClientStream;
+#[cfg(feature = "ws_deflate")]
+use crate::ws::deflate::{self, DeflateStream, Role};
 use crate::ws::client::ws_write::WsWrite;
 use crate::ws::ws_write_trait::WsWriteTrait;
 
 pub mod config;
 pub mod callback;
+pub mod handshake;
+pub mod json;
 mod ws_write;
+#[cfg(feature = "ws_deflate")]
+mod stream;
+
+/// the websocket stream of websocket client
+#[cfg(not(feature = "ws_deflate"))]
+pub(crate) type WsClientStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
 
 /// websocket client
 pub struct WsClient<C: WsClientCallBack> {
@@ -27,12 +53,25 @@ pub struct WsClient<C: WsClientCallBack> {
     pub cb: Arc<C>,
     /// websocket client writer
     pub(crate) write: Arc<RwLock<WsWrite>>,
+    /// the last connection span, a new span will be created each time the connection is successful
+    span: Arc<RwLock<ConnSpan>>,
+    /// keepalive ping state, reset each time the connection is successful
+    ping: Arc<PingState>,
+    /// closing handshake state, reset each time the connection is successful
+    ws_close: Arc<WsClose>,
 }
 
 /// support clone
 impl<C: WsClientCallBack> Clone for WsClient<C> {
     fn clone(&self) -> Self {
-        Self { conf: self.conf.clone(), cb: self.cb.clone(), write: self.write.clone() }
+        Self {
+            conf: self.conf.clone(),
+            cb: self.cb.clone(),
+            write: self.write.clone(),
+            span: self.span.clone(),
+            ping: self.ping.clone(),
+            ws_close: self.ws_close.clone(),
+        }
     }
 }
 
@@ -41,11 +80,12 @@ impl<C: WsClientCallBack> WsClient<C> {
     /// create websocket client<br />
     /// just create data, if you want to read data to recv method, you should be call start method
     pub fn new(conf: Arc<WsClientConfig>, cb: Arc<C>) -> Self {
-        Self { conf, cb, write: Arc::new(RwLock::new(WsWrite::default())) }
+        let ws_close = Arc::new(WsClose::new(conf.close_time_out));
+        Self { conf, cb, write: Arc::new(RwLock::new(WsWrite::default())), span: Arc::new(RwLock::default()), ping: Arc::new(PingState::default()), ws_close }
     }
 
     /// stop websocket server connect<br />
-    /// will shutdown tcp connection and will not new connection
+    /// will close websocket connection with close code 1000 and will not new connection, see [WsClient::shutdown]
     pub async fn stop(&self) {
         self.conf.reconn.enable.store(false, Ordering::Release);
         self.shutdown().await;
@@ -53,28 +93,37 @@ impl<C: WsClientCallBack> WsClient<C> {
 
     /// notify websocket to re connect<br />
     /// will shutdown websocket connection, if [`WsClientConfig`] reconn is disable<br />
-    /// will shutdown and create new websocket connection,if [`WsClientConfig`] reconn is enable
+    /// will shutdown and create new websocket connection,if [`WsClientConfig`] reconn is enable, see [WsClient::shutdown]
     pub async fn re_conn(&self) {
         self.shutdown().await;
     }
 
-    /// shutdown websocket server connect
+    /// send close to websocket server with close code 1000 without waiting for the reply, so it can be called in the recv callbacks<br />
+    /// the read loop ends when the close reply is received, or close_time_out is reached, use [WsWriteTrait::try_close] to wait for the reply
     async fn shutdown(&self) {
-        let mut write = self.write.write().await;
-        if let Some(write) = write.write.as_mut() {
-            if let Err(e) = write.close().await {
-                log::error!("shutdown websocket error: {e:?}");
+        if !self.is_connected().await { return; }
+        match self.try_send_close(Some(CloseFrame { code: CloseCode::Normal, reason: "".into() })).await {
+            Ok(()) => { self.ws_close.sent(); }
+            Err(e) => {
+                log::error!("{} close websocket error: {e:?}",self.conf.log_head);
+                self.ws_close.end();
             }
         }
 
         // as long as shutdown is called, write will be left blank directly
-        write.set_none();
+        self.write.write().await.set_none();
     }
 
     /// get has the websocket server connection been success
     pub async fn is_connected(&self) -> bool {
         self.write.read().await.write.is_some()
     }
+
+    /// get the last measured round trip time of keepalive ping<br />
+    /// None if keepalive is disabled or no pong has been received
+    pub fn get_latency(&self) -> Option<Duration> {
+        self.ping.get_latency()
+    }
 }
 
 /// tcp read logic
@@ -104,8 +153,8 @@ impl<C: WsClientCallBack> WsClient<C> {
             re_num += 1;
             let err =
                 match self.try_conn().await {
-                    Ok(ws_stream) => {
-                        self.read_spawn(ws_stream).await;
+                    Ok((ws_stream, handshake)) => {
+                        self.read_spawn(ws_stream, handshake).await;
                         return;
                     }
                     Err(e) => { e }
@@ -115,21 +164,28 @@ impl<C: WsClientCallBack> WsClient<C> {
             if !self.conf.reconn.enable.load(Ordering::Acquire) { return; }
 
             // re conn
-            self.cb.re_conn(re_num).await;
+            let span = self.span.read().await.clone();
+            span.re_conn(re_num);
+            span.instrument(self.cb.re_conn(re_num)).await;
             log::info!("{} websocket service will reconnect in {:?}",self.conf.log_head,self.conf.reconn.time);
             tokio::time::sleep(self.conf.reconn.time).await;
         }
     }
 
     /// read websocket server data
-    async fn read_spawn(&self, ws_stream: WebSocketStream<MaybeTlsStream<TcpStream>>) {
+    async fn read_spawn(&self, ws_stream: WsClientStream, handshake: WsHandshake) {
         let (write, read) = ws_stream.split();
         self.write.write().await.set_write(write);
+        self.ping.reset();
+        self.ws_close.reset();
 
         log::info!("{} started websocket server read data async success",self.conf.log_head);
-        self.cb.conn().await;
+        let span = ConnSpan::new("websocket client", self.conf.name.as_str(), self.conf.ws_url.as_str(), session::next_conn_id());
+        *self.span.write().await = span.clone();
+        span.conn();
+        span.instrument(self.cb.conn(handshake)).await;
 
-        if let Err(e) = self.try_read_spawn(read).await {
+        if let Err(e) = span.instrument(self.try_read_spawn(read)).await {
             // if the write is not closed, print the log.
             // otherwise, it is considered as actively closing the connection and there is no need to print the log
             if self.write.read().await.write.is_some() {
@@ -137,30 +193,30 @@ impl<C: WsClientCallBack> WsClient<C> {
             }
         }
 
-        // websocket read disabled, directly assume that websocket has been closed, simultaneously close read
-        self.shutdown().await;
-        self.cb.dis_conn().await;
+        // websocket read disabled, directly assume that websocket has been closed, simultaneously close write
+        self.write.write().await.set_none();
+        span.dis_conn();
+        span.instrument(self.cb.dis_conn(self.ws_close.get_recv())).await;
         log::info!("{} websocket server read data async is shutdown",self.conf.log_head);
     }
 
     /// try read data from websocket server
-    async fn try_read_spawn(&self, mut read: SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>) -> anyhow::Result<()> {
+    async fn try_read_spawn(&self, mut read: SplitStream<WsClientStream>) -> anyhow::Result<()> {
         loop {
-            let read = read.next();
+            self.keep_alive().await?;
+
+            let read = tokio::time::timeout(self.conf.get_read_time_out(), read.next());
+            let msg = tokio::select! {
+                msg = read => { msg }
+                // the close is not replied in time, end the connection directly
+                _ = self.ws_close.wait_end() => { return Ok(()); }
+            };
             let msg =
-                match tokio::time::timeout(self.conf.read_time_out, read).await {
+                match msg {
                     Ok(msg) => {
-                        cbsk_base::match_some_exec!(msg,{
-                            // read empty data, it is possible that ws has already been shut down
-                            if self.write.read().await.write.is_none(){
-                                return Ok(());
-                            }
-
-                            // if read empty data, and ws connection is normal, sleep 1 millis, and continue to next loop
-                            // read empty data, which may indicate a bug in tokio_tungstenite, so sleep for 1 millimeter and repeat the loop
-                            tokio::time::sleep(Duration::from_millis(1)).await;
-                            continue;
-                        })?
+                        // read None, the websocket connection has been closed
+                        let msg = cbsk_base::match_some_return!(msg,Ok(()));
+                        self.check_capacity(msg).await?
                     }
                     Err(_e) => {
                         // if ws has already been shut down, exit loop
@@ -177,20 +233,110 @@ impl<C: WsClientCallBack> WsClient<C> {
                 Message::Text(text) => { self.cb.recv_text(text).await }
                 Message::Binary(binary) => { self.cb.recv_binary(binary).await }
                 Message::Ping(ping) => { self.cb.recv_ping(ping).await }
-                Message::Pong(pong) => { self.cb.recv_pong(pong).await }
-                Message::Close(close) => { self.cb.recv_close(close).await }
+                Message::Pong(pong) => {
+                    // the reply of keepalive ping is not passed to recv_pong
+                    if self.ping.pong(pong.as_ref(), Instant::now()) { continue; }
+                    self.cb.recv_pong(pong).await
+                }
+                Message::Close(close) => {
+                    // the close reply is sent automatically, the next read will return None
+                    self.ws_close.recv(close.clone());
+                    self.cb.recv_close(close).await
+                }
                 Message::Frame(frame) => { self.cb.recv_frame(frame).await }
             }
         }
     }
 
+    /// if the websocket server exceeds the message size limits, call capacity_exceeded and send close with 1009
+    async fn check_capacity(&self, msg: tungstenite::Result<Message>) -> tungstenite::Result<Message> {
+        let capacity = match msg.as_ref() {
+            Err(tungstenite::Error::Capacity(e)) => { Some(*e) }
+            // the message exceeds the max size of permessage-deflate
+            #[cfg(feature = "ws_deflate")]
+            Err(tungstenite::Error::Io(e)) => { deflate::capacity_error(e) }
+            _ => { None }
+        };
+        if let Some(e) = capacity {
+            self.cb.capacity_exceeded(e).await;
+            let close = CloseFrame { code: CloseCode::Size, reason: e.to_string().into() };
+            if let Err(e) = self.try_send_close(Some(close)).await {
+                log::warn!("{} send websocket server close error: {e:?}",self.conf.log_head);
+            }
+        }
+        msg
+    }
+
+    /// send keepalive ping, return Err if the pong is not received in time
+    async fn keep_alive(&self) -> anyhow::Result<()> {
+        let keep_alive = cbsk_base::match_some_return!(self.conf.keep_alive.as_ref(),Ok(()));
+        match self.ping.tick(keep_alive, Instant::now()) {
+            PingTick::Idle => {}
+            PingTick::Ping(ping) => { self.try_send_ping(ping).await? }
+            PingTick::TimeOut => {
+                // the server may be gone, close with 1011 without waiting for the reply, the write is closed after the read loop ends
+                let close = CloseFrame { code: CloseCode::Error, reason: "pong time out".into() };
+                if let Ok(Err(e)) = tokio::time::timeout(self.ws_close.time_out, self.try_send_close(Some(close))).await {
+                    log::warn!("{} close websocket server error: {e:?}",self.conf.log_head);
+                }
+                return Err(anyhow::anyhow!("websocket server pong time out {:?}", keep_alive.pong_time_out));
+            }
+        }
+        Ok(())
+    }
+
     /// try connect websocket server
-    async fn try_conn(&self) -> anyhow::Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+    async fn try_conn(&self) -> anyhow::Result<(WsClientStream, WsHandshake)> {
         log::info!("{} try connect to websocket server",self.conf.log_head);
-        let ws_stream = tokio_tungstenite::connect_async(self.conf.ws_url.as_str());
-        let (ws_stream, _) = tokio::time::timeout(self.conf.conn_time_out, ws_stream).await??;
+        let mut request = self.conf.ws_url.as_str().into_client_request()?;
+        let headers = request.headers_mut();
+        for (name, value) in self.conf.headers.iter() {
+            headers.append(HeaderName::from_bytes(name.as_bytes())?, HeaderValue::from_str(value)?);
+        }
+        if !self.conf.protocols.is_empty() {
+            headers.insert("Sec-WebSocket-Protocol", HeaderValue::from_str(self.conf.protocols.join(", ").as_str())?);
+        }
+        #[cfg(feature = "ws_deflate")]
+        if let Some(conf) = self.conf.deflate.as_ref() {
+            deflate::offer(conf, headers);
+        }
+        // refreshed headers override the same name headers
+        if let Some(refresh_headers) = self.conf.refresh_headers.as_ref() {
+            for (name, value) in refresh_headers().await {
+                headers.insert(HeaderName::from_bytes(name.as_bytes())?, HeaderValue::from_str(value.as_str())?);
+            }
+        }
+
+        let ws_stream = self.connect(request);
+        let (ws_stream, response) = tokio::time::timeout(self.conf.conn_time_out, ws_stream).await??;
 
-        Ok(ws_stream)
+        Ok((ws_stream, WsHandshake::new(&response)))
+    }
+
+    /// connect websocket server by the request
+    #[cfg(not(feature = "ws_deflate"))]
+    async fn connect(&self, request: Request) -> anyhow::Result<(WsClientStream, Response)> {
+        Ok(tokio_tungstenite::connect_async_with_config(request, Some(self.conf.ws_conf), false).await?)
+    }
+
+    /// connect websocket server by the request<br />
+    /// if deflate is set, the permessage-deflate stream is placed under the websocket stream and only ws url is supported
+    #[cfg(feature = "ws_deflate")]
+    async fn connect(&self, request: Request) -> anyhow::Result<(WsClientStream, Response)> {
+        let Some(conf) = self.conf.deflate else {
+            let (ws_stream, response) = tokio_tungstenite::connect_async_with_config(request, Some(self.conf.ws_conf), false).await?;
+            return Ok((WsClientStream::Plain(ws_stream), response));
+        };
+
+        let uri = request.uri();
+        if uri.scheme_str() != Some("ws") { anyhow::bail!("permessage-deflate only supports ws url, but url is {uri}"); }
+        let host = uri.host().ok_or_else(|| anyhow::anyhow!("ws url {uri} has no host"))?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let tcp_stream = TcpStream::connect((host, uri.port_u16().unwrap_or(80))).await?;
+
+        let stream = DeflateStream::new(tcp_stream, Role::Client, Some(conf), self.conf.ws_conf.max_frame_size);
+        let (ws_stream, response) = tokio_tungstenite::client_async_with_config(request, stream, Some(self.conf.ws_conf)).await?;
+        Ok((WsClientStream::Deflate(ws_stream), response))
     }
 }
 
@@ -200,6 +346,10 @@ impl<C: WsClientCallBack> WsWriteTrait for WsClient<C> {
         self.conf.log_head.as_str()
     }
 
+    fn get_ws_close(&self) -> Option<&WsClose> {
+        Some(self.ws_close.as_ref())
+    }
+
     async fn try_send(&self, msg: Message) -> tungstenite::Result<()> {
         let mut write = self.write.write().await;
         let write = write.write.as_mut().ok_or_else(|| {
@@ -208,4 +358,19 @@ impl<C: WsClientCallBack> WsWriteTrait for WsClient<C> {
         write.send(msg).await?;
         write.flush().await
     }
+
+    async fn try_send_frames(&self, frames: impl Stream<Item = Frame>) -> tungstenite::Result<()> {
+        let mut frames = pin!(frames);
+        // the first frame is awaited before holding the write
+        let Some(first) = frames.next().await else { return Ok(()); };
+        let mut write = self.write.write().await;
+        let write = write.write.as_mut().ok_or_else(|| {
+            io::Error::from(io::ErrorKind::NotConnected)
+        })?;
+        write.feed(Message::Frame(first)).await?;
+        while let Some(frame) = frames.next().await {
+            write.feed(Message::Frame(frame)).await?;
+        }
+        write.flush().await
+    }
 }
@@ -0,0 +1,62 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use cbsk_base::tokio::net::TcpStream;
+use futures_util::{Sink, Stream};
+use tokio_tungstenite::{tungstenite, MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::tungstenite::Message;
+use crate::ws::deflate::DeflateStream;
+
+/// the websocket stream of websocket client<br />
+/// the permessage-deflate stream is only used if deflate is set, otherwise the connection is the same as without ws_deflate
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum WsClientStream {
+    /// deflate is not set, ws and wss url are supported
+    Plain(WebSocketStream<MaybeTlsStream<TcpStream>>),
+    /// deflate is set, only ws url is supported
+    Deflate(WebSocketStream<DeflateStream<TcpStream>>),
+}
+
+/// support read message
+impl Stream for WsClientStream {
+    type Item = tungstenite::Result<Message>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.get_mut() {
+            Self::Plain(stream) => { Pin::new(stream).poll_next(cx) }
+            Self::Deflate(stream) => { Pin::new(stream).poll_next(cx) }
+        }
+    }
+}
+
+/// support write message
+impl Sink<Message> for WsClientStream {
+    type Error = tungstenite::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.get_mut() {
+            Self::Plain(stream) => { Pin::new(stream).poll_ready(cx) }
+            Self::Deflate(stream) => { Pin::new(stream).poll_ready(cx) }
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        match self.get_mut() {
+            Self::Plain(stream) => { Pin::new(stream).start_send(item) }
+            Self::Deflate(stream) => { Pin::new(stream).start_send(item) }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.get_mut() {
+            Self::Plain(stream) => { Pin::new(stream).poll_flush(cx) }
+            Self::Deflate(stream) => { Pin::new(stream).poll_flush(cx) }
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.get_mut() {
+            Self::Plain(stream) => { Pin::new(stream).poll_close(cx) }
+            Self::Deflate(stream) => { Pin::new(stream).poll_close(cx) }
+        }
+    }
+}
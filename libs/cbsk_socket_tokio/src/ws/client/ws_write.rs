@@ -1,19 +1,18 @@
-use cbsk_base::tokio::net::TcpStream;
 use futures_util::stream::SplitSink;
-use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
 use tokio_tungstenite::tungstenite::Message;
+use crate::ws::client::WsClientStream;
 
 /// websocket write
 #[derive(Default)]
 pub struct WsWrite {
     /// websocket write
-    pub write: Option<SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>>,
+    pub write: Option<SplitSink<WsClientStream, Message>>,
 }
 
 /// custom method
 impl WsWrite {
     /// set write
-    pub fn set_write(&mut self, write: SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>) {
+    pub fn set_write(&mut self, write: SplitSink<WsClientStream, Message>) {
         self.write = Some(write);
     }
 
@@ -0,0 +1,105 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use cbsk_base::tokio;
+use cbsk_base::tokio::sync::Notify;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+
+/// websocket closing handshake state of one connection<br />
+/// the read loop records the close received from the peer, [crate::ws::ws_write_trait::WsWriteTrait::try_close] waits for it
+pub struct WsClose {
+    /// how long to wait for the close reply of the peer
+    pub time_out: Duration,
+    /// the close frame received from the peer
+    frame: Mutex<Option<CloseFrame>>,
+    /// has the close been received from the peer
+    received: AtomicBool,
+    /// the close has been sent without waiting, the read loop ends at this time
+    deadline: Mutex<Option<Instant>>,
+    /// notify the waiting close that the close of the peer has been received
+    received_notify: Notify,
+    /// does the read loop need to end
+    ended: AtomicBool,
+    /// notify the read loop to end
+    end_notify: Notify,
+}
+
+/// custom method
+impl WsClose {
+    /// create websocket closing handshake state
+    pub(crate) fn new(time_out: Duration) -> Self {
+        Self {
+            time_out,
+            frame: Mutex::default(),
+            received: AtomicBool::default(),
+            deadline: Mutex::default(),
+            received_notify: Notify::new(),
+            ended: AtomicBool::default(),
+            end_notify: Notify::new(),
+        }
+    }
+
+    /// reset the state for a new connection, the websocket client reuses the state when reconnect
+    #[cfg(feature = "ws_client")]
+    pub(crate) fn reset(&self) {
+        *self.frame.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        self.received.store(false, Ordering::Release);
+        *self.deadline.lock().unwrap_or_else(|e| e.into_inner()) = None;
+        self.ended.store(false, Ordering::Release);
+    }
+
+    /// record the close received from the peer and wake up the waiting close
+    pub(crate) fn recv(&self, close: Option<CloseFrame>) {
+        *self.frame.lock().unwrap_or_else(|e| e.into_inner()) = close;
+        self.received.store(true, Ordering::Release);
+        self.received_notify.notify_waiters();
+    }
+
+    /// has the close been received from the peer
+    pub fn is_received(&self) -> bool {
+        self.received.load(Ordering::Acquire)
+    }
+
+    /// get the close code and reason received from the peer<br />
+    /// None if the close is not received, or the peer sent close without close frame
+    pub fn get_recv(&self) -> Option<CloseFrame> {
+        self.frame.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// wait for the close of the peer, return false if it is not received in time
+    pub(crate) async fn wait_recv(&self) -> bool {
+        // the notified is created before checking, so that the notify between them will not be lost
+        let notified = self.received_notify.notified();
+        if self.is_received() { return true; }
+        tokio::time::timeout(self.time_out, notified).await.is_ok()
+    }
+
+    /// the close has been sent without waiting for the reply, the read loop ends if the peer does not reply in time
+    #[cfg(feature = "ws_client")]
+    pub(crate) fn sent(&self) {
+        *self.deadline.lock().unwrap_or_else(|e| e.into_inner()) = Some(Instant::now() + self.time_out);
+        // wake up the read loop to wait with the deadline
+        self.end_notify.notify_waiters();
+    }
+
+    /// notify the read loop to end, the connection is closed without waiting for the peer
+    pub(crate) fn end(&self) {
+        self.ended.store(true, Ordering::Release);
+        self.end_notify.notify_waiters();
+    }
+
+    /// wait until the read loop is notified to end, or the deadline of the sent close is reached
+    pub(crate) async fn wait_end(&self) {
+        loop {
+            let notified = self.end_notify.notified();
+            if self.ended.load(Ordering::Acquire) { return; }
+            let deadline = *self.deadline.lock().unwrap_or_else(|e| e.into_inner());
+            match deadline {
+                Some(deadline) => {
+                    if tokio::time::timeout_at(deadline.into(), notified).await.is_err() { return; }
+                }
+                None => { notified.await }
+            }
+        }
+    }
+}
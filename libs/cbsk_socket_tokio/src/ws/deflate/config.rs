@@ -0,0 +1,56 @@
+/// permessage-deflate compression config<br />
+/// the context takeover settings are negotiated during the handshake,
+/// a side that is asked to not take over the context resets the compression context after each message
+#[derive(Clone, Copy, Debug)]
+pub struct WsDeflate {
+    /// compression level, 0 to 9, default is 6
+    pub level: u32,
+    /// request the websocket server to reset the compression context after each message
+    pub server_no_context_takeover: bool,
+    /// request the websocket client to reset the compression context after each message
+    pub client_no_context_takeover: bool,
+    /// messages smaller than this length are sent uncompressed, default is 256 bytes
+    pub threshold: usize,
+    /// the max length of decompressed message, a larger message will call capacity_exceeded and close the connection, default is 16 MiB
+    pub max_size: usize,
+}
+
+/// support default
+impl Default for WsDeflate {
+    fn default() -> Self {
+        Self { level: 6, server_no_context_takeover: false, client_no_context_takeover: false, threshold: 256, max_size: 16 * 1024 * 1024 }
+    }
+}
+
+/// custom method
+impl WsDeflate {
+    /// set compression level, 0 to 9
+    pub fn set_level(mut self, level: u32) -> Self {
+        self.level = level.min(9);
+        self
+    }
+
+    /// set request the websocket server to reset the compression context after each message
+    pub fn set_server_no_context_takeover(mut self, server_no_context_takeover: bool) -> Self {
+        self.server_no_context_takeover = server_no_context_takeover;
+        self
+    }
+
+    /// set request the websocket client to reset the compression context after each message
+    pub fn set_client_no_context_takeover(mut self, client_no_context_takeover: bool) -> Self {
+        self.client_no_context_takeover = client_no_context_takeover;
+        self
+    }
+
+    /// set messages smaller than this length are sent uncompressed
+    pub fn set_threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// set the max length of decompressed message
+    pub fn set_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+}
@@ -0,0 +1,731 @@
+use std::io;
+use std::io::Cursor;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use cbsk_base::tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+use tokio_tungstenite::tungstenite::error::CapacityError;
+use tokio_tungstenite::tungstenite::http::{HeaderMap, HeaderValue};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::{Data, OpCode};
+use tokio_tungstenite::tungstenite::protocol::frame::FrameHeader;
+use crate::ws::deflate::config::WsDeflate;
+
+pub mod config;
+
+/// the extension header name
+const EXTENSIONS: &str = "Sec-WebSocket-Extensions";
+/// the extension name
+const PERMESSAGE_DEFLATE: &str = "permessage-deflate";
+/// the tail removed from each compressed message, see RFC 7692
+const DEFLATE_TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+/// the max length of the http handshake
+const MAX_HTTP_LEN: usize = 64 * 1024;
+/// stop accepting writes if this many transformed bytes are not written to the stream
+const MAX_WRITE_PENDING: usize = 128 * 1024;
+
+/// negotiated permessage-deflate parameters
+#[derive(Clone, Copy, Debug, Default)]
+struct Params {
+    /// the websocket server resets the compression context after each message
+    server_no_context_takeover: bool,
+    /// the websocket client resets the compression context after each message
+    client_no_context_takeover: bool,
+}
+
+/// custom method
+impl Params {
+    /// format as the extension header value
+    fn to_header(self) -> String {
+        let mut header = PERMESSAGE_DEFLATE.to_string();
+        if self.server_no_context_takeover { header.push_str("; server_no_context_takeover"); }
+        if self.client_no_context_takeover { header.push_str("; client_no_context_takeover"); }
+        header
+    }
+}
+
+/// find the first acceptable permessage-deflate element in the extension header values<br />
+/// response: the values are the accepted extension of the websocket server, otherwise the offers of the websocket client
+fn find_params<'a>(values: impl Iterator<Item=&'a str>, response: bool) -> Option<Params> {
+    values.flat_map(|value| value.split(',')).find_map(|extension| {
+        let mut params = extension.split(';').map(str::trim);
+        if !params.next()?.eq_ignore_ascii_case(PERMESSAGE_DEFLATE) { return None; }
+
+        let mut result = Params::default();
+        for param in params {
+            let (name, value) = param.split_once('=').map_or((param, None), |(name, value)| (name.trim(), Some(value.trim().trim_matches('"'))));
+            match name.to_ascii_lowercase().as_str() {
+                "server_no_context_takeover" => { result.server_no_context_takeover = true; }
+                "client_no_context_takeover" => { result.client_no_context_takeover = true; }
+                // the compression window can not be limited, so only the max window bits is acceptable
+                "server_max_window_bits" => { if value != Some("15") { return None; } }
+                // the offer of the websocket client only means that the window bits can be limited
+                "client_max_window_bits" => { if response && value.is_some_and(|value| value != "15") { return None; } }
+                _ => { return None; }
+            }
+        }
+        Some(result)
+    })
+}
+
+/// get the negotiated parameters from the http handshake response head<br />
+/// return None if permessage-deflate is not accepted by the websocket server
+fn negotiated(head: &[u8]) -> Option<Params> {
+    let head = std::str::from_utf8(head).ok()?;
+    // only the switching protocols response enables the extension
+    if !head.starts_with("HTTP/1.1 101") { return None; }
+
+    let values = head.split("\r\n").skip(1).filter_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim().eq_ignore_ascii_case(EXTENSIONS).then_some(value.trim())
+    });
+    find_params(values, true)
+}
+
+/// add the permessage-deflate offer to the websocket client request headers
+#[cfg(feature = "ws_client")]
+pub(crate) fn offer(conf: &WsDeflate, headers: &mut HeaderMap) {
+    let params = Params { server_no_context_takeover: conf.server_no_context_takeover, client_no_context_takeover: conf.client_no_context_takeover };
+    if let Ok(value) = HeaderValue::from_str(params.to_header().as_str()) {
+        headers.insert(EXTENSIONS, value);
+    }
+}
+
+/// accept the permessage-deflate offer of the websocket client, the accepted extension is added to the response headers
+#[cfg(feature = "ws_server")]
+pub(crate) fn accept(conf: &WsDeflate, request: &HeaderMap, response: &mut HeaderMap) {
+    let values = request.get_all(EXTENSIONS).iter().filter_map(|value| value.to_str().ok());
+    let Some(offer) = find_params(values, false) else { return; };
+    let params = Params {
+        server_no_context_takeover: offer.server_no_context_takeover || conf.server_no_context_takeover,
+        client_no_context_takeover: offer.client_no_context_takeover || conf.client_no_context_takeover,
+    };
+    if let Ok(value) = HeaderValue::from_str(params.to_header().as_str()) {
+        response.insert(EXTENSIONS, value);
+    }
+}
+
+/// create invalid data error
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("permessage-deflate {msg}"))
+}
+
+/// create message too long error, the read error can be converted back by [capacity_error]
+fn too_long(size: usize, max_size: usize) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, CapacityError::MessageTooLong { size, max_size })
+}
+
+/// get the capacity error if the message exceeds the max size of permessage-deflate
+pub(crate) fn capacity_error(e: &io::Error) -> Option<CapacityError> {
+    e.get_ref()?.downcast_ref::<CapacityError>().copied()
+}
+
+/// mask or unmask the frame payload
+fn apply_mask(payload: &mut [u8], mask: [u8; 4]) {
+    payload.iter_mut().enumerate().for_each(|(i, b)| *b ^= mask[i & 3]);
+}
+
+/// parse the frame header at the start of bytes, the payload may be incomplete<br />
+/// return the frame header, header length and payload length, None if the header is incomplete
+fn parse_header(bytes: &[u8]) -> io::Result<Option<(FrameHeader, usize, usize)>> {
+    let mut cursor = Cursor::new(bytes);
+    let Some((header, len)) = FrameHeader::parse(&mut cursor).map_err(io::Error::other)? else { return Ok(None); };
+    let head_len = usize::try_from(cursor.position()).map_err(io::Error::other)?;
+    let len = usize::try_from(len).map_err(io::Error::other)?;
+    Ok(Some((header, head_len, len)))
+}
+
+/// parse one complete frame at the start of bytes<br />
+/// return the frame header, header length and payload length, None if the frame is incomplete
+fn parse_frame(bytes: &[u8]) -> io::Result<Option<(FrameHeader, usize, usize)>> {
+    let Some((header, head_len, len)) = parse_header(bytes)? else { return Ok(None); };
+    if bytes.len() - head_len < len { return Ok(None); }
+    Ok(Some((header, head_len, len)))
+}
+
+/// format the frame with the payload, the payload is masked if the header has a mask
+fn format_frame(header: FrameHeader, mut payload: Vec<u8>, out: &mut Vec<u8>) -> io::Result<()> {
+    if let Some(mask) = header.mask { apply_mask(&mut payload, mask); }
+    header.format(payload.len() as u64, out).map_err(io::Error::other)?;
+    out.append(&mut payload);
+    Ok(())
+}
+
+/// which side of the websocket connection
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Role {
+    /// websocket server
+    Server,
+    /// websocket client
+    Client,
+}
+
+/// negotiated permessage-deflate compressor and decompressor
+struct Codec {
+    /// compressor of sent messages
+    compress: Compress,
+    /// decompressor of received messages, the context is always kept
+    decompress: Decompress,
+    /// reset the compression context after each sent message
+    no_context_takeover: bool,
+    /// messages smaller than this length are sent uncompressed
+    threshold: usize,
+    /// the max length of decompressed message
+    max_size: usize,
+    /// the first frame header and payload of the compressed message being received
+    inflating: Option<(FrameHeader, Vec<u8>)>,
+}
+
+/// custom method
+impl Codec {
+    /// create codec by the negotiated parameters
+    fn new(conf: WsDeflate, role: Role, params: Params) -> Self {
+        let no_context_takeover = match role {
+            Role::Server => { params.server_no_context_takeover }
+            Role::Client => { params.client_no_context_takeover }
+        };
+        Self {
+            compress: Compress::new(Compression::new(conf.level), false),
+            decompress: Decompress::new(false),
+            no_context_takeover,
+            threshold: conf.threshold,
+            max_size: conf.max_size,
+            inflating: None,
+        }
+    }
+
+    /// compress the message payload
+    fn deflate(&mut self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let start = self.compress.total_in();
+        let mut out = Vec::with_capacity(data.len() / 2 + 64);
+        loop {
+            if out.capacity() - out.len() < 64 { out.reserve(out.capacity()); }
+            let consumed = usize::try_from(self.compress.total_in() - start).map_err(io::Error::other)?;
+            self.compress.compress_vec(&data[consumed..], &mut out, FlushCompress::Sync).map_err(io::Error::other)?;
+            // the sync flush is complete if all data is consumed and the output is not full
+            if self.compress.total_in() - start == data.len() as u64 && out.len() < out.capacity() { break; }
+        }
+
+        if out.ends_with(&DEFLATE_TAIL) { out.truncate(out.len() - DEFLATE_TAIL.len()); }
+        if self.no_context_takeover { self.compress.reset(); }
+        Ok(out)
+    }
+
+    /// decompress the message payload
+    fn inflate(&mut self, mut data: Vec<u8>) -> io::Result<Vec<u8>> {
+        data.extend_from_slice(&DEFLATE_TAIL);
+        let start = self.decompress.total_in();
+        let mut out = Vec::with_capacity((data.len() * 2).min(self.max_size) + 64);
+        loop {
+            if out.capacity() - out.len() < 64 { out.reserve(out.capacity()); }
+            let (total_in, total_out) = (self.decompress.total_in(), self.decompress.total_out());
+            let consumed = usize::try_from(total_in - start).map_err(io::Error::other)?;
+            let status = self.decompress.decompress_vec(&data[consumed..], &mut out, FlushDecompress::Sync).map_err(io::Error::other)?;
+            if out.len() > self.max_size { return Err(too_long(out.len(), self.max_size)); }
+
+            let done = self.decompress.total_in() - start == data.len() as u64 && out.len() < out.capacity();
+            if done || status == Status::StreamEnd { break; }
+            // no progress with free output means the data is broken
+            if (total_in, total_out) == (self.decompress.total_in(), self.decompress.total_out()) && out.len() < out.capacity() {
+                return Err(invalid("data is broken"));
+            }
+        }
+        Ok(out)
+    }
+
+    /// check the received frame header, return true if the frame belongs to a compressed message<br />
+    /// the compressed message is limited by the max size before its payload is buffered
+    fn is_compressed(&self, header: &FrameHeader, len: usize) -> io::Result<bool> {
+        let continue_frame = header.opcode == OpCode::Data(Data::Continue);
+        match header.opcode {
+            OpCode::Control(_) => { return Ok(false); }
+            OpCode::Data(_) if self.inflating.is_some() && !continue_frame => { return Err(invalid("expected continuation frame")); }
+            OpCode::Data(_) if continue_frame && header.rsv1 => { return Err(invalid("continuation frame has rsv1")); }
+            // uncompressed message
+            OpCode::Data(_) if !header.rsv1 && self.inflating.is_none() => { return Ok(false); }
+            OpCode::Data(_) => {}
+        }
+
+        let size = self.inflating.as_ref().map_or(0, |(_, data)| data.len()).saturating_add(len);
+        if size > self.max_size { return Err(too_long(size, self.max_size)); }
+        Ok(true)
+    }
+
+    /// transform a received frame of compressed message, see [Self::is_compressed]<br />
+    /// the compressed message is decompressed to one uncompressed frame
+    fn recv_frame(&mut self, frame: &[u8], header: FrameHeader, head_len: usize, out: &mut Vec<u8>) -> io::Result<()> {
+        let (is_final, mask) = (header.is_final, header.mask);
+        let mut payload = frame[head_len..].to_vec();
+        if let Some(mask) = mask { apply_mask(&mut payload, mask); }
+        let (mut first, data) = match self.inflating.take() {
+            Some((first, mut data)) => {
+                data.append(&mut payload);
+                (first, data)
+            }
+            None => { (header, payload) }
+        };
+        if !is_final {
+            self.inflating = Some((first, data));
+            return Ok(());
+        }
+
+        first.rsv1 = false;
+        first.is_final = true;
+        first.mask = mask;
+        format_frame(first, self.inflate(data)?, out)
+    }
+
+    /// transform a sent frame, only the complete text or binary message is compressed
+    fn send_frame(&mut self, frame: &[u8], mut header: FrameHeader, head_len: usize, out: &mut Vec<u8>) -> io::Result<()> {
+        let whole = header.is_final && matches!(header.opcode, OpCode::Data(Data::Text) | OpCode::Data(Data::Binary));
+        if !whole || header.rsv1 || frame.len() - head_len < self.threshold {
+            out.extend_from_slice(frame);
+            return Ok(());
+        }
+
+        let mut payload = frame[head_len..].to_vec();
+        if let Some(mask) = header.mask { apply_mask(&mut payload, mask); }
+        header.rsv1 = true;
+        format_frame(header, self.deflate(payload.as_slice())?, out)
+    }
+}
+
+/// one direction of the stream
+#[derive(Default)]
+struct Direction {
+    /// bytes waiting to be transformed
+    raw: Vec<u8>,
+    /// transformed bytes
+    ready: Vec<u8>,
+    /// the consumed length of ready
+    ready_pos: usize,
+    /// the http handshake head has passed
+    http_done: bool,
+    /// the payload length of the uncompressed frame that is not yet passed through
+    passthrough: usize,
+}
+
+/// custom method
+impl Direction {
+    /// take the http handshake head, None if the head is incomplete
+    fn take_head(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let Some(end) = self.raw.windows(4).position(|w| w == b"\r\n\r\n") else {
+            if self.raw.len() > MAX_HTTP_LEN { return Err(invalid("http handshake is too long")); }
+            return Ok(None);
+        };
+        self.http_done = true;
+        Ok(Some(self.raw.drain(..end + 4).collect()))
+    }
+
+    /// transform all complete frames, the incomplete frame is kept
+    fn frames(&mut self, mut f: impl FnMut(&[u8], FrameHeader, usize, &mut Vec<u8>) -> io::Result<()>) -> io::Result<()> {
+        let mut pos = 0;
+        while let Some((header, head_len, len)) = parse_frame(&self.raw[pos..])? {
+            let end = pos + head_len + len;
+            f(&self.raw[pos..end], header, head_len, &mut self.ready)?;
+            pos = end;
+        }
+        self.raw.drain(..pos);
+        Ok(())
+    }
+
+    /// transform the received bytes, the compressed frame is kept until it is complete,
+    /// the uncompressed frame is passed through without waiting for its whole payload<br />
+    /// max_frame_size: the frame longer than this is rejected as soon as its header is received
+    fn recv_frames(&mut self, codec: &mut Codec, max_frame_size: Option<usize>) -> io::Result<()> {
+        loop {
+            if self.passthrough > 0 {
+                let n = self.passthrough.min(self.raw.len());
+                self.ready.extend(self.raw.drain(..n));
+                self.passthrough -= n;
+                if self.passthrough > 0 { return Ok(()); }
+            }
+
+            let Some((header, head_len, len)) = parse_header(&self.raw)? else { return Ok(()); };
+            if let Some(max_frame_size) = max_frame_size.filter(|max_frame_size| len > *max_frame_size) {
+                return Err(too_long(len, max_frame_size));
+            }
+            if !codec.is_compressed(&header, len)? {
+                self.ready.extend(self.raw.drain(..head_len));
+                self.passthrough = len;
+                continue;
+            }
+
+            let end = head_len + len;
+            if self.raw.len() < end { return Ok(()); }
+            codec.recv_frame(&self.raw[..end], header, head_len, &mut self.ready)?;
+            self.raw.drain(..end);
+        }
+    }
+
+    /// get the transformed bytes not consumed
+    fn pending(&self) -> &[u8] {
+        &self.ready[self.ready_pos..]
+    }
+
+    /// consume n transformed bytes
+    fn consume(&mut self, n: usize) {
+        self.ready_pos += n;
+        if self.ready_pos >= self.ready.len() {
+            self.ready.clear();
+            self.ready_pos = 0;
+        }
+    }
+}
+
+/// permessage-deflate stream, placed under the websocket stream<br />
+/// the http handshake is passed through, if permessage-deflate is accepted by the handshake response,
+/// compressed messages are decompressed before reading and sent messages are compressed,
+/// so the websocket stream only reads and writes uncompressed frames
+pub struct DeflateStream<S> {
+    /// the underlying stream
+    inner: S,
+    /// which side of the websocket connection
+    role: Role,
+    /// permessage-deflate config, None is disabled
+    conf: Option<WsDeflate>,
+    /// the negotiated codec, None if permessage-deflate is not negotiated
+    codec: Option<Codec>,
+    /// the max frame size of the websocket config, see [Direction::recv_frames]
+    max_frame_size: Option<usize>,
+    /// read direction
+    read: Direction,
+    /// write direction
+    write: Direction,
+}
+
+/// custom method
+impl<S> DeflateStream<S> {
+    /// create permessage-deflate stream<br />
+    /// max_frame_size: the max frame size of the websocket config, checked before the frame is buffered
+    pub(crate) fn new(inner: S, role: Role, conf: Option<WsDeflate>, max_frame_size: Option<usize>) -> Self {
+        Self { inner, role, conf, codec: None, max_frame_size, read: Direction::default(), write: Direction::default() }
+    }
+
+    /// get the underlying stream
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// get is permessage-deflate negotiated
+    pub fn is_negotiated(&self) -> bool {
+        self.codec.is_some()
+    }
+
+    /// create the codec if the handshake response accepts permessage-deflate
+    fn negotiate(&mut self, head: &[u8]) {
+        if let (Some(conf), Some(params)) = (self.conf, negotiated(head)) {
+            self.codec = Some(Codec::new(conf, self.role, params));
+        }
+    }
+
+    /// transform the bytes read from the underlying stream
+    fn process_read(&mut self) -> io::Result<()> {
+        if !self.read.http_done {
+            let Some(head) = self.read.take_head()? else { return Ok(()); };
+            // the websocket client reads the handshake response
+            if self.role == Role::Client { self.negotiate(head.as_slice()); }
+            self.read.ready.extend(head);
+        }
+
+        match self.codec.as_mut() {
+            Some(codec) => { self.read.recv_frames(codec, self.max_frame_size) }
+            None => {
+                self.read.ready.append(&mut self.read.raw);
+                Ok(())
+            }
+        }
+    }
+
+    /// transform the bytes written by the websocket stream
+    fn process_write(&mut self) -> io::Result<()> {
+        if !self.write.http_done {
+            let Some(head) = self.write.take_head()? else { return Ok(()); };
+            // the websocket server writes the handshake response
+            if self.role == Role::Server { self.negotiate(head.as_slice()); }
+            self.write.ready.extend(head);
+        }
+
+        match self.codec.as_mut() {
+            Some(codec) => { self.write.frames(|frame, header, head_len, out| codec.send_frame(frame, header, head_len, out)) }
+            None => {
+                self.write.ready.append(&mut self.write.raw);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// write the transformed bytes
+impl<S: AsyncWrite + Unpin> DeflateStream<S> {
+    /// write all transformed bytes to the underlying stream
+    fn poll_write_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while !self.write.pending().is_empty() {
+            let n = ready!(Pin::new(&mut self.inner).poll_write(cx, self.write.pending()))?;
+            if n == 0 { return Poll::Ready(Err(io::ErrorKind::WriteZero.into())); }
+            self.write.consume(n);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// support async read
+impl<S: AsyncRead + Unpin> AsyncRead for DeflateStream<S> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            let pending = this.read.pending();
+            if !pending.is_empty() {
+                let n = pending.len().min(buf.remaining());
+                buf.put_slice(&pending[..n]);
+                this.read.consume(n);
+                return Poll::Ready(Ok(()));
+            }
+
+            let mut chunk = [0u8; 8192];
+            let mut chunk_buf = ReadBuf::new(&mut chunk);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut chunk_buf))?;
+            // the underlying stream is closed
+            if chunk_buf.filled().is_empty() { return Poll::Ready(Ok(())); }
+            this.read.raw.extend_from_slice(chunk_buf.filled());
+            this.process_read()?;
+        }
+    }
+}
+
+/// support async write
+impl<S: AsyncWrite + Unpin> AsyncWrite for DeflateStream<S> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        // apply back pressure if too many transformed bytes are not written
+        if this.write.pending().len() >= MAX_WRITE_PENDING {
+            ready!(this.poll_write_pending(cx))?;
+        }
+
+        this.write.raw.extend_from_slice(buf);
+        this.process_write()?;
+        // write as much as possible, the rest will be written by the next write or flush
+        if let Poll::Ready(Err(e)) = this.poll_write_pending(cx) { return Poll::Ready(Err(e)); }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_pending(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_pending(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_tungstenite::tungstenite::protocol::frame::coding::{Control, Data, OpCode};
+    use tokio_tungstenite::tungstenite::protocol::frame::FrameHeader;
+    use crate::ws::deflate::config::WsDeflate;
+    use crate::ws::deflate::{apply_mask, capacity_error, find_params, format_frame, negotiated, parse_frame, Codec, Direction, Params, Role};
+
+    /// a large enough message to be compressed
+    const TEXT: &[u8] = b"the quick brown fox jumps over the lazy dog, the quick brown fox jumps over the lazy dog";
+
+    /// the websocket client sends masked frames
+    const MASK: Option<[u8; 4]> = Some([1, 2, 3, 4]);
+
+    fn header(opcode: OpCode, is_final: bool, rsv1: bool, mask: Option<[u8; 4]>) -> FrameHeader {
+        FrameHeader { is_final, rsv1, rsv2: false, rsv3: false, opcode, mask }
+    }
+
+    fn frame(header: FrameHeader, payload: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        format_frame(header, payload.to_vec(), &mut out).unwrap();
+        out
+    }
+
+    /// parse all frames, the payload is unmasked
+    fn parse_all(mut bytes: &[u8]) -> Vec<(FrameHeader, Vec<u8>)> {
+        let mut frames = Vec::new();
+        while let Some((header, head_len, len)) = parse_frame(bytes).unwrap() {
+            let mut payload = bytes[head_len..head_len + len].to_vec();
+            if let Some(mask) = header.mask { apply_mask(&mut payload, mask); }
+            frames.push((header, payload));
+            bytes = &bytes[head_len + len..];
+        }
+        assert!(bytes.is_empty());
+        frames
+    }
+
+    fn send(codec: &mut Codec, frame: &[u8]) -> Vec<u8> {
+        let (header, head_len, _) = parse_frame(frame).unwrap().unwrap();
+        let mut out = Vec::new();
+        codec.send_frame(frame, header, head_len, &mut out).unwrap();
+        out
+    }
+
+    fn recv(codec: &mut Codec, frame: &[u8]) -> std::io::Result<Vec<u8>> {
+        let mut read = Direction { raw: frame.to_vec(), http_done: true, ..Direction::default() };
+        read.recv_frames(codec, None)?;
+        assert!(read.raw.is_empty());
+        Ok(read.ready)
+    }
+
+    /// the websocket client and server codec
+    fn codecs(params: Params) -> (Codec, Codec) {
+        let conf = WsDeflate::default().set_threshold(16);
+        (Codec::new(conf, Role::Client, params), Codec::new(conf, Role::Server, params))
+    }
+
+    #[test]
+    fn round_trip() {
+        let (mut client, mut server) = codecs(Params::default());
+        for opcode in [OpCode::Data(Data::Text), OpCode::Data(Data::Binary)] {
+            let sent = send(&mut client, &frame(header(opcode, true, false, MASK), TEXT));
+            let [(sent_header, compressed)] = parse_all(&sent).try_into().unwrap();
+            assert!(sent_header.rsv1);
+            assert!(compressed.len() < TEXT.len());
+
+            let received = recv(&mut server, &sent).unwrap();
+            let [(received_header, payload)] = parse_all(&received).try_into().unwrap();
+            assert!(!received_header.rsv1);
+            assert_eq!(received_header.opcode, opcode);
+            assert_eq!(payload, TEXT);
+        }
+
+        // small messages and control frames are not compressed
+        for frame in [frame(header(OpCode::Data(Data::Text), true, false, MASK), b"hi"), frame(header(OpCode::Control(Control::Ping), true, false, MASK), TEXT)] {
+            let sent = send(&mut client, &frame);
+            assert_eq!(sent, frame);
+            assert_eq!(recv(&mut server, &sent).unwrap(), frame);
+        }
+    }
+
+    #[test]
+    fn fragmented_message() {
+        let (mut client, mut server) = codecs(Params::default());
+        let mut compressed = client.deflate(TEXT).unwrap();
+        let tail = compressed.split_off(compressed.len() / 2);
+        let middle = compressed.split_off(compressed.len() / 2);
+
+        assert!(recv(&mut server, &frame(header(OpCode::Data(Data::Text), false, true, MASK), &compressed)).unwrap().is_empty());
+        // control frames can be interleaved with the fragments
+        let ping = frame(header(OpCode::Control(Control::Ping), true, false, MASK), b"ping");
+        assert_eq!(recv(&mut server, &ping).unwrap(), ping);
+        assert!(recv(&mut server, &frame(header(OpCode::Data(Data::Continue), false, false, MASK), &middle)).unwrap().is_empty());
+        let received = recv(&mut server, &frame(header(OpCode::Data(Data::Continue), true, false, MASK), &tail)).unwrap();
+
+        let [(received_header, payload)] = parse_all(&received).try_into().unwrap();
+        assert!(received_header.is_final);
+        assert!(!received_header.rsv1);
+        assert_eq!(received_header.opcode, OpCode::Data(Data::Text));
+        assert_eq!(payload, TEXT);
+
+        // a new message is not allowed before the fragmented message is finished
+        assert!(recv(&mut server, &frame(header(OpCode::Data(Data::Text), false, true, MASK), &compressed)).unwrap().is_empty());
+        assert!(recv(&mut server, &frame(header(OpCode::Data(Data::Text), true, false, MASK), b"hi")).is_err());
+    }
+
+    #[test]
+    fn context_takeover() {
+        // the context is kept, the same message is compressed shorter the second time
+        let (mut client, mut server) = codecs(Params::default());
+        let first = client.deflate(TEXT).unwrap();
+        let second = client.deflate(TEXT).unwrap();
+        assert!(second.len() < first.len());
+        assert_eq!(server.inflate(first).unwrap(), TEXT);
+        assert_eq!(server.inflate(second).unwrap(), TEXT);
+
+        // the context is reset after each message, the same message is compressed the same
+        let (mut client, mut server) = codecs(Params { server_no_context_takeover: false, client_no_context_takeover: true });
+        let first = client.deflate(TEXT).unwrap();
+        let second = client.deflate(TEXT).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(server.inflate(first).unwrap(), TEXT);
+        assert_eq!(server.inflate(second).unwrap(), TEXT);
+
+        // only the side that is asked resets the context
+        let first = server.deflate(TEXT).unwrap();
+        assert!(server.deflate(TEXT).unwrap().len() < first.len());
+    }
+
+    #[test]
+    fn max_size() {
+        let params = Params::default();
+        let mut client = Codec::new(WsDeflate::default().set_threshold(16), Role::Client, params);
+        let mut server = Codec::new(WsDeflate::default().set_max_size(16), Role::Server, params);
+        let sent = send(&mut client, &frame(header(OpCode::Data(Data::Text), true, false, MASK), TEXT));
+
+        let e = recv(&mut server, &sent).unwrap_err();
+        let capacity = capacity_error(&e).unwrap();
+        assert!(capacity.to_string().contains("16"), "{capacity}");
+        assert!(capacity_error(&std::io::Error::other("other")).is_none());
+    }
+
+    #[test]
+    fn frame_limit_before_buffering() {
+        let (_, mut server) = codecs(Params::default());
+        let server_max_size = Codec::new(WsDeflate::default().set_max_size(16), Role::Server, Params::default());
+        // only the frame header is received, the payload of 2^62 bytes is announced
+        let mut head = Vec::new();
+        header(OpCode::Data(Data::Binary), true, true, MASK).format(1 << 62, &mut head).unwrap();
+
+        for (mut codec, max_frame_size) in [(server, Some(1024)), (server_max_size, None)] {
+            let mut read = Direction { raw: head.clone(), http_done: true, ..Direction::default() };
+            let e = read.recv_frames(&mut codec, max_frame_size).unwrap_err();
+            assert!(capacity_error(&e).is_some(), "{e}");
+        }
+
+        // the uncompressed frame is passed through before its payload is complete
+        server = codecs(Params::default()).1;
+        let sent = frame(header(OpCode::Data(Data::Binary), true, false, MASK), TEXT);
+        let mut read = Direction { raw: sent[..10].to_vec(), http_done: true, ..Direction::default() };
+        read.recv_frames(&mut server, Some(1024)).unwrap();
+        assert_eq!(read.ready, sent[..10]);
+        assert!(read.raw.is_empty());
+        read.raw.extend_from_slice(&sent[10..]);
+        read.recv_frames(&mut server, Some(1024)).unwrap();
+        assert_eq!(read.ready, sent);
+        assert_eq!(read.passthrough, 0);
+    }
+
+    #[test]
+    fn extension_negotiation() {
+        // the offer of the websocket client
+        let offer = find_params(["x-webkit-deflate-frame, permessage-deflate; client_max_window_bits; server_no_context_takeover"].into_iter(), false).unwrap();
+        assert!(offer.server_no_context_takeover);
+        assert!(!offer.client_no_context_takeover);
+        assert!(find_params(["permessage-deflate; server_max_window_bits=10"].into_iter(), false).is_none());
+        assert!(find_params(["permessage-deflate; unknown"].into_iter(), false).is_none());
+        // the first acceptable element is used
+        assert!(find_params(["permessage-deflate; server_max_window_bits=10, permessage-deflate; client_no_context_takeover"].into_iter(), false).unwrap().client_no_context_takeover);
+
+        // the response of the websocket server
+        let params = negotiated(b"HTTP/1.1 101 Switching Protocols\r\nsec-websocket-extensions: permessage-deflate; client_no_context_takeover\r\n\r\n").unwrap();
+        assert!(params.client_no_context_takeover);
+        assert!(!params.server_no_context_takeover);
+        assert!(negotiated(b"HTTP/1.1 101 Switching Protocols\r\nSec-WebSocket-Extensions: permessage-deflate; client_max_window_bits=10\r\n\r\n").is_none());
+        assert!(negotiated(b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\r\n").is_none());
+        assert!(negotiated(b"HTTP/1.1 400 Bad Request\r\nSec-WebSocket-Extensions: permessage-deflate\r\n\r\n").is_none());
+    }
+
+    #[cfg(all(feature = "ws_client", feature = "ws_server"))]
+    #[test]
+    fn offer_and_accept() {
+        use tokio_tungstenite::tungstenite::http::HeaderMap;
+
+        let mut request = HeaderMap::new();
+        crate::ws::deflate::offer(&WsDeflate::default().set_client_no_context_takeover(true), &mut request);
+        assert_eq!(request.get("Sec-WebSocket-Extensions").unwrap(), "permessage-deflate; client_no_context_takeover");
+
+        // the websocket server adds its own context takeover request
+        let mut response = HeaderMap::new();
+        crate::ws::deflate::accept(&WsDeflate::default().set_server_no_context_takeover(true), &request, &mut response);
+        assert_eq!(response.get("Sec-WebSocket-Extensions").unwrap(), "permessage-deflate; server_no_context_takeover; client_no_context_takeover");
+
+        // the websocket client does not offer permessage-deflate
+        let mut response = HeaderMap::new();
+        crate::ws::deflate::accept(&WsDeflate::default(), &HeaderMap::new(), &mut response);
+        assert!(response.is_empty());
+    }
+}
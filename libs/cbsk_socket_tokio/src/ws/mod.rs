@@ -1,4 +1,7 @@
 pub mod ws_write_trait;
+pub mod close;
+#[cfg(feature = "ws_deflate")]
+pub mod deflate;
 #[cfg(feature = "ws_client")]
 pub mod client;
 #[cfg(feature = "ws_server")]
@@ -1,22 +1,38 @@
 use crate::ws::server::client::WsServerClient;
+use crate::ws::server::handshake::{ErrorResponse, Request, Response};
+use cbsk_socket::session::attrs::SessionAttrs;
 use cbsk_base::log;
 use std::future::Future;
 use std::sync::Arc;
+pub use tokio_tungstenite::tungstenite::error::CapacityError;
+pub use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
 pub use tokio_tungstenite::tungstenite::protocol::frame::Frame;
 pub use tokio_tungstenite::tungstenite::protocol::CloseFrame;
 pub use tokio_tungstenite::tungstenite::{Bytes, Utf8Bytes};
 
 /// websocket connect and read data callback
 pub trait WsServerCallBack: Send + Sync + 'static {
+    /// inspect the websocket handshake request, called before the websocket client is created<br />
+    /// request: the request path, query string and headers, such as Authorization and Sec-WebSocket-Protocol<br />
+    /// response: the response to be sent, a subprotocol can be selected by [crate::ws::server::handshake::select_protocol]<br />
+    /// attrs: the session attributes of the websocket client, can be obtained later by [WsServerClient::attrs]<br />
+    /// return Err to reject the websocket client, see [crate::ws::server::handshake::reject]
+    #[allow(clippy::result_large_err)]
+    fn on_handshake(&self, request: &Request, response: Response, attrs: &SessionAttrs) -> Result<Response, ErrorResponse> {
+        let _ = (request, attrs);
+        Ok(response)
+    }
+
     /// a new websocket client come in
     fn conn(&self, client: Arc<WsServerClient>) -> impl Future<Output = ()> + Send {
         log::info!("{} websocket client connected", client.log_head);
         async {}
     }
 
-    /// the websocket client disconnected
-    fn dis_conn(&self, client: Arc<WsServerClient>) -> impl Future<Output = ()> + Send {
-        log::info!("{} websocket client disconnect", client.log_head);
+    /// the websocket client disconnected<br />
+    /// close: the close code and reason received from the websocket client, None if the connection is closed without close frame
+    fn dis_conn(&self, close: Option<CloseFrame>, client: Arc<WsServerClient>) -> impl Future<Output = ()> + Send {
+        log::info!("{} websocket client disconnect, close: {close:?}", client.log_head);
         async {}
     }
 
@@ -98,4 +114,18 @@ pub trait WsServerCallBack: Send + Sync + 'static {
         );
         async {}
     }
+
+    /// the websocket client sent a message or frame larger than the limits of [crate::ws::server::config::WsServerConfig::ws_conf]<br />
+    /// after this method, a close with code 1009 is sent and the websocket client will be closed
+    fn capacity_exceeded(
+        &self,
+        err: CapacityError,
+        client: Arc<WsServerClient>,
+    ) -> impl Future<Output = ()> + Send {
+        log::warn!(
+            "{} websocket client exceeded the capacity: {err}",
+            client.log_head
+        );
+        async {}
+    }
 }
@@ -1,30 +1,114 @@
 use std::net::SocketAddr;
+use std::pin::pin;
 use std::sync::Arc;
-use cbsk_base::tokio::net::TcpStream;
+use std::time::Duration;
 use cbsk_base::tokio::sync::RwLock;
-use futures_util::SinkExt;
+use cbsk_socket::metrics::conn_metrics::ConnMetrics;
+use cbsk_socket::metrics::server_metrics::ServerMetrics;
+use cbsk_socket::session;
+use cbsk_socket::session::attr_trait::AttrTrait;
+use cbsk_socket::session::attrs::SessionAttrs;
+use cbsk_socket::session::ping::PingState;
+use cbsk_socket::trace::conn_span::ConnSpan;
+use futures_util::{SinkExt, Stream, StreamExt};
 use futures_util::stream::SplitSink;
+use tokio_tungstenite::tungstenite::protocol::frame::Frame;
 use tokio_tungstenite::tungstenite::Message;
-use tokio_tungstenite::WebSocketStream;
+use crate::ws::close::WsClose;
+use crate::ws::server::WsServerStream;
 use crate::ws::server::config::WsServerConfig;
+use crate::ws::server::handshake::WsHandshake;
+use crate::ws::server::room::WsRooms;
 use crate::ws::ws_write_trait::WsWriteTrait;
 
 /// websocket client
 pub struct WsServerClient {
     /// websocket client addr
     pub addr: SocketAddr,
+    /// connection id, see [session::next_conn_id]
+    pub conn_id: u64,
     /// internal log name
     pub log_head: String,
+    /// connection span, read loop and callbacks run in this span
+    pub span: ConnSpan,
+    /// session attributes, see [AttrTrait]
+    pub attrs: SessionAttrs,
+    /// websocket client counters
+    pub metrics: Arc<ConnMetrics>,
+    /// the handshake request uri, headers and selected subprotocol
+    pub handshake: WsHandshake,
+    /// the rooms of the websocket server, can be used to publish message to other clients
+    pub rooms: Arc<WsRooms>,
+    /// keepalive ping state
+    pub(crate) ping: PingState,
+    /// closing handshake state, see [WsWriteTrait::try_close]
+    pub(crate) ws_close: WsClose,
     /// websocket client write
-    write: Arc<RwLock<SplitSink<WebSocketStream<TcpStream>, Message>>>,
+    write: Arc<RwLock<SplitSink<WsServerStream, Message>>>,
 }
 
 /// custom method
 impl WsServerClient {
     /// create tcp server client
-    pub(crate) fn new(addr: SocketAddr, conf: &WsServerConfig, writer: SplitSink<WebSocketStream<TcpStream>, Message>) -> Self {
+    pub(crate) fn new(addr: SocketAddr, conf: &WsServerConfig, writer: SplitSink<WsServerStream, Message>, server_metrics: Arc<ServerMetrics>, rooms: Arc<WsRooms>, attrs: SessionAttrs, handshake: WsHandshake) -> Self {
         let log_head = format!("{} tcp client[{}]", conf.name, addr);
-        Self { addr, log_head, write: RwLock::new(writer).into() }
+        let conn_id = session::next_conn_id();
+        Self {
+            addr,
+            conn_id,
+            log_head,
+            span: ConnSpan::new("websocket server client", conf.name.as_str(), addr, conn_id),
+            attrs,
+            metrics: ConnMetrics::new(server_metrics).into(),
+            handshake,
+            rooms,
+            ping: PingState::default(),
+            ws_close: WsClose::new(conf.close_time_out),
+            write: RwLock::new(writer).into(),
+        }
+    }
+}
+
+/// support keepalive
+impl WsServerClient {
+    /// close the websocket client write, the connection is closed even if the client is still held
+    pub(crate) async fn close_write(&self) -> tokio_tungstenite::tungstenite::Result<()> {
+        self.write.write().await.close().await
+    }
+
+    /// get the last measured round trip time of keepalive ping<br />
+    /// None if keepalive is disabled or no pong has been received
+    pub fn get_latency(&self) -> Option<Duration> {
+        self.ping.get_latency()
+    }
+}
+
+/// support rooms
+impl WsServerClient {
+    /// join the room, see [WsRooms::join]
+    pub fn join(self: &Arc<Self>, room: &str) -> bool {
+        self.rooms.join(room, self.clone())
+    }
+
+    /// leave the room, see [WsRooms::leave]
+    pub fn leave(&self, room: &str) -> bool {
+        self.rooms.leave(room, self.conn_id)
+    }
+
+    /// get the rooms joined by this client
+    pub fn get_joined_rooms(&self) -> Vec<String> {
+        self.rooms.get_joined(self.conn_id)
+    }
+}
+
+/// support session attr trait
+impl AttrTrait for WsServerClient {
+    fn get_conn_id(&self) -> u64 {
+        self.conn_id
+    }
+
+    fn get_attrs(&self) -> &SessionAttrs {
+        &self.attrs
     }
 }
 
@@ -33,9 +117,32 @@ impl WsWriteTrait for WsServerClient {
         self.log_head.as_str()
     }
 
+    fn get_ws_close(&self) -> Option<&WsClose> {
+        Some(&self.ws_close)
+    }
+
     async fn try_send(&self, msg: Message) -> tokio_tungstenite::tungstenite::Result<()> {
+        let len = msg.len();
         let mut write = self.write.write().await;
         write.send(msg).await?;
-        write.flush().await
+        write.flush().await?;
+        self.metrics.add_send(len);
+        Ok(())
+    }
+
+    async fn try_send_frames(&self, frames: impl Stream<Item = Frame>) -> tokio_tungstenite::tungstenite::Result<()> {
+        let mut frames = pin!(frames);
+        // the first frame is awaited before holding the write
+        let Some(first) = frames.next().await else { return Ok(()); };
+        let mut write = self.write.write().await;
+        let mut len = first.len();
+        write.feed(Message::Frame(first)).await?;
+        while let Some(frame) = frames.next().await {
+            len += frame.len();
+            write.feed(Message::Frame(frame)).await?;
+        }
+        write.flush().await?;
+        self.metrics.add_send(len);
+        Ok(())
     }
 }
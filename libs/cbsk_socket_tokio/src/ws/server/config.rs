@@ -1,5 +1,9 @@
 use std::net::SocketAddr;
 use std::time::Duration;
+use cbsk_socket::config::keep_alive::KeepAlive;
+pub use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+#[cfg(feature = "ws_deflate")]
+use crate::ws::deflate::config::WsDeflate;
 
 /// websocket server config
 pub struct WsServerConfig {
@@ -13,6 +17,15 @@ pub struct WsServerConfig {
     pub read_time_out: Duration,
     /// is enable log printing
     pub log: bool,
+    /// ping/pong keepalive, None is disabled
+    pub keep_alive: Option<KeepAlive>,
+    /// how long to wait for the close reply of the websocket client when closing the connection
+    pub close_time_out: Duration,
+    /// websocket protocol config, such as max message size, max frame size and write buffer sizes
+    pub ws_conf: WebSocketConfig,
+    /// permessage-deflate compression, None is disabled
+    #[cfg(feature = "ws_deflate")]
+    pub deflate: Option<WsDeflate>,
     // TODO TLS config coming soon
 }
 
@@ -24,7 +37,18 @@ impl WsServerConfig {
     /// log: is enable log printing
     pub fn new(name: String, addr: SocketAddr, log: bool) -> Self {
         let log_head = format!("{}[{}]", name, addr);
-        Self { name, addr, log_head, read_time_out: Duration::from_secs(1), log }
+        Self {
+            name,
+            addr,
+            log_head,
+            read_time_out: Duration::from_secs(1),
+            log,
+            keep_alive: None,
+            close_time_out: Duration::from_secs(3),
+            ws_conf: WebSocketConfig::default(),
+            #[cfg(feature = "ws_deflate")]
+            deflate: None,
+        }
     }
 
     /// set name
@@ -50,4 +74,64 @@ impl WsServerConfig {
         self.log = log;
         self
     }
+
+    /// set ping/pong keepalive<br />
+    /// a client that does not reply pong in time will be closed
+    pub fn set_keep_alive(mut self, keep_alive: KeepAlive) -> Self {
+        self.keep_alive = Some(keep_alive);
+        self
+    }
+
+    /// set how long to wait for the close reply of the websocket client, default 3 secs<br />
+    /// if the websocket client does not reply close in time, the connection is closed directly
+    pub fn set_close_time_out(mut self, close_time_out: Duration) -> Self {
+        self.close_time_out = close_time_out;
+        self
+    }
+
+    /// set websocket protocol config
+    pub fn set_ws_conf(mut self, ws_conf: WebSocketConfig) -> Self {
+        self.ws_conf = ws_conf;
+        self
+    }
+
+    /// set the max size of a message, None is unlimited, default is 64 MiB<br />
+    /// the websocket client sending a larger message will be closed, see [crate::ws::server::callback::WsServerCallBack::capacity_exceeded]
+    pub fn set_max_message_size(mut self, max_message_size: Option<usize>) -> Self {
+        self.ws_conf = self.ws_conf.max_message_size(max_message_size);
+        self
+    }
+
+    /// set the max size of a frame, None is unlimited, default is 16 MiB
+    pub fn set_max_frame_size(mut self, max_frame_size: Option<usize>) -> Self {
+        self.ws_conf = self.ws_conf.max_frame_size(max_frame_size);
+        self
+    }
+
+    /// set the write buffer size, default is 128 KiB<br />
+    /// data is written to the stream once the buffer is larger than this
+    pub fn set_write_buffer_size(mut self, write_buffer_size: usize) -> Self {
+        self.ws_conf = self.ws_conf.write_buffer_size(write_buffer_size);
+        self
+    }
+
+    /// set the max write buffer size, default is unlimited<br />
+    /// sending fails if the buffer is larger than this, must be larger than write buffer size
+    pub fn set_max_write_buffer_size(mut self, max_write_buffer_size: usize) -> Self {
+        self.ws_conf = self.ws_conf.max_write_buffer_size(max_write_buffer_size);
+        self
+    }
+
+    /// set permessage-deflate compression<br />
+    /// only used if the websocket client offers permessage-deflate during the handshake
+    #[cfg(feature = "ws_deflate")]
+    pub fn set_deflate(mut self, deflate: WsDeflate) -> Self {
+        self.deflate = Some(deflate);
+        self
+    }
+
+    /// get the read timeout, no more than the keepalive check time
+    pub(crate) fn get_read_time_out(&self) -> Duration {
+        self.keep_alive.map_or(self.read_time_out, |keep_alive| self.read_time_out.min(keep_alive.check_time()))
+    }
 }
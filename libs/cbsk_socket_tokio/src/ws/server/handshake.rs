@@ -0,0 +1,66 @@
+use tokio_tungstenite::tungstenite::http::{HeaderMap, HeaderValue, StatusCode, Uri};
+pub use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+pub use tokio_tungstenite::tungstenite::http;
+
+/// the subprotocol header name
+const PROTOCOL: &str = "Sec-WebSocket-Protocol";
+
+/// websocket handshake request info, saved after the handshake is successful
+#[derive(Clone, Debug)]
+pub struct WsHandshake {
+    /// the request uri, contains path and query string
+    pub uri: Uri,
+    /// the request headers
+    pub headers: HeaderMap,
+    /// the selected subprotocol
+    pub protocol: Option<String>,
+}
+
+/// custom method
+impl WsHandshake {
+    /// create handshake info from the request and the accepted response
+    pub(crate) fn new(request: &Request, response: &Response) -> Self {
+        let protocol = response.headers().get(PROTOCOL).and_then(|protocol| protocol.to_str().ok()).map(str::to_string);
+        Self { uri: request.uri().clone(), headers: request.headers().clone(), protocol }
+    }
+
+    /// get the request path
+    pub fn get_path(&self) -> &str {
+        self.uri.path()
+    }
+
+    /// get the request query string
+    pub fn get_query(&self) -> Option<&str> {
+        self.uri.query()
+    }
+
+    /// get the request header value, return None if the header does not exist or is not visible ascii
+    pub fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+}
+
+/// get the subprotocols requested by the client, in order of preference
+pub fn request_protocols(request: &Request) -> Vec<&str> {
+    request.headers().get_all(PROTOCOL).iter()
+        .filter_map(|protocols| protocols.to_str().ok())
+        .flat_map(|protocols| protocols.split(','))
+        .map(str::trim)
+        .filter(|protocol| !protocol.is_empty())
+        .collect()
+}
+
+/// select the first subprotocol requested by the client that is supported, and set it to the response<br />
+/// return None if the client does not request any supported subprotocol
+pub fn select_protocol(request: &Request, response: &mut Response, supported: &[&str]) -> Option<String> {
+    let protocol = request_protocols(request).into_iter().find(|protocol| supported.contains(protocol))?;
+    response.headers_mut().insert(PROTOCOL, HeaderValue::from_str(protocol).ok()?);
+    Some(protocol.to_string())
+}
+
+/// create a response that rejects the websocket client
+pub fn reject(status: StatusCode, reason: impl Into<String>) -> ErrorResponse {
+    let mut response = ErrorResponse::new(Some(reason.into()));
+    *response.status_mut() = status;
+    response
+}
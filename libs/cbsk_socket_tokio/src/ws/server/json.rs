@@ -0,0 +1,93 @@
+use std::future::Future;
+use std::sync::Arc;
+use cbsk_base::{log, serde_json};
+use cbsk_base::serde::de::DeserializeOwned;
+use cbsk_socket::session::attrs::SessionAttrs;
+use crate::ws::server::callback::{Bytes, CapacityError, CloseFrame, Frame, Utf8Bytes, WsServerCallBack};
+use crate::ws::server::client::WsServerClient;
+use crate::ws::server::handshake::{ErrorResponse, Request, Response};
+
+/// typed json websocket server callback, the text data is decoded into [WsServerJsonCallBack::Msg]<br />
+/// use [WsServerJson] to start websocket server with this callback
+pub trait WsServerJsonCallBack: Send + Sync + 'static {
+    /// the json message type, such as an enum tagged by the type field: #[serde(tag = "type")]
+    type Msg: DeserializeOwned + Send;
+
+    /// websocket server recv websocket client text data and decode success will call this method<br />
+    /// msg: the decoded websocket client data<br />
+    /// client: websocket client, you can use this send json to websocket client
+    fn recv_json(&self, msg: Self::Msg, client: Arc<WsServerClient>) -> impl Future<Output = ()> + Send;
+
+    /// the websocket client text data can not be decoded into [WsServerJsonCallBack::Msg]<br />
+    /// err: decode error<br />
+    /// text: the websocket client text data
+    fn decode_error(&self, err: serde_json::Error, text: Utf8Bytes, client: Arc<WsServerClient>) -> impl Future<Output = ()> + Send {
+        log::warn!("{} decode websocket client json data [{text}] error: {err:?}", client.log_head);
+        async {}
+    }
+}
+
+/// typed json websocket server callback adapter, the text data is passed to json,
+/// the other callbacks are passed to cb, the recv_text of cb is not called<br />
+/// example: WsServer::new(conf, WsServerJson::new(Chat, ChatConn).into())
+pub struct WsServerJson<J: WsServerJsonCallBack, C: WsServerCallBack> {
+    /// the typed json callback
+    pub json: J,
+    /// the websocket server callback of the other data
+    pub cb: C,
+}
+
+/// custom method
+impl<J: WsServerJsonCallBack, C: WsServerCallBack> WsServerJson<J, C> {
+    /// create a typed json callback adapter
+    pub fn new(json: J, cb: C) -> Self {
+        Self { json, cb }
+    }
+}
+
+/// support websocket server callback
+impl<J: WsServerJsonCallBack, C: WsServerCallBack> WsServerCallBack for WsServerJson<J, C> {
+    #[allow(clippy::result_large_err)]
+    fn on_handshake(&self, request: &Request, response: Response, attrs: &SessionAttrs) -> Result<Response, ErrorResponse> {
+        self.cb.on_handshake(request, response, attrs)
+    }
+
+    async fn conn(&self, client: Arc<WsServerClient>) {
+        self.cb.conn(client).await
+    }
+
+    async fn dis_conn(&self, close: Option<CloseFrame>, client: Arc<WsServerClient>) {
+        self.cb.dis_conn(close, client).await
+    }
+
+    async fn recv_text(&self, text: Utf8Bytes, client: Arc<WsServerClient>) {
+        match serde_json::from_str(text.as_str()) {
+            Ok(msg) => { self.json.recv_json(msg, client).await }
+            Err(e) => { self.json.decode_error(e, text, client).await }
+        }
+    }
+
+    async fn recv_binary(&self, binary: Bytes, client: Arc<WsServerClient>) {
+        self.cb.recv_binary(binary, client).await
+    }
+
+    async fn recv_ping(&self, ping: Bytes, client: Arc<WsServerClient>) {
+        self.cb.recv_ping(ping, client).await
+    }
+
+    async fn recv_pong(&self, pong: Bytes, client: Arc<WsServerClient>) {
+        self.cb.recv_pong(pong, client).await
+    }
+
+    async fn recv_close(&self, close: Option<CloseFrame>, client: Arc<WsServerClient>) {
+        self.cb.recv_close(close, client).await
+    }
+
+    async fn recv_frame(&self, frame: Frame, client: Arc<WsServerClient>) {
+        self.cb.recv_frame(frame, client).await
+    }
+
+    async fn capacity_exceeded(&self, err: CapacityError, client: Arc<WsServerClient>) {
+        self.cb.capacity_exceeded(err, client).await
+    }
+}
@@ -1,20 +1,47 @@
+use std::collections::HashMap;
 use std::io;
-use std::sync::Arc;
-use std::time::Duration;
+#[cfg(feature = "metrics")]
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 use cbsk_base::{anyhow, log, tokio};
 use cbsk_base::tokio::net::{TcpListener, TcpStream};
+use cbsk_base::tokio::sync::Notify;
 use cbsk_base::tokio::task::JoinHandle;
+use cbsk_socket::metrics::server_metrics::ServerMetrics;
+use cbsk_socket::session::attrs::SessionAttrs;
+use cbsk_socket::session::ping::PingTick;
 use futures_util::stream::SplitStream;
 use futures_util::StreamExt;
+use tokio_tungstenite::tungstenite;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
 use tokio_tungstenite::tungstenite::Message;
 use tokio_tungstenite::WebSocketStream;
 use crate::ws::server::callback::WsServerCallBack;
 use crate::ws::server::client::WsServerClient;
 use crate::ws::server::config::WsServerConfig;
+use crate::ws::server::handshake::{Request, Response, WsHandshake};
+use crate::ws::server::room::WsRooms;
+#[cfg(feature = "ws_deflate")]
+use crate::ws::deflate::{self, DeflateStream, Role};
+use crate::ws::ws_write_trait::WsWriteTrait;
 
 pub mod config;
 pub mod callback;
 pub mod client;
+pub mod handshake;
+pub mod json;
+pub mod room;
+pub mod router;
+
+/// the websocket stream of websocket server client
+#[cfg(feature = "ws_deflate")]
+pub(crate) type WsServerStream = WebSocketStream<DeflateStream<TcpStream>>;
+/// the websocket stream of websocket server client
+#[cfg(not(feature = "ws_deflate"))]
+pub(crate) type WsServerStream = WebSocketStream<TcpStream>;
 
 /// websocket server
 pub struct WsServer<C: WsServerCallBack> {
@@ -22,12 +49,30 @@ pub struct WsServer<C: WsServerCallBack> {
     pub conf: Arc<WsServerConfig>,
     /// websocket server business callback
     pub cb: Arc<C>,
+    /// websocket server counters
+    pub metrics: Arc<ServerMetrics>,
+    /// websocket server rooms, see [WsServerClient::join]
+    pub rooms: Arc<WsRooms>,
+    /// the connected websocket clients, key is the connection id
+    clients: Arc<RwLock<HashMap<u64, Arc<WsServerClient>>>>,
+    /// stop websocket server
+    stopped: Arc<AtomicBool>,
+    /// notify the accept loop to stop
+    stop_notify: Arc<Notify>,
 }
 
 /// support clone
 impl<C: WsServerCallBack> Clone for WsServer<C> {
     fn clone(&self) -> Self {
-        Self { conf: self.conf.clone(), cb: self.cb.clone() }
+        Self {
+            conf: self.conf.clone(),
+            cb: self.cb.clone(),
+            metrics: self.metrics.clone(),
+            rooms: self.rooms.clone(),
+            clients: self.clients.clone(),
+            stopped: self.stopped.clone(),
+            stop_notify: self.stop_notify.clone(),
+        }
     }
 }
 
@@ -36,7 +81,27 @@ impl<C: WsServerCallBack> WsServer<C> {
     /// create a websocket server<br />
     /// just create data, if you want to read data to recv method, you should be call start method
     pub fn new(conf: Arc<WsServerConfig>, cb: Arc<C>) -> Self {
-        Self { conf, cb }
+        Self {
+            conf,
+            cb,
+            metrics: Arc::new(ServerMetrics::default()),
+            rooms: Arc::new(WsRooms::default()),
+            clients: Arc::default(),
+            stopped: Arc::new(AtomicBool::new(false)),
+            stop_notify: Arc::new(Notify::new()),
+        }
+    }
+
+    /// get the connected websocket clients
+    pub fn get_clients(&self) -> Vec<Arc<WsServerClient>> {
+        self.clients.read().unwrap_or_else(|e| e.into_inner()).values().cloned().collect()
+    }
+
+    /// start prometheus metrics http server in join handle<br />
+    /// addr: metrics http server bind addr, serve /metrics and /health
+    #[cfg(feature = "metrics")]
+    pub fn start_metrics_in_handle(&self, addr: SocketAddr) -> JoinHandle<()> {
+        crate::metrics::MetricsServer::new(self.conf.name.as_str(), addr, self.metrics.clone()).start_in_handle()
     }
 }
 
@@ -55,57 +120,136 @@ impl<C: WsServerCallBack> WsServer<C> {
         tokio::spawn(async move { ws_server.start().await; })
     }
 
+    /// stop websocket server, no new websocket client will be accepted<br />
+    /// all connected websocket clients are closed with close code 1001 (going away), see [WsWriteTrait::try_close]
+    pub async fn stop(&self) {
+        self.stopped.store(true, Ordering::Release);
+        self.stop_notify.notify_waiters();
+
+        let clients = self.get_clients();
+        let closes = clients.iter().map(|client| async {
+            match client.try_close(CloseCode::Away, "websocket server shutdown").await {
+                Err(e) if self.conf.log => { log::warn!("{} close websocket client error: {e:?}",client.log_head); }
+                _ => {}
+            }
+        });
+        futures_util::future::join_all(closes).await;
+    }
+
     /// try start websocket server
     async fn try_start(&self) -> io::Result<()> {
         let listener = TcpListener::bind(self.conf.addr).await?;
         log::info!("{} listener WebSocket[{}] success",self.conf.log_head,self.conf.addr);
+        self.metrics.set_listening(true);
 
         // loop waiting for client to connect
         loop {
-            if let Err(e) = self.try_accept(&listener).await {
-                log::error!("{} wait websocket accept error. wait for the next accept in three seconds. error: {e:?}",self.conf.log_head);
-                tokio::time::sleep(Duration::from_secs(3)).await;
+            // the notified is created before checking, so that the stop between them will not be lost
+            let stop = self.stop_notify.notified();
+            // if stop the server, return function
+            if self.stopped.load(Ordering::Acquire) {
+                self.metrics.set_listening(false);
+                log::info!("{} websocket server stopped",self.conf.log_head);
+                return Ok(());
+            }
+
+            tokio::select! {
+                result = self.try_accept(&listener) => {
+                    if let Err(e) = result {
+                        log::error!("{} wait websocket accept error. wait for the next accept in three seconds. error: {e:?}",self.conf.log_head);
+                        tokio::time::sleep(Duration::from_secs(3)).await;
+                    }
+                }
+                _ = stop => {}
             }
         }
     }
 
     /// try accept websocket client and read websocket client data
+    #[allow(clippy::result_large_err)]
     async fn try_accept(&self, listener: &TcpListener) -> anyhow::Result<()> {
         // accept client and split write and read
         let (tcp_stream, addr) = listener.accept().await?;
-        let (write, read) = tokio_tungstenite::accept_async(tcp_stream).await?.split();
+        #[cfg(feature = "ws_deflate")]
+        let tcp_stream = DeflateStream::new(tcp_stream, Role::Server, self.conf.deflate, self.conf.ws_conf.max_frame_size);
+        let attrs = SessionAttrs::default();
+        let mut handshake = None;
+        let ws_stream = tokio_tungstenite::accept_hdr_async_with_config(tcp_stream, |request: &Request, response: Response| {
+            let response = self.cb.on_handshake(request, response, &attrs)?;
+            #[cfg(feature = "ws_deflate")]
+            let response = self.accept_deflate(request, response);
+            handshake = Some(WsHandshake::new(request, &response));
+            Ok(response)
+        }, Some(self.conf.ws_conf)).await;
+        // the handshake fail or is rejected, just wait for the next client
+        let (ws_stream, handshake) = match (ws_stream, handshake) {
+            (Ok(ws_stream), Some(handshake)) => { (ws_stream, handshake) }
+            (Err(e), _) => {
+                if self.conf.log { log::warn!("{} websocket client[{addr}] handshake fail: {e:?}",self.conf.log_head); }
+                return Ok(());
+            }
+            (Ok(_), None) => { return Ok(()); }
+        };
+        let (write, read) = ws_stream.split();
 
         // start read data
-        let client = Arc::new(WsServerClient::new(addr, self.conf.as_ref(), write));
+        let client = Arc::new(WsServerClient::new(addr, self.conf.as_ref(), write, self.metrics.clone(), self.rooms.clone(), attrs, handshake));
+        self.metrics.client_conn();
+        self.clients.write().unwrap_or_else(|e| e.into_inner()).insert(client.conn_id, client.clone());
+        client.span.conn();
         self.read_spawn(client.clone(), read);
-        self.cb.conn(client).await;
+        let span = client.span.clone();
+        span.instrument(self.cb.conn(client)).await;
 
         Ok(())
     }
 
+    /// accept the permessage-deflate offer of the websocket client if enabled
+    #[cfg(feature = "ws_deflate")]
+    fn accept_deflate(&self, request: &Request, mut response: Response) -> Response {
+        if let Some(conf) = self.conf.deflate.as_ref() {
+            deflate::accept(conf, request.headers(), response.headers_mut());
+        }
+        response
+    }
+
     /// start read async
-    fn read_spawn(&self, client: Arc<WsServerClient>, read: SplitStream<WebSocketStream<TcpStream>>) {
+    fn read_spawn(&self, client: Arc<WsServerClient>, read: SplitStream<WsServerStream>) {
         let ws_server = self.clone();
-        tokio::spawn(async move {
+        let span = client.span.clone();
+        tokio::spawn(span.instrument(async move {
             if let Err(e) = ws_server.try_read_spawn(client.clone(), read).await {
                 if ws_server.conf.log { log::error!("{} read websocket client data error: {e:?}",client.log_head); }
             }
 
             // if websocket read is closed, it is considered that websocket has been closed
-            ws_server.cb.dis_conn(client.clone()).await;
-        });
+            ws_server.metrics.client_dis_conn();
+            client.span.dis_conn();
+            ws_server.cb.dis_conn(client.ws_close.get_recv(), client.clone()).await;
+            // the client can still get the joined rooms in dis_conn
+            ws_server.rooms.leave_all(client.conn_id);
+            ws_server.clients.write().unwrap_or_else(|e| e.into_inner()).remove(&client.conn_id);
+        }));
     }
 
     /// try read websocket client data
-    async fn try_read_spawn(&self, client: Arc<WsServerClient>, mut read: SplitStream<WebSocketStream<TcpStream>>) -> anyhow::Result<()> {
+    async fn try_read_spawn(&self, client: Arc<WsServerClient>, mut read: SplitStream<WsServerStream>) -> anyhow::Result<()> {
         if self.conf.log { log::info!("{} start websocket client read async success",client.log_head); }
 
         loop {
-            let read = read.next();
-            let msg = match tokio::time::timeout(self.conf.read_time_out, read).await {
+            self.keep_alive(client.as_ref()).await?;
+
+            let read = tokio::time::timeout(self.conf.get_read_time_out(), read.next());
+            let msg = tokio::select! {
+                msg = read => { msg }
+                // the close is not replied in time, end the connection directly
+                _ = client.ws_close.wait_end() => { return Ok(()); }
+            };
+            let msg = match msg {
                 Ok(msg) => {
-                    // if read empty data, continue to next loop
-                    cbsk_base::match_some_continue!(msg)?
+                    // read None, the websocket connection has been closed
+                    let msg = cbsk_base::match_some_return!(msg,Ok(()));
+                    self.check_capacity(msg, &client).await?
                 }
                 Err(_e) => {
                     // if just timeout, continue to next loop
@@ -113,14 +257,63 @@ impl<C: WsServerCallBack> WsServer<C> {
                 }
             };
 
+            client.metrics.add_recv(msg.len());
             match msg {
                 Message::Text(text) => { self.cb.recv_text(text, client.clone()).await }
                 Message::Binary(binary) => { self.cb.recv_binary(binary, client.clone()).await }
                 Message::Ping(ping) => { self.cb.recv_ping(ping, client.clone()).await }
-                Message::Pong(pong) => { self.cb.recv_pong(pong, client.clone()).await }
-                Message::Close(close) => { self.cb.recv_close(close, client.clone()).await }
+                Message::Pong(pong) => {
+                    // the reply of keepalive ping is not passed to recv_pong
+                    if client.ping.pong(pong.as_ref(), Instant::now()) { continue; }
+                    self.cb.recv_pong(pong, client.clone()).await
+                }
+                Message::Close(close) => {
+                    // the close reply is sent automatically, the next read will return None
+                    client.ws_close.recv(close.clone());
+                    self.cb.recv_close(close, client.clone()).await
+                }
                 Message::Frame(frame) => { self.cb.recv_frame(frame, client.clone()).await }
             }
         }
     }
+
+    /// if the websocket client exceeds the message size limits, call capacity_exceeded and send close with 1009
+    async fn check_capacity(&self, msg: tungstenite::Result<Message>, client: &Arc<WsServerClient>) -> tungstenite::Result<Message> {
+        let capacity = match msg.as_ref() {
+            Err(tungstenite::Error::Capacity(e)) => { Some(*e) }
+            // the message exceeds the max size of permessage-deflate
+            #[cfg(feature = "ws_deflate")]
+            Err(tungstenite::Error::Io(e)) => { deflate::capacity_error(e) }
+            _ => { None }
+        };
+        if let Some(e) = capacity {
+            self.cb.capacity_exceeded(e, client.clone()).await;
+            let close = CloseFrame { code: CloseCode::Size, reason: e.to_string().into() };
+            match client.try_send_close(Some(close)).await {
+                Err(e) if self.conf.log => { log::warn!("{} send websocket client close error: {e:?}",client.log_head); }
+                _ => {}
+            }
+        }
+        msg
+    }
+
+    /// send keepalive ping, return Err if the pong is not received in time
+    async fn keep_alive(&self, client: &WsServerClient) -> anyhow::Result<()> {
+        let keep_alive = cbsk_base::match_some_return!(self.conf.keep_alive.as_ref(),Ok(()));
+        match client.ping.tick(keep_alive, Instant::now()) {
+            PingTick::Idle => {}
+            PingTick::Ping(ping) => { client.try_send_ping(ping).await? }
+            PingTick::TimeOut => {
+                // the client may be gone, close with 1011 without waiting for the reply, then close the write
+                let close = CloseFrame { code: CloseCode::Error, reason: "pong time out".into() };
+                let closed = tokio::time::timeout(client.ws_close.time_out, async {
+                    client.try_send_close(Some(close)).await?;
+                    client.close_write().await
+                }).await;
+                if let (Ok(Err(e)), true) = (closed, self.conf.log) { log::warn!("{} close websocket client error: {e:?}",client.log_head); }
+                return Err(anyhow::anyhow!("websocket client pong time out {:?}", keep_alive.pong_time_out));
+            }
+        }
+        Ok(())
+    }
 }
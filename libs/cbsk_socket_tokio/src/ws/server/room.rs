@@ -0,0 +1,111 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use cbsk_base::log;
+use tokio_tungstenite::tungstenite::Message;
+use crate::ws::server::client::WsServerClient;
+use crate::ws::ws_write_trait::WsWriteTrait;
+
+/// room members and the rooms joined by each client
+#[derive(Default)]
+struct RoomsInner {
+    /// the members of each room, key is the connection id
+    rooms: HashMap<String, HashMap<u64, Arc<WsServerClient>>>,
+    /// the rooms joined by each client, key is the connection id
+    joined: HashMap<u64, HashSet<String>>,
+}
+
+/// websocket server rooms, clients join or leave named rooms and messages are published to all members<br />
+/// the client leaves all rooms automatically after disconnect
+#[derive(Default)]
+pub struct WsRooms {
+    /// rooms data
+    inner: RwLock<RoomsInner>,
+}
+
+/// custom method
+impl WsRooms {
+    /// the client joins the room, the room is created if it does not exist<br />
+    /// return false if the client is already in the room
+    pub fn join(&self, room: &str, client: Arc<WsServerClient>) -> bool {
+        let mut inner = self.write();
+        let conn_id = client.conn_id;
+        if inner.rooms.entry(room.to_string()).or_default().insert(conn_id, client).is_some() { return false; }
+        inner.joined.entry(conn_id).or_default().insert(room.to_string());
+        true
+    }
+
+    /// the client leaves the room, the room is removed if it is empty<br />
+    /// return false if the client is not in the room
+    pub fn leave(&self, room: &str, conn_id: u64) -> bool {
+        let mut inner = self.write();
+        let left = inner.rooms.get_mut(room).and_then(|members| members.remove(&conn_id)).is_some();
+        if !left { return false; }
+
+        if inner.rooms.get(room).is_some_and(HashMap::is_empty) { inner.rooms.remove(room); }
+        if let Some(joined) = inner.joined.get_mut(&conn_id) {
+            joined.remove(room);
+            if joined.is_empty() { inner.joined.remove(&conn_id); }
+        }
+        true
+    }
+
+    /// the client leaves all rooms<br />
+    /// return the rooms the client left
+    pub fn leave_all(&self, conn_id: u64) -> Vec<String> {
+        let mut inner = self.write();
+        let joined = inner.joined.remove(&conn_id).unwrap_or_default();
+        for room in joined.iter() {
+            let Some(members) = inner.rooms.get_mut(room) else { continue; };
+            members.remove(&conn_id);
+            if members.is_empty() { inner.rooms.remove(room); }
+        }
+        joined.into_iter().collect()
+    }
+
+    /// publish message to all members of the room, the message is built once and shared by all members<br />
+    /// return the number of members the message was sent to successfully
+    pub async fn publish(&self, room: &str, msg: impl Into<Message>) -> usize {
+        let members = self.get_members(room);
+        if members.is_empty() { return 0; }
+
+        let msg = msg.into();
+        let sends = members.iter().map(|client| async {
+            let result = client.try_send(msg.clone()).await;
+            if let Err(e) = result.as_ref() {
+                log::warn!("{} publish room[{room}] message fail: {e:?}",client.log_head);
+            }
+            result.is_ok()
+        });
+        futures_util::future::join_all(sends).await.into_iter().filter(|ok| *ok).count()
+    }
+
+    /// get the members of the room
+    pub fn get_members(&self, room: &str) -> Vec<Arc<WsServerClient>> {
+        self.read().rooms.get(room).map(|members| members.values().cloned().collect()).unwrap_or_default()
+    }
+
+    /// get the number of members of the room, 0 if the room does not exist
+    pub fn get_room_size(&self, room: &str) -> usize {
+        self.read().rooms.get(room).map_or(0, HashMap::len)
+    }
+
+    /// get the names of all rooms that have members
+    pub fn get_rooms(&self) -> Vec<String> {
+        self.read().rooms.keys().cloned().collect()
+    }
+
+    /// get the rooms joined by the client
+    pub fn get_joined(&self, conn_id: u64) -> Vec<String> {
+        self.read().joined.get(&conn_id).map(|joined| joined.iter().cloned().collect()).unwrap_or_default()
+    }
+
+    /// read lock, the rooms are still usable if the lock is poisoned
+    fn read(&self) -> RwLockReadGuard<'_, RoomsInner> {
+        self.inner.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// write lock, the rooms are still usable if the lock is poisoned
+    fn write(&self) -> RwLockWriteGuard<'_, RoomsInner> {
+        self.inner.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
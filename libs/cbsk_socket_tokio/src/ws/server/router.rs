@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use cbsk_base::log;
+use cbsk_socket::session::attrs::SessionAttrs;
+use tokio_tungstenite::tungstenite::http::StatusCode;
+use crate::ws::server::callback::{Bytes, CapacityError, CloseFrame, Frame, Utf8Bytes, WsServerCallBack};
+use crate::ws::server::client::WsServerClient;
+use crate::ws::server::handshake::{self, ErrorResponse, Request, Response};
+
+/// boxed callback future
+type BoxFuture = Pin<Box<dyn Future<Output=()> + Send>>;
+
+/// object safe [WsServerCallBack], used to store different callbacks in one router
+trait RouteCallBack: Send + Sync + 'static {
+    #[allow(clippy::result_large_err)]
+    fn on_handshake(&self, request: &Request, response: Response, attrs: &SessionAttrs) -> Result<Response, ErrorResponse>;
+    fn conn(self: Arc<Self>, client: Arc<WsServerClient>) -> BoxFuture;
+    fn dis_conn(self: Arc<Self>, close: Option<CloseFrame>, client: Arc<WsServerClient>) -> BoxFuture;
+    fn recv_text(self: Arc<Self>, text: Utf8Bytes, client: Arc<WsServerClient>) -> BoxFuture;
+    fn recv_binary(self: Arc<Self>, binary: Bytes, client: Arc<WsServerClient>) -> BoxFuture;
+    fn recv_ping(self: Arc<Self>, ping: Bytes, client: Arc<WsServerClient>) -> BoxFuture;
+    fn recv_pong(self: Arc<Self>, pong: Bytes, client: Arc<WsServerClient>) -> BoxFuture;
+    fn recv_close(self: Arc<Self>, close: Option<CloseFrame>, client: Arc<WsServerClient>) -> BoxFuture;
+    fn recv_frame(self: Arc<Self>, frame: Frame, client: Arc<WsServerClient>) -> BoxFuture;
+    fn capacity_exceeded(self: Arc<Self>, err: CapacityError, client: Arc<WsServerClient>) -> BoxFuture;
+}
+
+/// support all websocket server callbacks
+impl<C: WsServerCallBack> RouteCallBack for C {
+    #[allow(clippy::result_large_err)]
+    fn on_handshake(&self, request: &Request, response: Response, attrs: &SessionAttrs) -> Result<Response, ErrorResponse> {
+        WsServerCallBack::on_handshake(self, request, response, attrs)
+    }
+
+    fn conn(self: Arc<Self>, client: Arc<WsServerClient>) -> BoxFuture {
+        Box::pin(async move { WsServerCallBack::conn(self.as_ref(), client).await })
+    }
+
+    fn dis_conn(self: Arc<Self>, close: Option<CloseFrame>, client: Arc<WsServerClient>) -> BoxFuture {
+        Box::pin(async move { WsServerCallBack::dis_conn(self.as_ref(), close, client).await })
+    }
+
+    fn recv_text(self: Arc<Self>, text: Utf8Bytes, client: Arc<WsServerClient>) -> BoxFuture {
+        Box::pin(async move { WsServerCallBack::recv_text(self.as_ref(), text, client).await })
+    }
+
+    fn recv_binary(self: Arc<Self>, binary: Bytes, client: Arc<WsServerClient>) -> BoxFuture {
+        Box::pin(async move { WsServerCallBack::recv_binary(self.as_ref(), binary, client).await })
+    }
+
+    fn recv_ping(self: Arc<Self>, ping: Bytes, client: Arc<WsServerClient>) -> BoxFuture {
+        Box::pin(async move { WsServerCallBack::recv_ping(self.as_ref(), ping, client).await })
+    }
+
+    fn recv_pong(self: Arc<Self>, pong: Bytes, client: Arc<WsServerClient>) -> BoxFuture {
+        Box::pin(async move { WsServerCallBack::recv_pong(self.as_ref(), pong, client).await })
+    }
+
+    fn recv_close(self: Arc<Self>, close: Option<CloseFrame>, client: Arc<WsServerClient>) -> BoxFuture {
+        Box::pin(async move { WsServerCallBack::recv_close(self.as_ref(), close, client).await })
+    }
+
+    fn recv_frame(self: Arc<Self>, frame: Frame, client: Arc<WsServerClient>) -> BoxFuture {
+        Box::pin(async move { WsServerCallBack::recv_frame(self.as_ref(), frame, client).await })
+    }
+
+    fn capacity_exceeded(self: Arc<Self>, err: CapacityError, client: Arc<WsServerClient>) -> BoxFuture {
+        Box::pin(async move { WsServerCallBack::capacity_exceeded(self.as_ref(), err, client).await })
+    }
+}
+
+/// websocket path router, register different callbacks per url path on the same websocket server<br />
+/// the client that requests an unknown path is rejected with 404 during the handshake<br />
+/// example: WsServer::new(conf, WsRouter::new().route("/telemetry", Telemetry).route("/control", Control).into())
+#[derive(Default)]
+pub struct WsRouter {
+    /// the callback of each path
+    routes: HashMap<String, Arc<dyn RouteCallBack>>,
+}
+
+/// custom method
+impl WsRouter {
+    /// create an empty router
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// register the callback of the path, the trailing slash of the path is ignored<br />
+    /// if the path is already registered, the callback will be replaced
+    pub fn route(mut self, path: &str, cb: impl WsServerCallBack) -> Self {
+        self.routes.insert(Self::normalize(path).to_string(), Arc::new(cb));
+        self
+    }
+
+    /// get the registered paths
+    pub fn get_paths(&self) -> Vec<&str> {
+        self.routes.keys().map(String::as_str).collect()
+    }
+
+    /// remove the trailing slash of the path, except the root path
+    fn normalize(path: &str) -> &str {
+        let path = path.trim_end_matches('/');
+        if path.is_empty() { "/" } else { path }
+    }
+
+    /// get the callback of the path
+    fn get_route(&self, path: &str) -> Option<Arc<dyn RouteCallBack>> {
+        self.routes.get(Self::normalize(path)).cloned()
+    }
+
+    /// get the callback of the websocket client<br />
+    /// the client path is checked during the handshake, so the route should always exist
+    fn client_route(&self, client: &WsServerClient) -> Option<Arc<dyn RouteCallBack>> {
+        let route = self.get_route(client.handshake.get_path());
+        if route.is_none() {
+            log::warn!("{} websocket client path[{}] is not routed",client.log_head,client.handshake.get_path());
+        }
+        route
+    }
+}
+
+/// dispatch the client callback to the route of the client path
+macro_rules! route_dispatch {
+    ($self:expr,$client:expr,$method:ident($($arg:expr),*)) => {
+        if let Some(route) = $self.client_route($client.as_ref()) {
+            route.$method($($arg,)* $client).await;
+        }
+    };
+}
+
+/// support websocket server callback
+impl WsServerCallBack for WsRouter {
+    #[allow(clippy::result_large_err)]
+    fn on_handshake(&self, request: &Request, response: Response, attrs: &SessionAttrs) -> Result<Response, ErrorResponse> {
+        match self.get_route(request.uri().path()) {
+            Some(route) => { route.on_handshake(request, response, attrs) }
+            None => { Err(handshake::reject(StatusCode::NOT_FOUND, format!("websocket path[{}] not found", request.uri().path()))) }
+        }
+    }
+
+    async fn conn(&self, client: Arc<WsServerClient>) {
+        route_dispatch!(self, client, conn());
+    }
+
+    async fn dis_conn(&self, close: Option<CloseFrame>, client: Arc<WsServerClient>) {
+        route_dispatch!(self, client, dis_conn(close));
+    }
+
+    async fn recv_text(&self, text: Utf8Bytes, client: Arc<WsServerClient>) {
+        route_dispatch!(self, client, recv_text(text));
+    }
+
+    async fn recv_binary(&self, binary: Bytes, client: Arc<WsServerClient>) {
+        route_dispatch!(self, client, recv_binary(binary));
+    }
+
+    async fn recv_ping(&self, ping: Bytes, client: Arc<WsServerClient>) {
+        route_dispatch!(self, client, recv_ping(ping));
+    }
+
+    async fn recv_pong(&self, pong: Bytes, client: Arc<WsServerClient>) {
+        route_dispatch!(self, client, recv_pong(pong));
+    }
+
+    async fn recv_close(&self, close: Option<CloseFrame>, client: Arc<WsServerClient>) {
+        route_dispatch!(self, client, recv_close(close));
+    }
+
+    async fn recv_frame(&self, frame: Frame, client: Arc<WsServerClient>) {
+        route_dispatch!(self, client, recv_frame(frame));
+    }
+
+    async fn capacity_exceeded(&self, err: CapacityError, client: Arc<WsServerClient>) {
+        route_dispatch!(self, client, capacity_exceeded(err));
+    }
+}
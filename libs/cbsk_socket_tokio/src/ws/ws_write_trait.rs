@@ -1,10 +1,15 @@
-use crate::ws::client::callback::Utf8Bytes;
-use cbsk_base::log;
+use crate::ws::close::WsClose;
+use cbsk_base::{anyhow, log};
+use cbsk_base::json::to_json::ToJson;
+use cbsk_base::serde::Serialize;
+use futures_util::stream::{self, Stream, StreamExt};
 use std::fmt::Debug;
+use std::io;
 use tokio_tungstenite::tungstenite;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::{CloseCode, Data, OpCode};
 use tokio_tungstenite::tungstenite::protocol::frame::Frame;
 use tokio_tungstenite::tungstenite::protocol::CloseFrame;
-use tokio_tungstenite::tungstenite::{Bytes, Message};
+use tokio_tungstenite::tungstenite::{Bytes, Message, Utf8Bytes};
 
 /// send data and print log
 macro_rules! send_ws_log {
@@ -18,6 +23,12 @@ pub trait WsWriteTrait {
     /// get internal log name
     fn get_log_head(&self) -> &str;
 
+    /// get the closing handshake state of the connection<br />
+    /// the default is None, [WsWriteTrait::try_close] only sends the close without waiting for the reply
+    fn get_ws_close(&self) -> Option<&WsClose> {
+        None
+    }
+
     /// send text to websocket
     async fn send_text(&self, text: impl Into<Utf8Bytes> + Debug) {
         send_ws_log!(self.try_send_text(text), self.get_log_head(), "text", text);
@@ -28,6 +39,18 @@ pub trait WsWriteTrait {
         self.try_send(Message::Text(text.into())).await
     }
 
+    /// send json to websocket as text
+    async fn send_json(&self, json: &(impl Serialize + Sync)) {
+        send_ws_log!(self.try_send_json(json), self.get_log_head(), "json", json.to_json());
+    }
+
+    /// try send json to websocket as text
+    async fn try_send_json(&self, json: &(impl Serialize + Sync)) -> anyhow::Result<()> {
+        let text = json.to_json()?.to_string();
+        self.try_send_text(text).await?;
+        Ok(())
+    }
+
     /// send binary to websocket
     async fn send_binary(&self, binary: impl Into<Bytes> + Debug) {
         send_ws_log!(
@@ -43,6 +66,31 @@ pub trait WsWriteTrait {
         self.try_send(Message::Binary(binary.into())).await
     }
 
+    /// send large binary to websocket as explicit fragments, each item of the stream is sent as one frame<br />
+    /// the peer receives all fragments as one binary message, an empty stream sends nothing
+    async fn send_binary_fragments(&self, fragments: impl Stream<Item = Bytes>) {
+        if let Err(e) = self.try_send_binary_fragments(fragments).await {
+            log::error!("{} try send binary fragments to WebSocket error : {e:?}", self.get_log_head());
+            return;
+        }
+        log::debug!("{} send binary fragments to WebSocket success", self.get_log_head());
+    }
+
+    /// try send large binary to websocket as explicit fragments, each item of the stream is sent as one frame<br />
+    /// the peer receives all fragments as one binary message, an empty stream sends nothing<br />
+    /// see [WsWriteTrait::try_send_frames] for how long the other sends wait
+    async fn try_send_binary_fragments(&self, fragments: impl Stream<Item = Bytes>) -> tungstenite::Result<()> {
+        let fragments = Box::pin(fragments.peekable());
+        let frames = stream::unfold((fragments, OpCode::Data(Data::Binary)), |(mut fragments, opcode)| async move {
+            let fragment = fragments.next().await?;
+            // the next fragment is awaited first, so that the last frame can be marked as final
+            let is_final = fragments.as_mut().peek().await.is_none();
+            let frame = Frame::message(fragment, opcode, is_final);
+            Some((frame, (fragments, OpCode::Data(Data::Continue))))
+        });
+        self.try_send_frames(frames).await
+    }
+
     /// send ping to websocket
     async fn send_ping(&self, ping: impl Into<Bytes> + Debug) {
         send_ws_log!(self.try_send_ping(ping), self.get_log_head(), "ping", ping);
@@ -78,6 +126,30 @@ pub trait WsWriteTrait {
         self.try_send(Message::Close(close)).await
     }
 
+    /// close websocket gracefully, see [WsWriteTrait::try_close]
+    async fn close(&self, code: CloseCode, reason: impl Into<Utf8Bytes> + Debug) {
+        send_ws_log!(
+            self.try_close(code, reason),
+            self.get_log_head(),
+            "close",
+            (code, &reason)
+        );
+    }
+
+    /// try close websocket gracefully, send close with the code and reason, then wait for the close reply of the peer<br />
+    /// the received close is passed to dis_conn, if the peer does not reply in time, the connection is closed directly and Err is returned<br />
+    /// the close reply is read by the read loop of this connection, so in the recv callbacks of this connection, use [WsWriteTrait::try_send_close] instead
+    async fn try_close(&self, code: CloseCode, reason: impl Into<Utf8Bytes>) -> tungstenite::Result<()> {
+        let result = self.try_send_close(Some(CloseFrame { code, reason: reason.into() })).await;
+        let ws_close = cbsk_base::match_some_return!(self.get_ws_close(), result);
+        if result.is_ok() && ws_close.wait_recv().await { return Ok(()); }
+
+        // the close is not sent or the peer does not reply, end the connection directly
+        ws_close.end();
+        result?;
+        Err(io::Error::new(io::ErrorKind::TimedOut, format!("close reply is not received in {:?}", ws_close.time_out)).into())
+    }
+
     /// send frame to websocket
     async fn send_frame(&self, frame: Frame) {
         send_ws_log!(
@@ -95,4 +167,17 @@ pub trait WsWriteTrait {
 
     /// try send message to websocket
     async fn try_send(&self, msg: Message) -> tungstenite::Result<()>;
+
+    /// try send frames to websocket in order<br />
+    /// the default sends each frame by [WsWriteTrait::try_send], other messages may be sent between these frames<br />
+    /// the websocket client and server send no other message between these frames,
+    /// the first frame is awaited before writing, then the write is held until the stream ends,
+    /// so the next frames should be ready, otherwise the other sends of this connection wait for them
+    async fn try_send_frames(&self, frames: impl Stream<Item = Frame>) -> tungstenite::Result<()> {
+        let mut frames = Box::pin(frames);
+        while let Some(frame) = frames.next().await {
+            self.try_send(Message::Frame(frame)).await?;
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// how to deliver received large payload
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum ChunkRecvMode {
+    /// reassemble in memory, deliver a single completed payload
+    #[default]
+    Memory,
+    /// reassemble into a file in this directory, deliver the completed file path
+    File(PathBuf),
+    /// do not reassemble, deliver each chunk as it arrives
+    Stream,
+}
+
+/// large payload chunking config
+#[derive(Clone, Debug)]
+pub struct ChunkConfig {
+    /// the max data length of each chunk when sending, default is 64 KiB
+    pub chunk_size: usize,
+    /// the max total length of received payload, larger transfers will be rejected, default is 256 MiB
+    pub max_size: u64,
+    /// how to deliver received payload
+    pub recv_mode: ChunkRecvMode,
+    /// the max number of transfers being received at the same time, more transfers will be rejected, default is 16
+    pub max_transfers: usize,
+    /// the max total length of all transfers being received, more transfers will be rejected, default is 512 MiB
+    pub max_total_size: u64,
+    /// the transfer that has not received a chunk in this time will be dropped, default is 60 seconds
+    pub time_out: Duration,
+}
+
+/// support default
+impl Default for ChunkConfig {
+    fn default() -> Self {
+        Self {
+            chunk_size: 64 * 1024,
+            max_size: 256 * 1024 * 1024,
+            recv_mode: ChunkRecvMode::default(),
+            max_transfers: 16,
+            max_total_size: 512 * 1024 * 1024,
+            time_out: Duration::from_secs(60),
+        }
+    }
+}
+
+/// custom method
+impl ChunkConfig {
+    /// set the max data length of each chunk when sending
+    pub fn set_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// set the max total length of received payload
+    pub fn set_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// set how to deliver received payload
+    pub fn set_recv_mode(mut self, recv_mode: ChunkRecvMode) -> Self {
+        self.recv_mode = recv_mode;
+        self
+    }
+
+    /// set the max number of transfers being received at the same time
+    pub fn set_max_transfers(mut self, max_transfers: usize) -> Self {
+        self.max_transfers = max_transfers.max(1);
+        self
+    }
+
+    /// set the max total length of all transfers being received
+    pub fn set_max_total_size(mut self, max_total_size: u64) -> Self {
+        self.max_total_size = max_total_size;
+        self
+    }
+
+    /// set how long an incomplete transfer can wait for the next chunk
+    pub fn set_time_out(mut self, time_out: Duration) -> Self {
+        self.time_out = time_out;
+        self
+    }
+}
@@ -0,0 +1,398 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::hash_map::Entry;
+use std::future::Future;
+use std::io;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+use cbsk::hello::FEATURE_CHUNK;
+use cbsk::message::Message;
+use cbsk_base::{anyhow, log, tokio};
+use cbsk_base::tokio::fs::File;
+use cbsk_base::tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use cbsk_base::tokio::sync::Mutex;
+use cbsk_socket_tokio::tcp::common::tcp_write_trait::TcpWriteTrait;
+use crate::business::chunk::config::{ChunkConfig, ChunkRecvMode};
+use crate::business::codec::Codec;
+
+pub mod config;
+
+/// the number of completed transfer ids kept to discard the late chunks
+const COMPLETED_LEN: usize = 1024;
+
+/// the last transfer id
+static TRANSFER_ID: AtomicU64 = AtomicU64::new(0);
+
+/// get next transfer id, unique within the process
+fn next_transfer_id() -> u64 {
+    TRANSFER_ID.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// large payload transfer progress
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkProgress {
+    /// transfer id
+    pub id: u64,
+    /// the length of data sent or received
+    pub done: u64,
+    /// the total length of payload
+    pub total: u64,
+}
+
+/// a chunk of large payload, delivered if [ChunkRecvMode::Stream]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChunkData {
+    /// transfer id
+    pub id: u64,
+    /// the offset of data in payload
+    pub offset: u64,
+    /// the total length of payload
+    pub total: u64,
+    /// chunk data
+    pub data: Vec<u8>,
+}
+
+/// completed large payload
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChunkPayload {
+    /// reassembled in memory, see [ChunkRecvMode::Memory]
+    Memory(Vec<u8>),
+    /// reassembled into file, see [ChunkRecvMode::File]
+    File(PathBuf),
+    /// all chunks have been delivered, see [ChunkRecvMode::Stream]
+    Stream,
+}
+
+/// called after each chunk is sent
+pub(crate) type ProgressFn<T> = Box<dyn Fn(ChunkProgress, T) -> Pin<Box<dyn Future<Output=()> + Send>> + Send + Sync>;
+
+/// large payload sender
+pub(crate) struct ChunkSender<T> {
+    /// chunk config
+    pub(crate) conf: Arc<ChunkConfig>,
+    /// send progress callback
+    progress: ProgressFn<T>,
+}
+
+/// custom method
+impl<T: Clone> ChunkSender<T> {
+    /// create large payload sender
+    pub(crate) fn new(conf: Arc<ChunkConfig>, progress: ProgressFn<T>) -> Self {
+        Self { conf, progress }
+    }
+
+    /// read payload from reader and send chunk by chunk, other frames can be sent between chunks<br />
+    /// total: the total length of payload, reader must provide at least this length<br />
+    /// return the transfer id
+    pub(crate) async fn send(&self, reader: &mut (impl AsyncRead + Unpin), total: u64, write: &impl TcpWriteTrait, codec: &Codec, arg: T) -> io::Result<u64> {
+        codec.check_feature(FEATURE_CHUNK, "large payload chunking")?;
+        let id = next_transfer_id();
+        let mut offset = 0;
+        loop {
+            let len = usize::try_from(total - offset).unwrap_or(usize::MAX).min(self.conf.chunk_size);
+            let mut data = vec![0; len];
+            reader.read_exact(data.as_mut_slice()).await?;
+
+            codec.send_message(Message::Chunk { id, offset, total, data }, write).await?;
+            offset += u64::try_from(len).map_err(io::Error::other)?;
+            (self.progress)(ChunkProgress { id, done: offset, total }, arg.clone()).await;
+
+            if offset >= total { return Ok(id); }
+        }
+    }
+}
+
+/// the received chunk
+pub(crate) struct ChunkRecv {
+    /// receive progress
+    pub(crate) progress: ChunkProgress,
+    /// the chunk, only if [ChunkRecvMode::Stream]
+    pub(crate) data: Option<ChunkData>,
+    /// the completed payload, only if all chunks are received
+    pub(crate) payload: Option<ChunkPayload>,
+}
+
+/// a transfer being received
+struct Transfer {
+    /// the total length of payload
+    total: u64,
+    /// the received ranges of payload
+    received: Ranges,
+    /// the last time a chunk was received
+    last_recv: Instant,
+    /// where the payload is reassembled
+    target: TransferTarget,
+}
+
+/// sorted and non-overlapping [start, end) ranges
+#[derive(Debug, Default)]
+struct Ranges(Vec<(u64, u64)>);
+
+/// custom method
+impl Ranges {
+    /// add a range, merge with the overlapping or adjacent ranges
+    fn insert(&mut self, start: u64, end: u64) {
+        if start >= end { return; }
+        let (mut start, mut end) = (start, end);
+        let first = self.0.partition_point(|r| r.1 < start);
+        let last = self.0.partition_point(|r| r.0 <= end);
+        if first < last {
+            start = start.min(self.0[first].0);
+            end = end.max(self.0[last - 1].1);
+        }
+        self.0.splice(first..last, [(start, end)]);
+    }
+
+    /// the length covered by all ranges
+    fn len(&self) -> u64 {
+        self.0.iter().map(|r| r.1 - r.0).sum()
+    }
+
+    /// is [0, total) fully covered
+    fn is_complete(&self, total: u64) -> bool {
+        total == 0 || self.0.first() == Some(&(0, total))
+    }
+}
+
+/// custom method
+impl Transfer {
+    /// drop the incomplete transfer, remove the file if reassembled into file
+    async fn discard(self) {
+        let TransferTarget::File(file, path) = self.target else { return; };
+        drop(file);
+        if let Err(e) = tokio::fs::remove_file(path.as_path()).await {
+            log::warn!("remove incomplete chunk file {path:?} error: {e:?}");
+        }
+    }
+}
+
+/// where the payload is reassembled
+enum TransferTarget {
+    /// reassemble in memory
+    Memory(Vec<u8>),
+    /// reassemble into file
+    File(File, PathBuf),
+    /// not reassembled
+    Stream,
+}
+
+/// large payload receiver, reassemble chunks of each transfer
+pub(crate) struct ChunkReceiver {
+    /// chunk config
+    conf: Arc<ChunkConfig>,
+    /// file name prefix, used to distinguish transfers of different connections
+    file_prefix: String,
+    /// transfers being received, key is transfer id
+    transfers: Mutex<HashMap<u64, Transfer>>,
+    /// failed transfer ids, the remaining chunks will be discarded
+    failed: Mutex<HashSet<u64>>,
+    /// the last completed transfer ids, the late chunks will be discarded,
+    /// so that a duplicate chunk does not start the transfer again
+    completed: Mutex<VecDeque<u64>>,
+}
+
+/// custom method
+impl ChunkReceiver {
+    /// create large payload receiver
+    pub(crate) fn new(conf: Arc<ChunkConfig>, file_prefix: impl Into<String>) -> Self {
+        Self { conf, file_prefix: file_prefix.into(), transfers: Mutex::default(), failed: Mutex::default(), completed: Mutex::default() }
+    }
+
+    /// drop all incomplete transfers, example: the connection is lost
+    pub(crate) async fn clear(&self) {
+        self.failed.lock().await.clear();
+        self.completed.lock().await.clear();
+        let transfers = std::mem::take(&mut *self.transfers.lock().await);
+        for transfer in transfers.into_values() {
+            transfer.discard().await;
+        }
+    }
+
+    /// recv a chunk<br />
+    /// return Err if the transfer is rejected or failed, the transfer will be dropped<br />
+    /// return None if the transfer has failed or completed before, the chunk is discarded
+    pub(crate) async fn recv(&self, id: u64, offset: u64, total: u64, data: Vec<u8>) -> anyhow::Result<Option<ChunkRecv>> {
+        let is_last = offset.saturating_add(u64::try_from(data.len()).unwrap_or(u64::MAX)) >= total;
+        let mut failed = self.failed.lock().await;
+        if failed.contains(&id) {
+            if is_last { failed.remove(&id); }
+            return Ok(None);
+        }
+        let mut completed = self.completed.lock().await;
+        if completed.contains(&id) { return Ok(None); }
+
+        let mut transfers = self.transfers.lock().await;
+        self.drop_timed_out(&mut transfers, &mut failed, id).await;
+        let result = self.try_recv(&mut transfers, id, offset, total, data).await;
+        match result.as_ref() {
+            Ok(recv) => {
+                if recv.payload.is_some() {
+                    transfers.remove(&id);
+                    if completed.len() >= COMPLETED_LEN { completed.pop_front(); }
+                    completed.push_back(id);
+                }
+            }
+            Err(_) => {
+                if let Some(transfer) = transfers.remove(&id) { transfer.discard().await; }
+                if !is_last { failed.insert(id); }
+            }
+        }
+        result.map(Some)
+    }
+
+    /// drop the other transfers that have not received a chunk in time, the remaining chunks will be discarded
+    async fn drop_timed_out(&self, transfers: &mut HashMap<u64, Transfer>, failed: &mut HashSet<u64>, id: u64) {
+        let timed_out = transfers.iter()
+            .filter(|(transfer_id, transfer)| **transfer_id != id && transfer.last_recv.elapsed() >= self.conf.time_out)
+            .map(|(transfer_id, _)| *transfer_id)
+            .collect::<Vec<u64>>();
+        for transfer_id in timed_out {
+            let Some(transfer) = transfers.remove(&transfer_id) else { continue; };
+            log::warn!("chunk transfer[{transfer_id}] has not received a chunk in {:?}, will be dropped", self.conf.time_out);
+            transfer.discard().await;
+            failed.insert(transfer_id);
+        }
+    }
+
+    /// try recv a chunk
+    async fn try_recv(&self, transfers: &mut HashMap<u64, Transfer>, id: u64, offset: u64, total: u64, data: Vec<u8>) -> anyhow::Result<ChunkRecv> {
+        if total > self.conf.max_size {
+            return Err(anyhow::anyhow!("payload length {total} exceeds the max size {}", self.conf.max_size));
+        }
+        let len = u64::try_from(data.len())?;
+        if offset.checked_add(len).is_none_or(|end| end > total) {
+            return Err(anyhow::anyhow!("chunk[offset {offset}, length {len}] exceeds the payload length {total}"));
+        }
+
+        if !transfers.contains_key(&id) {
+            self.check_limit(transfers, total)?;
+        }
+        let transfer = match transfers.entry(id) {
+            Entry::Occupied(transfer) => { transfer.into_mut() }
+            Entry::Vacant(transfer) => { transfer.insert(self.new_transfer(id, total).await?) }
+        };
+        if transfer.total != total {
+            return Err(anyhow::anyhow!("payload length changed from {} to {total}", transfer.total));
+        }
+
+        let mut chunk_data = None;
+        match &mut transfer.target {
+            TransferTarget::Memory(payload) => {
+                let offset = usize::try_from(offset)?;
+                payload[offset..offset + data.len()].copy_from_slice(data.as_slice());
+            }
+            TransferTarget::File(file, _) => {
+                file.seek(io::SeekFrom::Start(offset)).await?;
+                file.write_all(data.as_slice()).await?;
+            }
+            TransferTarget::Stream => { chunk_data = Some(ChunkData { id, offset, total, data }); }
+        }
+        // duplicate or overlapping chunks are counted once
+        transfer.received.insert(offset, offset + len);
+        transfer.last_recv = Instant::now();
+
+        let progress = ChunkProgress { id, done: transfer.received.len(), total };
+        if !transfer.received.is_complete(total) {
+            return Ok(ChunkRecv { progress, data: chunk_data, payload: None });
+        }
+
+        let payload = match &mut transfer.target {
+            TransferTarget::Memory(payload) => { ChunkPayload::Memory(std::mem::take(payload)) }
+            TransferTarget::File(file, path) => {
+                file.flush().await?;
+                ChunkPayload::File(path.clone())
+            }
+            TransferTarget::Stream => { ChunkPayload::Stream }
+        };
+        Ok(ChunkRecv { progress, data: chunk_data, payload: Some(payload) })
+    }
+
+    /// check the number and the total length of transfers being received before a new transfer
+    fn check_limit(&self, transfers: &HashMap<u64, Transfer>, total: u64) -> anyhow::Result<()> {
+        if transfers.len() >= self.conf.max_transfers {
+            return Err(anyhow::anyhow!("the number of transfers being received exceeds the max transfers {}", self.conf.max_transfers));
+        }
+        let all_total = transfers.values().map(|transfer| transfer.total).sum::<u64>().saturating_add(total);
+        if all_total > self.conf.max_total_size {
+            return Err(anyhow::anyhow!("the total length {all_total} of transfers being received exceeds the max total size {}", self.conf.max_total_size));
+        }
+        Ok(())
+    }
+
+    /// create transfer
+    async fn new_transfer(&self, id: u64, total: u64) -> anyhow::Result<Transfer> {
+        let target = match &self.conf.recv_mode {
+            ChunkRecvMode::Memory => { TransferTarget::Memory(vec![0; usize::try_from(total)?]) }
+            ChunkRecvMode::File(dir) => {
+                tokio::fs::create_dir_all(dir).await?;
+                let path = dir.join(format!("{}{id}.chunk", self.file_prefix));
+                let file = File::create(path.as_path()).await?;
+                if let Err(e) = file.set_len(total).await {
+                    drop(file);
+                    let _ = tokio::fs::remove_file(path.as_path()).await;
+                    return Err(e.into());
+                }
+                TransferTarget::File(file, path)
+            }
+            ChunkRecvMode::Stream => { TransferTarget::Stream }
+        };
+        Ok(Transfer { total, received: Ranges::default(), last_recv: Instant::now(), target })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use cbsk_base::tokio;
+    use crate::business::chunk::{ChunkPayload, ChunkReceiver, Ranges};
+    use crate::business::chunk::config::ChunkConfig;
+
+    #[test]
+    fn ranges_count_overlapping_once() {
+        let mut ranges = Ranges::default();
+        ranges.insert(0, 10);
+        ranges.insert(0, 10);
+        ranges.insert(5, 10);
+        assert_eq!(ranges.len(), 10);
+        assert!(!ranges.is_complete(20));
+
+        ranges.insert(15, 20);
+        ranges.insert(8, 12);
+        assert_eq!(ranges.0, vec![(0, 12), (15, 20)]);
+        assert_eq!(ranges.len(), 17);
+        assert!(!ranges.is_complete(20));
+
+        ranges.insert(12, 15);
+        assert_eq!(ranges.0, vec![(0, 20)]);
+        assert!(ranges.is_complete(20));
+    }
+
+    #[test]
+    fn ranges_complete_only_from_start() {
+        let mut ranges = Ranges::default();
+        assert!(ranges.is_complete(0));
+        ranges.insert(1, 4);
+        ranges.insert(4, 4);
+        assert_eq!(ranges.len(), 3);
+        assert!(!ranges.is_complete(4));
+        ranges.insert(0, 1);
+        assert!(ranges.is_complete(4));
+    }
+
+    #[test]
+    fn late_chunk_of_completed_transfer_is_discarded() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        runtime.block_on(async {
+            let receiver = ChunkReceiver::new(Arc::new(ChunkConfig::default()), "test-");
+            assert!(receiver.recv(1, 0, 4, vec![1, 2]).await.unwrap().unwrap().payload.is_none());
+            let recv = receiver.recv(1, 2, 4, vec![3, 4]).await.unwrap().unwrap();
+            assert!(matches!(recv.payload, Some(ChunkPayload::Memory(payload)) if payload == vec![1, 2, 3, 4]));
+
+            // the duplicate chunk does not start the transfer again
+            assert!(receiver.recv(1, 0, 4, vec![1, 2]).await.unwrap().is_none());
+            assert!(receiver.transfers.lock().await.is_empty());
+        });
+    }
+}
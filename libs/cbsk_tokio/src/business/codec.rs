@@ -0,0 +1,315 @@
+use std::io;
+use std::sync::Arc;
+use cbsk::business;
+use cbsk::data::len_encoding::LenEncoding;
+use cbsk::hello::{FEATURE_CHUNK, FEATURE_FILE, FEATURE_RELIABLE, Hello};
+use cbsk::message::Message;
+use cbsk_base::log;
+use cbsk_socket_tokio::tcp::common::tcp_write_trait::TcpWriteTrait;
+use crate::business::compress;
+use crate::business::compress::config::CompressConfig;
+#[cfg(any(feature = "encrypt_aes_gcm", feature = "encrypt_chacha20"))]
+use crate::business::encrypt::Cipher;
+#[cfg(any(feature = "encrypt_aes_gcm", feature = "encrypt_chacha20"))]
+use crate::business::encrypt::config::EncryptConfig;
+
+/// cbsk frame codec, shared by all frames of a client or server
+pub(crate) struct Codec {
+    /// the cbsk first frame<br />
+    /// Used to determine if it is cbsk data
+    pub(crate) header: Arc<Vec<u8>>,
+    /// is message mode enabled, see [Message]
+    pub(crate) message: bool,
+    /// the encoding of the data length after the header frame
+    pub(crate) len_encoding: LenEncoding,
+    /// frame compression, if enabled, the first byte of each cbsk frame data is the compression flag
+    compress: Option<CompressConfig>,
+    /// frame encryption, if enabled, each cbsk frame data is encrypted with a random nonce
+    #[cfg(any(feature = "encrypt_aes_gcm", feature = "encrypt_chacha20"))]
+    cipher: Option<Arc<Cipher>>,
+    /// the feature bits agreed with the peer, None if negotiation is not applied, all enabled features can be used
+    features: Option<u64>,
+}
+
+/// decode cbsk frame data error
+pub(crate) enum DecodeError {
+    /// the data can not be decrypted or authenticated, carries the source data
+    #[cfg(any(feature = "encrypt_aes_gcm", feature = "encrypt_chacha20"))]
+    Decrypt(Vec<u8>),
+    /// the data can not be decompressed or is not a valid message, carries the source data
+    Invalid(Vec<u8>),
+}
+
+/// custom method
+impl Codec {
+    /// create codec
+    pub(crate) fn new(header: Arc<Vec<u8>>, message: bool) -> Self {
+        Self {
+            header,
+            message,
+            len_encoding: LenEncoding::default(),
+            compress: None,
+            #[cfg(any(feature = "encrypt_aes_gcm", feature = "encrypt_chacha20"))]
+            cipher: None,
+            features: None,
+        }
+    }
+
+    /// set the encoding of the data length
+    pub(crate) fn set_len_encoding(mut self, len_encoding: LenEncoding) -> Self {
+        self.len_encoding = len_encoding;
+        self
+    }
+
+    /// set frame compression
+    pub(crate) fn set_compress(mut self, compress: Option<CompressConfig>) -> Self {
+        self.compress = compress;
+        self
+    }
+
+    /// set frame encryption
+    #[cfg(any(feature = "encrypt_aes_gcm", feature = "encrypt_chacha20"))]
+    pub(crate) fn set_encrypt(mut self, encrypt: Option<EncryptConfig>) -> Self {
+        self.cipher = encrypt.as_ref().map(|conf| Cipher::new(conf).into());
+        self
+    }
+
+    /// create the hello of this side, advertise the enabled features<br />
+    /// reliable, chunk, file: is the message feature enabled
+    pub(crate) fn hello(&self, reliable: bool, chunk: bool, file: bool) -> Hello {
+        let messages = [(reliable, FEATURE_RELIABLE), (chunk, FEATURE_CHUNK), (file, FEATURE_FILE)];
+        let compress = self.compress.as_ref().map_or(0, |_| compress::features());
+        let features = messages.into_iter().filter(|(enabled, _)| *enabled).fold(compress, |features, (_, feature)| features | feature);
+        Hello::new(features | self.encrypt_feature())
+    }
+
+    /// the feature bit of frame encryption
+    #[cfg(any(feature = "encrypt_aes_gcm", feature = "encrypt_chacha20"))]
+    fn encrypt_feature(&self) -> u64 {
+        self.cipher.as_deref().map_or(0, Cipher::feature)
+    }
+
+    /// the feature bit of frame encryption, frame encryption is disabled
+    #[cfg(not(any(feature = "encrypt_aes_gcm", feature = "encrypt_chacha20")))]
+    fn encrypt_feature(&self) -> u64 {
+        0
+    }
+
+    /// create the codec of a connection by the negotiated feature bits, the feature not supported by both sides is disabled:<br />
+    /// compression is kept if both sides support any algorithm, message mode is kept if both sides support any message feature<br />
+    /// the peer that does not negotiate is negotiated with no feature bits, so plain cbsk frame is used<br />
+    /// return Err if frame encryption is enabled but the peer does not support the same algorithm
+    pub(crate) fn negotiate(&self, features: u64) -> io::Result<Self> {
+        let encrypt = self.encrypt_feature();
+        if features & encrypt != encrypt {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, "frame encryption is not supported by the peer"));
+        }
+
+        Ok(Self {
+            header: self.header.clone(),
+            message: self.message && features & (FEATURE_RELIABLE | FEATURE_CHUNK | FEATURE_FILE) != 0,
+            len_encoding: self.len_encoding,
+            compress: self.compress.and_then(|conf| compress::negotiate(conf, features)),
+            #[cfg(any(feature = "encrypt_aes_gcm", feature = "encrypt_chacha20"))]
+            cipher: self.cipher.clone(),
+            features: Some(features),
+        })
+    }
+
+    /// check the message feature can be used with the peer, see [Self::negotiate]<br />
+    /// return Err with [io::ErrorKind::Unsupported] if the feature is not supported by the peer
+    pub(crate) fn check_feature(&self, feature: u64, name: &str) -> io::Result<()> {
+        if self.features.is_none_or(|features| features & feature == feature) { return Ok(()); }
+        Err(io::Error::new(io::ErrorKind::Unsupported, format!("{name} is not supported by the peer")))
+    }
+
+    /// encode hello to cbsk frame, the hello frame data is sent as is
+    pub(crate) fn frame_hello(&self, hello: &Hello) -> io::Result<Vec<u8>> {
+        self.frame_with_len(hello.encode())
+    }
+
+    /// encode business data to cbsk frame<br />
+    /// return Err with [io::ErrorKind::InvalidInput] if the data can not be encoded
+    pub(crate) fn frame_data(&self, bytes: Vec<u8>) -> io::Result<Vec<u8>> {
+        if !self.message {
+            return self.frame(bytes);
+        }
+        self.frame_message(Message::Data(bytes))
+    }
+
+    /// encode message to cbsk frame, only used if message mode is enabled
+    pub(crate) fn frame_message(&self, message: Message) -> io::Result<Vec<u8>> {
+        self.frame(message.encode())
+    }
+
+    /// compress and encrypt data if enabled, and encode to cbsk frame<br />
+    /// return Err with [io::ErrorKind::InvalidInput] if the data can not be encrypted or is too long for the length encoding
+    fn frame(&self, bytes: Vec<u8>) -> io::Result<Vec<u8>> {
+        let bytes = match self.compress.as_ref() {
+            Some(conf) => { compress::compress(conf, bytes) }
+            None => { bytes }
+        };
+        #[cfg(any(feature = "encrypt_aes_gcm", feature = "encrypt_chacha20"))]
+        let bytes = match self.cipher.as_ref().map(|cipher| cipher.encrypt(bytes.as_slice())) {
+            Some(Ok(bytes)) => { bytes }
+            Some(Err(e)) => {
+                // only if the data is too long for the cipher
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("encrypt cbsk frame error: {e:?}")));
+            }
+            None => { bytes }
+        };
+        self.frame_with_len(bytes)
+    }
+
+    /// encode data to cbsk frame with the length encoding<br />
+    /// return Err with [io::ErrorKind::InvalidInput] if the data is too long for the length encoding
+    fn frame_with_len(&self, bytes: Vec<u8>) -> io::Result<Vec<u8>> {
+        business::frame_with_len(bytes, self.header.as_slice(), self.len_encoding).map_err(|bytes| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("data length {} exceeds the length encoding {:?}", bytes.len(), self.len_encoding))
+        })
+    }
+
+    /// decode cbsk frame data to message, if message mode is disabled, all data is business data<br />
+    /// return Err with the source data if the data can not be decrypted, decompressed or is not a valid message
+    pub(crate) fn decode(&self, bytes: Vec<u8>) -> Result<Message, DecodeError> {
+        #[cfg(any(feature = "encrypt_aes_gcm", feature = "encrypt_chacha20"))]
+        let bytes = match self.cipher.as_ref().map(|cipher| cipher.decrypt(bytes.as_slice())) {
+            Some(Ok(bytes)) => { bytes }
+            Some(Err(_)) => { return Err(DecodeError::Decrypt(bytes)); }
+            None => { bytes }
+        };
+        let bytes = match self.compress.as_ref() {
+            Some(conf) => {
+                match compress::decompress(conf, bytes.as_slice()) {
+                    Ok(bytes) => { bytes }
+                    Err(e) => {
+                        log::warn!("decompress cbsk frame error: {e:?}");
+                        return Err(DecodeError::Invalid(bytes));
+                    }
+                }
+            }
+            None => { bytes }
+        };
+
+        if !self.message {
+            return Ok(Message::Data(bytes));
+        }
+        Message::decode(bytes).map_err(DecodeError::Invalid)
+    }
+
+    /// encode business data to cbsk frame and send it
+    pub(crate) async fn send_data(&self, bytes: Vec<u8>, write: &impl TcpWriteTrait) -> io::Result<()> {
+        let frame = self.frame_data(bytes)?;
+        write.try_send_bytes(frame.as_slice()).await
+    }
+
+    /// encode message to cbsk frame and send it, only used if message mode is enabled
+    pub(crate) async fn send_message(&self, message: Message, write: &impl TcpWriteTrait) -> io::Result<()> {
+        let frame = self.frame_message(message)?;
+        write.try_send_bytes(frame.as_slice()).await
+    }
+
+    /// encode hello to cbsk frame and send it
+    pub(crate) async fn send_hello(&self, hello: &Hello, write: &impl TcpWriteTrait) -> io::Result<()> {
+        let frame = self.frame_hello(hello)?;
+        write.try_send_bytes(frame.as_slice()).await
+    }
+
+    /// send ack of the reliable data sequence number
+    pub(crate) async fn send_ack(&self, seq: u64, write: &impl TcpWriteTrait) {
+        if let Err(e) = self.send_message(Message::Ack(seq), write).await {
+            log::warn!("{} send ack[{seq}] error: {e:?}",write.get_log_head());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::Arc;
+    use cbsk::business;
+    use cbsk::data::decoded::Decoded;
+    use cbsk::hello::{FEATURE_CHUNK, FEATURE_RELIABLE};
+    use cbsk::message::Message;
+    use crate::business::codec::Codec;
+
+    fn codec(message: bool) -> Codec {
+        Codec::new(Arc::new(cbsk::data::default_header()), message)
+    }
+
+    /// get the data of the only cbsk frame
+    fn frame_data(frame: Vec<u8>, codec: &Codec) -> Vec<u8> {
+        let (decoded, rest) = business::decode(frame, codec.header.as_slice(), codec.len_encoding);
+        assert!(rest.is_empty());
+        match <[_; 1]>::try_from(decoded) {
+            Ok([Decoded::Data(data)]) => { data }
+            _ => { panic!("not a single data frame") }
+        }
+    }
+
+    /// decode the only cbsk frame to message
+    fn decode(frame: Vec<u8>, codec: &Codec) -> Message {
+        codec.decode(frame_data(frame, codec)).ok().unwrap()
+    }
+
+    #[test]
+    fn negotiate_message_mode() {
+        let (reliable, plain) = (codec(true), codec(false));
+        let hello = reliable.hello(true, false, false).negotiate(&plain.hello(false, false, false));
+        let (reliable, plain) = (reliable.negotiate(hello.features).unwrap(), plain.negotiate(hello.features).unwrap());
+        // the message feature is not supported by both sides, so the message kind is not framed
+        assert!(!reliable.message);
+        assert_eq!(reliable.frame_data(b"cbsk".to_vec()).unwrap(), plain.frame_data(b"cbsk".to_vec()).unwrap());
+        assert_eq!(reliable.check_feature(FEATURE_RELIABLE, "reliable").unwrap_err().kind(), io::ErrorKind::Unsupported);
+
+        // the message features supported by both sides can be used
+        let both = codec(true).negotiate(FEATURE_RELIABLE).unwrap();
+        assert!(both.check_feature(FEATURE_RELIABLE, "reliable").is_ok());
+        assert!(both.check_feature(FEATURE_CHUNK, "chunk").is_err());
+        assert!(matches!(decode(both.frame_message(Message::Ack(1)).unwrap(), &both), Message::Ack(1)));
+        // negotiation is not applied, all enabled features can be used
+        assert!(codec(true).check_feature(FEATURE_CHUNK, "chunk").is_ok());
+    }
+
+    #[cfg(any(feature = "compress_zstd", feature = "compress_lz4"))]
+    #[test]
+    fn negotiate_compress() {
+        use crate::business::compress::config::{CompressAlgorithm, CompressConfig};
+
+        #[cfg(feature = "compress_zstd")]
+        let algorithm = CompressAlgorithm::Zstd(0);
+        #[cfg(not(feature = "compress_zstd"))]
+        let algorithm = CompressAlgorithm::Lz4;
+        let compress = codec(false).set_compress(Some(CompressConfig::new(algorithm).set_threshold(0)));
+        let data = b"cbsk ".repeat(100);
+
+        // both sides support compression, the compressed frame has the compression flag
+        let both = compress.negotiate(compress.hello(false, false, false).features).unwrap();
+        let frame = both.frame_data(data.clone()).unwrap();
+        assert!(frame.len() < data.len());
+        assert!(matches!(decode(frame, &compress), Message::Data(received) if received == data));
+
+        // the peer that does not negotiate receives plain cbsk frame
+        let plain = compress.negotiate(0).unwrap();
+        assert_eq!(frame_data(plain.frame_data(data.clone()).unwrap(), &plain), data);
+    }
+
+    #[cfg(any(feature = "encrypt_aes_gcm", feature = "encrypt_chacha20"))]
+    #[test]
+    fn negotiate_encrypt() {
+        use crate::business::encrypt::config::{EncryptAlgorithm, EncryptConfig};
+
+        #[cfg(feature = "encrypt_aes_gcm")]
+        let algorithm = EncryptAlgorithm::Aes256Gcm;
+        #[cfg(not(feature = "encrypt_aes_gcm"))]
+        let algorithm = EncryptAlgorithm::ChaCha20Poly1305;
+        let encrypt = codec(false).set_encrypt(Some(EncryptConfig::new(algorithm, [1; 32])));
+
+        // encryption is never dropped by negotiation
+        assert_eq!(encrypt.negotiate(0).err().unwrap().kind(), io::ErrorKind::PermissionDenied);
+        let both = encrypt.negotiate(encrypt.hello(false, false, false).features).unwrap();
+        let frame = both.frame_data(b"cbsk".to_vec()).unwrap();
+        assert_ne!(frame_data(frame.clone(), &both), b"cbsk");
+        assert!(matches!(decode(frame, &encrypt), Message::Data(data) if data == b"cbsk"));
+    }
+}
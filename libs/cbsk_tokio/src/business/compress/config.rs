@@ -0,0 +1,41 @@
+/// compression algorithm
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompressAlgorithm {
+    /// zstd with compression level, 0 is the default level
+    #[cfg(feature = "compress_zstd")]
+    Zstd(i32),
+    /// lz4 block format
+    #[cfg(feature = "compress_lz4")]
+    Lz4,
+}
+
+/// frame compression config
+#[derive(Clone, Copy, Debug)]
+pub struct CompressConfig {
+    /// the algorithm used to compress sent frames, received frames can use any compiled algorithm
+    pub algorithm: CompressAlgorithm,
+    /// frames smaller than this length are sent raw, default is 256 bytes
+    pub threshold: usize,
+    /// the max length of decompressed frame, larger frames will be discarded, default is 16 MiB
+    pub max_size: usize,
+}
+
+/// custom method
+impl CompressConfig {
+    /// create frame compression config
+    pub fn new(algorithm: CompressAlgorithm) -> Self {
+        Self { algorithm, threshold: 256, max_size: 16 * 1024 * 1024 }
+    }
+
+    /// set frames smaller than this length are sent raw
+    pub fn set_threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// set the max length of decompressed frame
+    pub fn set_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+}
@@ -0,0 +1,173 @@
+use std::io;
+#[cfg(feature = "compress_zstd")]
+use std::io::Read;
+#[cfg(feature = "compress_zstd")]
+use cbsk::hello::FEATURE_COMPRESS_ZSTD;
+#[cfg(feature = "compress_lz4")]
+use cbsk::hello::FEATURE_COMPRESS_LZ4;
+#[cfg(any(feature = "compress_zstd", feature = "compress_lz4"))]
+use crate::business::compress::config::CompressAlgorithm;
+use crate::business::compress::config::CompressConfig;
+
+pub mod config;
+
+/// the frame is not compressed
+const FLAG_RAW: u8 = 0;
+/// the frame is compressed by zstd
+#[cfg(feature = "compress_zstd")]
+const FLAG_ZSTD: u8 = 1;
+/// the frame is compressed by lz4
+#[cfg(feature = "compress_lz4")]
+const FLAG_LZ4: u8 = 2;
+
+/// the feature bits of all compiled algorithms, received frames can use any compiled algorithm
+pub(crate) fn features() -> u64 {
+    let features: &[u64] = &[
+        #[cfg(feature = "compress_zstd")]
+        FEATURE_COMPRESS_ZSTD,
+        #[cfg(feature = "compress_lz4")]
+        FEATURE_COMPRESS_LZ4,
+    ];
+    features.iter().fold(0, |features, feature| features | feature)
+}
+
+/// the feature bit of the algorithm
+#[cfg(any(feature = "compress_zstd", feature = "compress_lz4"))]
+fn feature(algorithm: CompressAlgorithm) -> u64 {
+    match algorithm {
+        #[cfg(feature = "compress_zstd")]
+        CompressAlgorithm::Zstd(_) => { FEATURE_COMPRESS_ZSTD }
+        #[cfg(feature = "compress_lz4")]
+        CompressAlgorithm::Lz4 => { FEATURE_COMPRESS_LZ4 }
+    }
+}
+
+/// keep the compression if both sides support any algorithm, see [features]<br />
+/// if the peer does not support the configured algorithm, the first algorithm supported by both sides is used to compress sent frames<br />
+/// return None if no algorithm is supported by both sides, frames are not compressed and have no compression flag
+pub(crate) fn negotiate(conf: CompressConfig, features: u64) -> Option<CompressConfig> {
+    #[cfg(any(feature = "compress_zstd", feature = "compress_lz4"))]
+    {
+        if features & feature(conf.algorithm) != 0 { return Some(conf); }
+        let algorithms = [
+            #[cfg(feature = "compress_zstd")]
+            CompressAlgorithm::Zstd(0),
+            #[cfg(feature = "compress_lz4")]
+            CompressAlgorithm::Lz4,
+        ];
+        let algorithm = algorithms.into_iter().find(|algorithm| features & feature(*algorithm) != 0)?;
+        Some(CompressConfig { algorithm, ..conf })
+    }
+    #[cfg(not(any(feature = "compress_zstd", feature = "compress_lz4")))]
+    {
+        let _ = (conf, features);
+        None
+    }
+}
+
+/// compress frame data, the first byte is the compression flag<br />
+/// if the data is smaller than the threshold or compression does not reduce the length, the data is sent raw
+pub(crate) fn compress(conf: &CompressConfig, mut bytes: Vec<u8>) -> Vec<u8> {
+    #[cfg(any(feature = "compress_zstd", feature = "compress_lz4"))]
+    if bytes.len() >= conf.threshold {
+        let compressed: Option<(u8, Vec<u8>)> = match conf.algorithm {
+            #[cfg(feature = "compress_zstd")]
+            CompressAlgorithm::Zstd(level) => { zstd::bulk::compress(bytes.as_slice(), level).ok().map(|data| (FLAG_ZSTD, data)) }
+            #[cfg(feature = "compress_lz4")]
+            CompressAlgorithm::Lz4 => { Some((FLAG_LZ4, lz4_flex::compress_prepend_size(bytes.as_slice()))) }
+        };
+        if let Some((flag, mut data)) = compressed.filter(|(_, data)| data.len() < bytes.len()) {
+            data.insert(0, flag);
+            return data;
+        }
+    }
+    #[cfg(not(any(feature = "compress_zstd", feature = "compress_lz4")))]
+    let _ = conf;
+
+    bytes.insert(0, FLAG_RAW);
+    bytes
+}
+
+/// decompress frame data by the compression flag
+pub(crate) fn decompress(conf: &CompressConfig, bytes: &[u8]) -> io::Result<Vec<u8>> {
+    #[cfg(not(any(feature = "compress_zstd", feature = "compress_lz4")))]
+    let _ = conf;
+    let Some((flag, data)) = bytes.split_first() else {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "compression flag is missing"));
+    };
+    match *flag {
+        FLAG_RAW => { Ok(data.to_vec()) }
+        #[cfg(feature = "compress_zstd")]
+        FLAG_ZSTD => { decompress_zstd(data, conf.max_size) }
+        #[cfg(feature = "compress_lz4")]
+        FLAG_LZ4 => {
+            // the first 4 bytes is the little endian decompressed length
+            let len = data.get(..4).map_or(usize::MAX, |len| u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize);
+            if len > conf.max_size { return Err(too_large(len, conf.max_size)); }
+            lz4_flex::decompress_size_prepended(data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        }
+        flag => { Err(io::Error::new(io::ErrorKind::Unsupported, format!("unsupported compression flag {flag}"))) }
+    }
+}
+
+/// decompress zstd frame, the buffer is allocated by the content size saved in the frame instead of the max size<br />
+/// if the content size is not saved, decompress by stream and stop reading after the max size
+#[cfg(feature = "compress_zstd")]
+fn decompress_zstd(data: &[u8], max_size: usize) -> io::Result<Vec<u8>> {
+    let content_size = zstd::zstd_safe::get_frame_content_size(data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    if let Some(len) = content_size {
+        let len = usize::try_from(len).unwrap_or(usize::MAX);
+        if len > max_size { return Err(too_large(len, max_size)); }
+        return zstd::bulk::decompress(data, len);
+    }
+
+    let mut bytes = Vec::new();
+    let limit = u64::try_from(max_size).unwrap_or(u64::MAX).saturating_add(1);
+    zstd::stream::read::Decoder::new(data)?.take(limit).read_to_end(&mut bytes)?;
+    if bytes.len() > max_size { return Err(too_large(bytes.len(), max_size)); }
+    Ok(bytes)
+}
+
+/// the decompressed length exceeds the max size
+#[cfg(any(feature = "compress_zstd", feature = "compress_lz4"))]
+fn too_large(len: usize, max_size: usize) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("decompressed length {len} exceeds the max size {max_size}"))
+}
+
+#[cfg(all(test, feature = "compress_zstd"))]
+mod tests {
+    use crate::business::compress::config::{CompressAlgorithm, CompressConfig};
+    use crate::business::compress::{compress, decompress, FLAG_ZSTD};
+
+    fn config(max_size: usize) -> CompressConfig {
+        CompressConfig::new(CompressAlgorithm::Zstd(0)).set_threshold(0).set_max_size(max_size)
+    }
+
+    #[test]
+    fn round_trip() {
+        let data = b"cbsk ".repeat(1000);
+        let compressed = compress(&config(data.len()), data.clone());
+        assert_eq!(compressed[0], FLAG_ZSTD);
+        assert_eq!(decompress(&config(data.len()), &compressed).unwrap(), data);
+    }
+
+    #[test]
+    fn content_size_exceeds_max_size() {
+        let compressed = compress(&config(usize::MAX), vec![0; 1024 * 1024]);
+        assert_eq!(compressed[0], FLAG_ZSTD);
+        let e = decompress(&config(1000), &compressed).unwrap_err();
+        assert!(e.to_string().contains("1048576 exceeds the max size 1000"), "{e}");
+    }
+
+    #[test]
+    fn unknown_content_size() {
+        // the stream encoder does not save the content size
+        let data = b"cbsk ".repeat(1000);
+        let stream = zstd::stream::encode_all(data.as_slice(), 0).unwrap();
+        assert!(matches!(zstd::zstd_safe::get_frame_content_size(&stream), Ok(None)));
+        let compressed = [[FLAG_ZSTD].as_slice(), &stream].concat();
+
+        assert_eq!(decompress(&config(data.len()), &compressed).unwrap(), data);
+        assert!(decompress(&config(data.len() - 1), &compressed).is_err());
+    }
+}
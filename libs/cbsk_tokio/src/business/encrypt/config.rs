@@ -0,0 +1,40 @@
+/// encryption algorithm
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EncryptAlgorithm {
+    /// AES-256-GCM
+    #[cfg(feature = "encrypt_aes_gcm")]
+    Aes256Gcm,
+    /// ChaCha20-Poly1305
+    #[cfg(feature = "encrypt_chacha20")]
+    ChaCha20Poly1305,
+}
+
+/// frame encryption config
+#[derive(Clone)]
+pub struct EncryptConfig {
+    /// encryption algorithm
+    pub algorithm: EncryptAlgorithm,
+    /// 256 bit pre-shared key
+    key: [u8; 32],
+}
+
+/// custom method
+impl EncryptConfig {
+    /// create frame encryption config<br />
+    /// key: 256 bit pre-shared key, the peer must use the same algorithm and key
+    pub fn new(algorithm: EncryptAlgorithm, key: [u8; 32]) -> Self {
+        Self { algorithm, key }
+    }
+
+    /// get the pre-shared key
+    pub(crate) fn get_key(&self) -> &[u8; 32] {
+        &self.key
+    }
+}
+
+/// support debug, the key is not printed
+impl std::fmt::Debug for EncryptConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptConfig").field("algorithm", &self.algorithm).finish_non_exhaustive()
+    }
+}
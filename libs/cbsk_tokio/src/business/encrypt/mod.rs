@@ -0,0 +1,155 @@
+#[cfg(feature = "encrypt_aes_gcm")]
+use aes_gcm::aead;
+#[cfg(all(feature = "encrypt_chacha20", not(feature = "encrypt_aes_gcm")))]
+use chacha20poly1305::aead;
+use aead::{Aead, AeadCore, KeyInit, OsRng};
+#[cfg(feature = "encrypt_aes_gcm")]
+use cbsk::hello::FEATURE_ENCRYPT_AES_GCM;
+#[cfg(feature = "encrypt_chacha20")]
+use cbsk::hello::FEATURE_ENCRYPT_CHACHA20;
+use crate::business::encrypt::config::{EncryptAlgorithm, EncryptConfig};
+
+pub mod config;
+
+/// the nonce length of each frame
+const NONCE_LEN: usize = 12;
+
+/// frame cipher, created from [EncryptConfig]
+pub(crate) enum Cipher {
+    /// AES-256-GCM
+    #[cfg(feature = "encrypt_aes_gcm")]
+    Aes256Gcm(Box<aes_gcm::Aes256Gcm>),
+    /// ChaCha20-Poly1305
+    #[cfg(feature = "encrypt_chacha20")]
+    ChaCha20Poly1305(Box<chacha20poly1305::ChaCha20Poly1305>),
+}
+
+/// custom method
+impl Cipher {
+    /// create frame cipher
+    pub(crate) fn new(conf: &EncryptConfig) -> Self {
+        match conf.algorithm {
+            #[cfg(feature = "encrypt_aes_gcm")]
+            EncryptAlgorithm::Aes256Gcm => { Self::Aes256Gcm(aes_gcm::Aes256Gcm::new(conf.get_key().into()).into()) }
+            #[cfg(feature = "encrypt_chacha20")]
+            EncryptAlgorithm::ChaCha20Poly1305 => {
+                Self::ChaCha20Poly1305(chacha20poly1305::ChaCha20Poly1305::new(conf.get_key().into()).into())
+            }
+        }
+    }
+
+    /// the feature bit of the algorithm
+    pub(crate) fn feature(&self) -> u64 {
+        match self {
+            #[cfg(feature = "encrypt_aes_gcm")]
+            Self::Aes256Gcm(_) => { FEATURE_ENCRYPT_AES_GCM }
+            #[cfg(feature = "encrypt_chacha20")]
+            Self::ChaCha20Poly1305(_) => { FEATURE_ENCRYPT_CHACHA20 }
+        }
+    }
+
+    /// encrypt frame data with a random nonce, the nonce is placed before the ciphertext
+    pub(crate) fn encrypt(&self, bytes: &[u8]) -> Result<Vec<u8>, aead::Error> {
+        match self {
+            #[cfg(feature = "encrypt_aes_gcm")]
+            Self::Aes256Gcm(cipher) => { seal(cipher.as_ref(), bytes) }
+            #[cfg(feature = "encrypt_chacha20")]
+            Self::ChaCha20Poly1305(cipher) => { seal(cipher.as_ref(), bytes) }
+        }
+    }
+
+    /// decrypt and authenticate frame data
+    pub(crate) fn decrypt(&self, bytes: &[u8]) -> Result<Vec<u8>, aead::Error> {
+        match self {
+            #[cfg(feature = "encrypt_aes_gcm")]
+            Self::Aes256Gcm(cipher) => { open(cipher.as_ref(), bytes) }
+            #[cfg(feature = "encrypt_chacha20")]
+            Self::ChaCha20Poly1305(cipher) => { open(cipher.as_ref(), bytes) }
+        }
+    }
+}
+
+/// encrypt with a random nonce
+fn seal<C: Aead + AeadCore>(cipher: &C, bytes: &[u8]) -> Result<Vec<u8>, aead::Error> {
+    let nonce = C::generate_nonce(&mut OsRng);
+    let mut sealed = nonce.to_vec();
+    sealed.append(&mut cipher.encrypt(&nonce, bytes)?);
+    Ok(sealed)
+}
+
+/// split nonce and decrypt
+fn open<C: Aead + AeadCore>(cipher: &C, bytes: &[u8]) -> Result<Vec<u8>, aead::Error> {
+    if bytes.len() < NONCE_LEN { return Err(aead::Error); }
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+    cipher.decrypt(nonce.into(), ciphertext)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::business::encrypt::{Cipher, NONCE_LEN};
+    use crate::business::encrypt::config::{EncryptAlgorithm, EncryptConfig};
+
+    /// all enabled algorithms
+    fn algorithms() -> Vec<EncryptAlgorithm> {
+        vec![
+            #[cfg(feature = "encrypt_aes_gcm")]
+            EncryptAlgorithm::Aes256Gcm,
+            #[cfg(feature = "encrypt_chacha20")]
+            EncryptAlgorithm::ChaCha20Poly1305,
+        ]
+    }
+
+    fn cipher(algorithm: EncryptAlgorithm, key: u8) -> Cipher {
+        Cipher::new(&EncryptConfig::new(algorithm, [key; 32]))
+    }
+
+    #[test]
+    fn round_trip() {
+        for algorithm in algorithms() {
+            let cipher = cipher(algorithm, 1);
+            for data in [b"".as_slice(), b"hello cbsk", &[0xAB; 4096]] {
+                let sealed = cipher.encrypt(data).unwrap();
+                // nonce + ciphertext + 16 byte tag
+                assert_eq!(sealed.len(), NONCE_LEN + data.len() + 16, "{algorithm:?}");
+                if !data.is_empty() {
+                    assert_ne!(&sealed[NONCE_LEN..NONCE_LEN + data.len()], data, "{algorithm:?}");
+                }
+                assert_eq!(cipher.decrypt(&sealed).unwrap(), data, "{algorithm:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn nonce_is_unique_per_frame() {
+        for algorithm in algorithms() {
+            let cipher = cipher(algorithm, 1);
+            let first = cipher.encrypt(b"same data").unwrap();
+            let second = cipher.encrypt(b"same data").unwrap();
+            assert_ne!(first[..NONCE_LEN], second[..NONCE_LEN], "{algorithm:?}");
+            assert_ne!(first, second, "{algorithm:?}");
+            assert_eq!(cipher.decrypt(&first).unwrap(), cipher.decrypt(&second).unwrap(), "{algorithm:?}");
+        }
+    }
+
+    #[test]
+    fn tampered_frame_is_rejected() {
+        for algorithm in algorithms() {
+            let cipher = cipher(algorithm, 1);
+            let sealed = cipher.encrypt(b"hello cbsk").unwrap();
+
+            // flip one bit in the nonce, the ciphertext and the tag
+            for i in [0, NONCE_LEN, sealed.len() - 1] {
+                let mut tampered = sealed.clone();
+                tampered[i] ^= 1;
+                assert!(cipher.decrypt(&tampered).is_err(), "{algorithm:?} byte {i}");
+            }
+
+            // truncated frame, shorter than the nonce
+            assert!(cipher.decrypt(&sealed[..sealed.len() - 1]).is_err(), "{algorithm:?}");
+            assert!(cipher.decrypt(&sealed[..NONCE_LEN - 1]).is_err(), "{algorithm:?}");
+
+            // other key
+            assert!(self::cipher(algorithm, 2).decrypt(&sealed).is_err(), "{algorithm:?}");
+        }
+    }
+}
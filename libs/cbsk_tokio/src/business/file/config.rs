@@ -0,0 +1,74 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// file transfer config
+#[derive(Clone, Debug)]
+pub struct FileConfig {
+    /// received files are saved in this directory,
+    /// incomplete files are saved as .part files and resumed by the next transfer of the same file, also after the sender or receiver restarted
+    pub dir: PathBuf,
+    /// the max data length of each frame when sending, default is 64 KiB
+    pub chunk_size: usize,
+    /// the receiver confirms the offset after receiving this length of data, default is 1 MiB
+    pub ack_size: u64,
+    /// the max size of received file, larger files will be rejected, default is 4 GiB
+    pub max_size: u64,
+    /// the time to wait for the peer reply when sending, default is 30 seconds
+    pub time_out: Duration,
+    /// the time to wait for the receiver verifying the file after all data is sent, default is 10 minutes
+    pub verify_time_out: Duration,
+    /// overwrite the existing file with the same name in dir when receiving, default is false, the file is rejected
+    pub overwrite: bool,
+}
+
+/// custom method
+impl FileConfig {
+    /// create file transfer config, received files are saved in dir
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            chunk_size: 64 * 1024,
+            ack_size: 1024 * 1024,
+            max_size: 4 * 1024 * 1024 * 1024,
+            time_out: Duration::from_secs(30),
+            verify_time_out: Duration::from_secs(600),
+            overwrite: false,
+        }
+    }
+
+    /// set the max data length of each frame when sending
+    pub fn set_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// set the receiver confirms the offset after receiving this length of data
+    pub fn set_ack_size(mut self, ack_size: u64) -> Self {
+        self.ack_size = ack_size;
+        self
+    }
+
+    /// set the max size of received file
+    pub fn set_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// set the time to wait for the peer reply when sending
+    pub fn set_time_out(mut self, time_out: Duration) -> Self {
+        self.time_out = time_out;
+        self
+    }
+
+    /// set the time to wait for the receiver verifying the file after all data is sent
+    pub fn set_verify_time_out(mut self, verify_time_out: Duration) -> Self {
+        self.verify_time_out = verify_time_out;
+        self
+    }
+
+    /// set overwrite the existing file with the same name when receiving
+    pub fn set_overwrite(mut self, overwrite: bool) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+}
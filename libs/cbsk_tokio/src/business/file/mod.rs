@@ -0,0 +1,423 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, PoisonError};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use cbsk::checksum::Crc32;
+use cbsk::hello::FEATURE_FILE;
+use cbsk::message::Message;
+use cbsk_base::{anyhow, log, tokio};
+use cbsk_base::tokio::fs::File;
+use cbsk_base::tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use cbsk_base::tokio::sync::{mpsc, Mutex};
+use cbsk_socket_tokio::tcp::common::tcp_write_trait::TcpWriteTrait;
+use crate::business::codec::Codec;
+use crate::business::file::config::FileConfig;
+
+pub mod config;
+
+/// the last file transfer id, starts from the current time, so that different senders rarely use the same id
+static FILE_ID: LazyLock<AtomicU64> = LazyLock::new(|| {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    AtomicU64::new(u64::try_from(now.as_nanos()).unwrap_or_default())
+});
+
+/// get next file transfer id, unique within the process
+fn next_file_id() -> u64 {
+    FILE_ID.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// file metadata
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileMeta {
+    /// transfer id
+    pub id: u64,
+    /// file name, not including directory
+    pub name: String,
+    /// file size
+    pub size: u64,
+    /// crc32 of the file, see [cbsk::checksum::crc32]
+    pub checksum: u32,
+}
+
+/// the receiver reply
+enum FileReply {
+    /// confirmed offset
+    Ack(u64),
+    /// integrity verification result
+    Done(bool),
+}
+
+/// file sender
+pub(crate) struct FileSender {
+    /// file transfer config
+    conf: Arc<FileConfig>,
+    /// the receiver reply of sending files, key is transfer id
+    replies: std::sync::Mutex<HashMap<u64, mpsc::UnboundedSender<FileReply>>>,
+    /// the transfer id of the files that failed to send, the next transfer of the same file uses the same id to resume
+    unfinished: std::sync::Mutex<HashMap<PathBuf, u64>>,
+}
+
+/// custom method
+impl FileSender {
+    /// create file sender
+    pub(crate) fn new(conf: Arc<FileConfig>) -> Self {
+        Self { conf, replies: std::sync::Mutex::default(), unfinished: std::sync::Mutex::default() }
+    }
+
+    /// the receiver confirmed offset
+    pub(crate) fn ack(&self, id: u64, offset: u64) {
+        self.reply(id, FileReply::Ack(offset));
+    }
+
+    /// the receiver finished verification
+    pub(crate) fn done(&self, id: u64, ok: bool) {
+        self.reply(id, FileReply::Done(ok));
+    }
+
+    /// send reply to the sending file
+    fn reply(&self, id: u64, reply: FileReply) {
+        let replies = self.replies.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(sender) = replies.get(&id) {
+            let _ = sender.send(reply);
+        }
+    }
+
+    /// send file, resume from the offset confirmed by the receiver<br />
+    /// return Ok after the receiver verified the file
+    pub(crate) async fn send(&self, path: &Path, write: &impl TcpWriteTrait, codec: &Codec) -> io::Result<FileMeta> {
+        codec.check_feature(FEATURE_FILE, "file transfer")?;
+        let (sender, mut replies) = mpsc::unbounded_channel();
+        // the unfinished transfer of the same file is resumed by the same id
+        let id = self.unfinished.lock().unwrap_or_else(PoisonError::into_inner).remove(path).unwrap_or_else(next_file_id);
+        self.replies.lock().unwrap_or_else(PoisonError::into_inner).insert(id, sender);
+        let result = self.try_send(id, path, write, codec, &mut replies).await;
+        self.replies.lock().unwrap_or_else(PoisonError::into_inner).remove(&id);
+        if result.is_err() { self.unfinished.lock().unwrap_or_else(PoisonError::into_inner).insert(path.to_path_buf(), id); }
+        result
+    }
+
+    /// try send file
+    async fn try_send(&self, id: u64, path: &Path, write: &impl TcpWriteTrait, codec: &Codec, replies: &mut mpsc::UnboundedReceiver<FileReply>)
+                      -> io::Result<FileMeta> {
+        let name = path.file_name().map(|name| name.to_string_lossy().to_string())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "file name is empty"))?;
+        let mut file = File::open(path).await?;
+        let size = file.metadata().await?.len();
+        let checksum = checksum(&mut file, self.conf.chunk_size).await?;
+        let meta = FileMeta { id, name, size, checksum };
+
+        codec.send_message(Message::FileOffer { id, size, checksum, name: meta.name.clone() }, write).await?;
+        let mut offset = match self.wait_reply(replies, self.conf.time_out).await? {
+            FileReply::Ack(offset) if offset <= size => { offset }
+            _ => { return Err(io::Error::new(io::ErrorKind::InvalidData, "file is rejected by the receiver")); }
+        };
+        if offset > 0 { log::info!("{} resume file[{}] from offset {offset}",write.get_log_head(),meta.name); }
+
+        file.seek(io::SeekFrom::Start(offset)).await?;
+        while offset < size {
+            let len = usize::try_from(size - offset).unwrap_or(usize::MAX).min(self.conf.chunk_size);
+            let mut data = vec![0; len];
+            file.read_exact(data.as_mut_slice()).await?;
+
+            codec.send_message(Message::FileData { id, offset, data }, write).await?;
+            offset += u64::try_from(len).map_err(io::Error::other)?;
+        }
+
+        // the receiver verifies the whole file after all data is received
+        loop {
+            match self.wait_reply(replies, self.conf.verify_time_out).await? {
+                FileReply::Ack(_) => { continue; }
+                FileReply::Done(true) => { return Ok(meta); }
+                FileReply::Done(false) => {
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, "file integrity verification failed"));
+                }
+            }
+        }
+    }
+
+    /// wait the receiver reply
+    async fn wait_reply(&self, replies: &mut mpsc::UnboundedReceiver<FileReply>, time_out: Duration) -> io::Result<FileReply> {
+        match tokio::time::timeout(time_out, replies.recv()).await {
+            Ok(Some(reply)) => { Ok(reply) }
+            _ => { Err(io::Error::new(io::ErrorKind::TimedOut, "wait file transfer reply timeout")) }
+        }
+    }
+}
+
+/// the result of receiving a file message
+pub(crate) enum FileRecv {
+    /// receiving, or the message is discarded
+    Pending,
+    /// all data is received, the file should be verified by [FileVerify::run] outside the receive path
+    Verify(FileVerify),
+    /// the file is received and verified
+    Finished(FileMeta, PathBuf),
+    /// receive file failed
+    Failed(FileMeta, anyhow::Error),
+}
+
+/// the .part files opened in this process, value is the number of opened times<br />
+/// the .part file that is not opened is left by an interrupted transfer, and can be resumed by the next transfer of the same file
+static PART_FILES: LazyLock<std::sync::Mutex<HashMap<PathBuf, usize>>> = LazyLock::new(std::sync::Mutex::default);
+
+/// the .part file is opened until dropped
+struct PartClaim(PathBuf);
+
+/// custom method
+impl PartClaim {
+    /// mark the .part file is opened
+    fn new(part: PathBuf) -> Self {
+        *PART_FILES.lock().unwrap_or_else(PoisonError::into_inner).entry(part.clone()).or_default() += 1;
+        Self(part)
+    }
+
+    /// mark the .part file is opened if it is not opened
+    fn try_new(part: PathBuf) -> Option<Self> {
+        let mut part_files = PART_FILES.lock().unwrap_or_else(PoisonError::into_inner);
+        if part_files.contains_key(&part) { return None; }
+        part_files.insert(part.clone(), 1);
+        Some(Self(part))
+    }
+}
+
+/// support drop
+impl Drop for PartClaim {
+    fn drop(&mut self) {
+        let mut part_files = PART_FILES.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(count) = part_files.get_mut(&self.0) else { return; };
+        *count -= 1;
+        if *count == 0 { part_files.remove(&self.0); }
+    }
+}
+
+/// a file being received
+struct RecvFile {
+    /// file metadata
+    meta: FileMeta,
+    /// the .part file
+    file: File,
+    /// the .part file path
+    part: PathBuf,
+    /// the .part file is opened
+    _claim: PartClaim,
+    /// the length of data saved
+    written: u64,
+    /// the last confirmed offset
+    acked: u64,
+}
+
+/// file receiver, save received files to directory
+pub(crate) struct FileReceiver {
+    /// file transfer config
+    conf: Arc<FileConfig>,
+    /// files being received, key is transfer id
+    files: Mutex<HashMap<u64, RecvFile>>,
+}
+
+/// custom method
+impl FileReceiver {
+    /// create file receiver
+    pub(crate) fn new(conf: Arc<FileConfig>) -> Self {
+        Self { conf, files: Mutex::default() }
+    }
+
+    /// drop all incomplete files, the .part files are kept to resume, example: the connection is lost
+    #[cfg(feature = "client")]
+    pub(crate) async fn clear(&self) {
+        self.files.lock().await.clear();
+    }
+
+    /// the sender offers a file, reply the offset to resume from
+    pub(crate) async fn offer(&self, meta: FileMeta, write: &impl TcpWriteTrait, codec: &Codec) -> FileRecv {
+        let id = meta.id;
+        let recv_file = match self.open(meta.clone()).await {
+            Ok(recv_file) => { recv_file }
+            Err(e) => {
+                Self::send(Message::FileDone { id, ok: false }, write, codec).await;
+                return FileRecv::Failed(meta, e);
+            }
+        };
+
+        Self::send(Message::FileAck { id, offset: recv_file.written }, write, codec).await;
+        if recv_file.written < recv_file.meta.size {
+            self.files.lock().await.insert(id, recv_file);
+            return FileRecv::Pending;
+        }
+        // all data has been received by the previous transfer
+        self.finish(recv_file)
+    }
+
+    /// recv file data
+    pub(crate) async fn data(&self, id: u64, offset: u64, data: Vec<u8>, write: &impl TcpWriteTrait, codec: &Codec) -> FileRecv {
+        let mut files = self.files.lock().await;
+        let Some(recv_file) = files.get_mut(&id) else { return FileRecv::Pending; };
+
+        if let Err(e) = Self::write(recv_file, offset, data.as_slice()).await {
+            let meta = recv_file.meta.clone();
+            files.remove(&id);
+            Self::send(Message::FileDone { id, ok: false }, write, codec).await;
+            return FileRecv::Failed(meta, e);
+        }
+
+        if recv_file.written < recv_file.meta.size {
+            if recv_file.written - recv_file.acked >= self.conf.ack_size {
+                recv_file.acked = recv_file.written;
+                Self::send(Message::FileAck { id, offset: recv_file.written }, write, codec).await;
+            }
+            return FileRecv::Pending;
+        }
+
+        let Some(recv_file) = files.remove(&id) else { return FileRecv::Pending; };
+        self.finish(recv_file)
+    }
+
+    /// open the .part file of the transfer id, the saved data will be resumed<br />
+    /// if the .part file of the transfer id does not exist,
+    /// the .part file of the same file left by an interrupted transfer is resumed, example: the sender or receiver restarted
+    async fn open(&self, meta: FileMeta) -> anyhow::Result<RecvFile> {
+        if meta.size > self.conf.max_size {
+            return Err(anyhow::anyhow!("file size {} exceeds the max size {}", meta.size, self.conf.max_size));
+        }
+        // only the file name is used, the sender can not write files outside the directory
+        let name = Path::new(meta.name.as_str()).file_name().filter(|name| name.to_string_lossy() == meta.name.as_str())
+            .ok_or_else(|| anyhow::anyhow!("invalid file name {}", meta.name))?;
+
+        check_exists(&self.conf, self.conf.dir.join(name).as_path()).await?;
+
+        let prefix = format!("{}.{}-{:08x}-", name.to_string_lossy(), meta.size, meta.checksum);
+        let part = self.conf.dir.join(format!("{prefix}{}.part", meta.id));
+        let claim = PartClaim::new(part.clone());
+        if !tokio::fs::try_exists(part.as_path()).await? {
+            self.resume_part(prefix.as_str(), part.as_path()).await?;
+        }
+
+        let mut file = File::from_std(cbsk_file::open_create_file(part.as_path())?);
+        let written = file.metadata().await?.len().min(meta.size);
+        file.set_len(written).await?;
+        file.seek(io::SeekFrom::Start(written)).await?;
+        Ok(RecvFile { meta, file, part, _claim: claim, written, acked: written })
+    }
+
+    /// rename the longest .part file of the same file that is not opened to the .part file of the transfer id
+    async fn resume_part(&self, prefix: &str, part: &Path) -> anyhow::Result<()> {
+        let mut dir = match tokio::fs::read_dir(self.conf.dir.as_path()).await {
+            Ok(dir) => { dir }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => { return Ok(()); }
+            Err(e) => { return Err(e.into()); }
+        };
+
+        let mut parts = Vec::new();
+        while let Some(entry) = dir.next_entry().await? {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let Some(id) = file_name.strip_prefix(prefix).and_then(|id| id.strip_suffix(".part")) else { continue; };
+            if id.parse::<u64>().is_err() { continue; }
+            parts.push((entry.metadata().await?.len(), entry.path()));
+        }
+
+        parts.sort_unstable_by_key(|(len, _)| std::cmp::Reverse(*len));
+        for (len, path) in parts {
+            let Some(_claim) = PartClaim::try_new(path.clone()) else { continue; };
+            tokio::fs::rename(path.as_path(), part).await?;
+            log::info!("resume file from {path:?}, saved length is {len}");
+            return Ok(());
+        }
+        Ok(())
+    }
+
+    /// write data to the .part file
+    async fn write(recv_file: &mut RecvFile, offset: u64, data: &[u8]) -> anyhow::Result<()> {
+        if offset != recv_file.written {
+            return Err(anyhow::anyhow!("file data offset {offset} is not the expected offset {}", recv_file.written));
+        }
+        let len = u64::try_from(data.len())?;
+        if offset.saturating_add(len) > recv_file.meta.size {
+            return Err(anyhow::anyhow!("file data exceeds the file size {}", recv_file.meta.size));
+        }
+        recv_file.file.write_all(data).await?;
+        recv_file.written += len;
+        Ok(())
+    }
+
+    /// all data is received, the file will be verified by [FileVerify::run]
+    fn finish(&self, recv_file: RecvFile) -> FileRecv {
+        FileRecv::Verify(FileVerify { conf: self.conf.clone(), recv_file })
+    }
+
+    /// send reply to the sender
+    async fn send(message: Message, write: &impl TcpWriteTrait, codec: &Codec) {
+        if let Err(e) = codec.send_message(message, write).await {
+            log::warn!("{} send file transfer reply error: {e:?}",write.get_log_head());
+        }
+    }
+}
+
+/// a received file waiting for integrity verification<br />
+/// the verification reads the whole file, so it should be run in a spawned task instead of the receive path
+pub(crate) struct FileVerify {
+    /// file transfer config
+    conf: Arc<FileConfig>,
+    /// the received file
+    recv_file: RecvFile,
+}
+
+/// custom method
+impl FileVerify {
+    /// verify the .part file, rename to the file name if success, and reply the result to the sender
+    pub(crate) async fn run(self, write: &impl TcpWriteTrait, codec: &Codec) -> FileRecv {
+        let Self { conf, mut recv_file } = self;
+        let id = recv_file.meta.id;
+        let result = Self::verify(&mut recv_file, conf.chunk_size).await;
+        drop(recv_file.file);
+        let result = match result {
+            Ok(()) => {
+                // the file may be created while receiving, the .part file is kept
+                let path = conf.dir.join(recv_file.meta.name.as_str());
+                match check_exists(&conf, path.as_path()).await {
+                    Ok(()) => { tokio::fs::rename(recv_file.part.as_path(), path.as_path()).await.map(|_| path).map_err(anyhow::Error::from) }
+                    Err(e) => { Err(e) }
+                }
+            }
+            Err(e) => {
+                // the data is broken, it can not be resumed
+                let _ = tokio::fs::remove_file(recv_file.part.as_path()).await;
+                Err(e)
+            }
+        };
+
+        FileReceiver::send(Message::FileDone { id, ok: result.is_ok() }, write, codec).await;
+        match result {
+            Ok(path) => { FileRecv::Finished(recv_file.meta, path) }
+            Err(e) => { FileRecv::Failed(recv_file.meta, e) }
+        }
+    }
+
+    /// verify the checksum of the .part file
+    async fn verify(recv_file: &mut RecvFile, buf_len: usize) -> anyhow::Result<()> {
+        recv_file.file.flush().await?;
+        let checksum = checksum(&mut recv_file.file, buf_len).await?;
+        if checksum != recv_file.meta.checksum {
+            return Err(anyhow::anyhow!("file checksum {checksum:08x} is not the expected checksum {:08x}", recv_file.meta.checksum));
+        }
+        Ok(())
+    }
+}
+
+/// check the file with the same name does not exist, unless overwrite is enabled
+async fn check_exists(conf: &FileConfig, path: &Path) -> anyhow::Result<()> {
+    if conf.overwrite || !tokio::fs::try_exists(path).await? { return Ok(()); }
+    Err(anyhow::anyhow!("file {} already exists", path.display()))
+}
+
+/// get crc32 of the whole file, the file will be seek to start
+async fn checksum(file: &mut File, buf_len: usize) -> io::Result<u32> {
+    file.seek(io::SeekFrom::Start(0)).await?;
+    let mut crc = Crc32::default();
+    let mut buf = vec![0; buf_len.max(1)];
+    loop {
+        let len = file.read(buf.as_mut_slice()).await?;
+        if len == 0 { return Ok(crc.finish()); }
+        crc.update(&buf[..len]);
+    }
+}
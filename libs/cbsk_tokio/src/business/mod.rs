@@ -1 +1,10 @@
 pub mod cbsk_write_trait;
+pub mod reliable;
+pub mod chunk;
+pub mod compress;
+pub mod resync;
+#[cfg(any(feature = "encrypt_aes_gcm", feature = "encrypt_chacha20"))]
+pub mod encrypt;
+#[cfg(feature = "file_transfer")]
+pub mod file;
+pub(crate) mod codec;
\ No newline at end of file
@@ -0,0 +1,129 @@
+use std::collections::BTreeMap;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::sync::{PoisonError, atomic};
+use std::sync::atomic::AtomicU64;
+use std::time::{Duration, Instant, SystemTime};
+use cbsk::hello::FEATURE_RELIABLE;
+use cbsk::message::Message;
+use cbsk_base::log;
+use cbsk_base::tokio::sync::Mutex;
+use cbsk_socket_tokio::tcp::common::tcp_write_trait::TcpWriteTrait;
+use crate::business::codec::Codec;
+
+/// reliable session, keep unacked data and received sequence number across reconnects<br />
+/// data sent by reliable is retransmitted after reconnect until the peer acks it,
+/// and duplicate data is suppressed on the receiving side
+pub struct ReliableSession {
+    /// session id
+    pub id: u64,
+    /// unacked data, also held while sending to keep data in order
+    unacked: Mutex<Unacked>,
+    /// the last received sequence number, 0 is nothing received
+    last_recv_seq: AtomicU64,
+    /// the last time the session was used
+    last_active: std::sync::Mutex<Instant>,
+}
+
+/// unacked data
+#[derive(Default)]
+struct Unacked {
+    /// the last sent sequence number
+    last_seq: u64,
+    /// unacked data may have been lost, new data is only queued until retransmitted
+    need_resend: bool,
+    /// unacked data, key is sequence number
+    data: BTreeMap<u64, Vec<u8>>,
+}
+
+/// custom method
+impl ReliableSession {
+    /// create reliable session
+    pub fn new(id: u64) -> Self {
+        Self { id, unacked: Mutex::default(), last_recv_seq: AtomicU64::default(), last_active: Instant::now().into() }
+    }
+
+    /// create reliable session with random id
+    pub fn new_random() -> Self {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default().as_nanos());
+        Self::new(hasher.finish())
+    }
+
+    /// get the number of unacked data
+    pub async fn get_unacked_len(&self) -> usize {
+        self.unacked.lock().await.data.len()
+    }
+
+    /// get the time since the session was last used
+    pub fn get_idle_time(&self) -> Duration {
+        self.last_active.lock().unwrap_or_else(PoisonError::into_inner).elapsed()
+    }
+
+    /// mark the session is used now
+    pub(crate) fn touch(&self) {
+        *self.last_active.lock().unwrap_or_else(PoisonError::into_inner) = Instant::now();
+    }
+
+    /// send reliable data, the data will be kept until the peer acks it<br />
+    /// if send fails or the connection is lost, the data will be retransmitted after reconnect<br />
+    /// return the sequence number of the data, or Err if the data can not be encoded or the peer does not support this mode
+    pub(crate) async fn send(&self, bytes: Vec<u8>, write: &impl TcpWriteTrait, codec: &Codec) -> io::Result<u64> {
+        codec.check_feature(FEATURE_RELIABLE, "acknowledged send mode")?;
+        let mut unacked = self.unacked.lock().await;
+        let seq = unacked.last_seq + 1;
+        // the data is encoded before it is kept, so that the data that can never be sent is not retransmitted
+        let frame = codec.frame_message(Message::Reliable { seq, data: bytes.clone() })?;
+        unacked.last_seq = seq;
+        unacked.data.insert(seq, bytes);
+        // keep data in order, the data will be sent by resend
+        if unacked.need_resend { return Ok(seq); }
+
+        if let Err(e) = write.try_send_bytes(frame.as_slice()).await {
+            log::warn!("{} send reliable data[{seq}] error, will be retransmitted after reconnect: {e:?}",write.get_log_head());
+            unacked.need_resend = true;
+        }
+        Ok(seq)
+    }
+
+    /// mark the connection is lost, the unacked data may have been lost and will be retransmitted by resend
+    pub(crate) async fn lost(&self) {
+        self.unacked.lock().await.need_resend = true;
+        self.touch();
+    }
+
+    /// retransmit all unacked data in order, stop if send fails
+    pub(crate) async fn resend(&self, write: &impl TcpWriteTrait, codec: &Codec) {
+        let mut unacked = self.unacked.lock().await;
+        if !unacked.data.is_empty() {
+            log::info!("{} retransmit {} unacked reliable data",write.get_log_head(),unacked.data.len());
+        }
+
+        for (seq, bytes) in unacked.data.iter() {
+            if let Err(e) = codec.send_message(Message::Reliable { seq: *seq, data: bytes.clone() }, write).await {
+                log::warn!("{} retransmit reliable data[{seq}] error: {e:?}",write.get_log_head());
+                return;
+            }
+        }
+        unacked.need_resend = false;
+    }
+
+    /// the peer acks all data up to the sequence number
+    pub(crate) async fn ack(&self, seq: u64) {
+        let mut unacked = self.unacked.lock().await;
+        unacked.data = unacked.data.split_off(&seq.saturating_add(1));
+    }
+
+    /// record received sequence number<br />
+    /// return false if the data has been received before
+    pub(crate) fn recv(&self, seq: u64) -> bool {
+        self.last_recv_seq.fetch_max(seq, atomic::Ordering::AcqRel) < seq
+    }
+
+    /// the peer has created a new session, the received sequence number starts over
+    #[cfg(feature = "client")]
+    pub(crate) fn reset_recv(&self) {
+        self.last_recv_seq.store(0, atomic::Ordering::Release);
+    }
+}
@@ -0,0 +1,48 @@
+/// how to resynchronise the stream when the data after the header frame is invalid
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResyncStrategy {
+    /// skip the first byte of the header frame and rescan, the following frames are kept
+    #[default]
+    SkipByte,
+    /// discard the whole received buffer
+    Discard,
+}
+
+/// stream resynchronisation config
+#[derive(Clone, Copy, Debug)]
+pub struct ResyncConfig {
+    /// how to resynchronise the stream
+    pub strategy: ResyncStrategy,
+    /// the max data length of a frame, a larger length means the header frame may be spurious, default is unlimited
+    pub max_len: usize,
+    /// the max byte count of the diagnostic hex dump, default is 0, the hex dump is disabled
+    pub dump_len: usize,
+}
+
+/// support default
+impl Default for ResyncConfig {
+    fn default() -> Self {
+        Self { strategy: ResyncStrategy::default(), max_len: usize::MAX, dump_len: 0 }
+    }
+}
+
+/// custom method
+impl ResyncConfig {
+    /// set how to resynchronise the stream
+    pub fn set_strategy(mut self, strategy: ResyncStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// set the max data length of a frame
+    pub fn set_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    /// set the max byte count of the diagnostic hex dump
+    pub fn set_dump_len(mut self, dump_len: usize) -> Self {
+        self.dump_len = dump_len;
+        self
+    }
+}
@@ -0,0 +1,99 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use cbsk::data::len_encoding::{LenDecode, LenEncoding};
+use crate::business::resync::config::{ResyncConfig, ResyncStrategy};
+
+pub mod config;
+
+/// why the stream is resynchronised
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResyncReason {
+    /// the bytes before the header frame are not cbsk frame
+    ErrorFrame,
+    /// the length after the header frame is invalid, carries the first byte
+    TooLong(u8),
+    /// the length after the header frame exceeds the max data length, carries the length
+    Oversized(usize),
+}
+
+/// stream resynchronisation event
+#[derive(Clone, Debug)]
+pub struct ResyncEvent {
+    /// why the stream is resynchronised
+    pub reason: ResyncReason,
+    /// the count of discarded bytes
+    pub discarded: usize,
+    /// hex dump of the bytes at the resync point, None if the hex dump is disabled
+    pub dump: Option<String>,
+}
+
+/// stream resynchronisation counters
+#[derive(Debug, Default)]
+pub struct ResyncStats {
+    /// the count of resync events
+    events: AtomicU64,
+    /// the count of discarded bytes
+    discarded_bytes: AtomicU64,
+}
+
+/// custom method
+impl ResyncStats {
+    /// get the count of resync events
+    pub fn get_events(&self) -> u64 {
+        self.events.load(Ordering::Relaxed)
+    }
+
+    /// get the count of discarded bytes
+    pub fn get_discarded_bytes(&self) -> u64 {
+        self.discarded_bytes.load(Ordering::Relaxed)
+    }
+
+    /// record a resync event
+    pub(crate) fn add(&self, event: &ResyncEvent) {
+        self.events.fetch_add(1, Ordering::Relaxed);
+        self.discarded_bytes.fetch_add(u64::try_from(event.discarded).unwrap_or(u64::MAX), Ordering::Relaxed);
+    }
+}
+
+/// custom method
+impl ResyncConfig {
+    /// the bytes before the header frame are discarded
+    pub(crate) fn error_frame(&self, error_frame: &[u8]) -> ResyncEvent {
+        // the bytes close to the next header frame are more helpful
+        let start = error_frame.len().saturating_sub(self.dump_len);
+        self.event(ResyncReason::ErrorFrame, error_frame.len(), &error_frame[start..])
+    }
+
+    /// check the length after the header frame<br />
+    /// if the length is invalid or exceeds the max data length, return the resync event and the next verify frame
+    pub(crate) fn check(&self, header: &[u8], data_frame: &[u8], len_encoding: LenEncoding) -> Option<(ResyncEvent, Vec<u8>)> {
+        let reason = match len_encoding.decode(data_frame) {
+            LenDecode::TooLong(byte) => { ResyncReason::TooLong(byte) }
+            LenDecode::Len { data_len, .. } if data_len > self.max_len => { ResyncReason::Oversized(data_len) }
+            _ => { return None; }
+        };
+
+        let frame = [header, data_frame].concat();
+        let (discarded, next_verify_frame) = match self.strategy {
+            ResyncStrategy::SkipByte => { (1, frame[1..].to_vec()) }
+            ResyncStrategy::Discard => { (frame.len(), Vec::new()) }
+        };
+        Some((self.event(reason, discarded, frame.as_slice()), next_verify_frame))
+    }
+
+    /// create resync event, the hex dump contains up to dump_len bytes
+    fn event(&self, reason: ResyncReason, discarded: usize, bytes: &[u8]) -> ResyncEvent {
+        let dump = (self.dump_len > 0).then(|| hex_dump(&bytes[..bytes.len().min(self.dump_len)]));
+        ResyncEvent { reason, discarded, dump }
+    }
+}
+
+/// hex dump bytes, example: `63 62 73 6b 01 ff`
+pub fn hex_dump(bytes: &[u8]) -> String {
+    let mut dump = String::with_capacity(bytes.len() * 3);
+    for (i, b) in bytes.iter().enumerate() {
+        if i > 0 { dump.push(' '); }
+        let _ = write!(dump, "{b:02x}");
+    }
+    dump
+}
@@ -1,23 +1,71 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+use std::sync::atomic::{AtomicBool, Ordering};
 use cbsk::{business, data};
+use cbsk::data::decoded::Decoded;
+use cbsk::hello::{FEATURE_RELIABLE, Hello};
+use cbsk::message::Message;
+use cbsk_base::{log, tokio};
 use cbsk_base::async_trait::async_trait;
+use cbsk_base::tokio::sync::RwLock;
 use cbsk_socket_tokio::tcp::client::callback::TcpClientCallBack;
+use cbsk_socket_tokio::tcp::client::TcpClient;
+use cbsk_socket_tokio::tcp::common::tcp_write_trait::TcpWriteTrait;
+use crate::business::chunk::{ChunkReceiver, ChunkSender};
+use crate::business::codec::{Codec, DecodeError};
+#[cfg(feature = "file_transfer")]
+use crate::business::file::{FileMeta, FileReceiver, FileRecv, FileSender};
+use crate::business::reliable::ReliableSession;
+use crate::business::resync::{ResyncEvent, ResyncReason, ResyncStats};
+use crate::business::resync::config::ResyncConfig;
 use crate::client::callback::CbskClientCallBack;
+use crate::client::outbox::Outbox;
 
 /// support tcp client callback
 pub struct CbskClientBusiness<C: CbskClientCallBack> {
     /// the cbsk first frame<br />
     /// Used to determine if it is cbsk data
     pub header: Arc<Vec<u8>>,
+    /// cbsk frame codec
+    codec: Arc<Codec>,
+    /// the codec of current connection, replaced by the negotiated codec after the hello exchange
+    pub(crate) conn_codec: Arc<Mutex<Arc<Codec>>>,
     /// business callback
     pub cb: Arc<C>,
+    /// the hello of this side, see [CbskClientCallBack::negotiate]
+    hello: Option<Hello>,
+    /// the negotiated capabilities of current connection
+    pub(crate) capabilities: Arc<Mutex<Option<Hello>>>,
+    /// is the hello frame sent and the first frame not received yet
+    hello_pending: AtomicBool,
+    /// stream resynchronisation config
+    resync: ResyncConfig,
+    /// stream resynchronisation counters
+    pub(crate) resync_stats: Arc<ResyncStats>,
+    /// authentication frame, will be sent automatically after each connection is successful
+    pub auth: Arc<RwLock<Option<Vec<u8>>>>,
+    /// offline outbox, see [CbskClientCallBack::outbox]
+    pub outbox: Option<Arc<Outbox>>,
+    /// reliable session, see [CbskClientCallBack::reliable]
+    pub reliable: Option<Arc<ReliableSession>>,
+    /// large payload sender, see [CbskClientCallBack::chunk]
+    pub(crate) chunk_sender: Option<Arc<ChunkSender<()>>>,
+    /// large payload receiver, see [CbskClientCallBack::chunk]
+    chunk_receiver: Option<ChunkReceiver>,
+    /// file sender, see [CbskClientCallBack::file]
+    #[cfg(feature = "file_transfer")]
+    pub(crate) file_sender: Option<Arc<FileSender>>,
+    /// file receiver, see [CbskClientCallBack::file]
+    #[cfg(feature = "file_transfer")]
+    file_receiver: Option<FileReceiver>,
+    /// tcp client, used to send authentication frame and flush outbox
+    tcp_client: Weak<TcpClient>,
 }
 
 /// custom method
 impl<C: CbskClientCallBack> CbskClientBusiness<C> {
     /// new business
     pub fn new(cb: Arc<C>) -> Self {
-        Self { cb, header: data::default_header().into() }
+        Self::new_with_head(cb, data::default_header())
     }
 
     /// new business, custom header frame
@@ -26,7 +74,252 @@ impl<C: CbskClientCallBack> CbskClientBusiness<C> {
         if header.is_empty() {
             header = data::default_header()
         }
-        Self { cb, header: header.into() }
+        let outbox = cb.outbox().map(|conf| {
+            let dropped_cb = cb.clone();
+            Outbox::new(conf, Box::new(move |bytes| {
+                let cb = dropped_cb.clone();
+                Box::pin(async move { cb.outbox_dropped(bytes).await })
+            })).into()
+        });
+        let header = Arc::new(header);
+        let is_reliable = cb.reliable();
+        let reliable = is_reliable.then(|| ReliableSession::new_random().into());
+        let chunk = cb.chunk().map(Arc::new);
+        #[cfg(feature = "file_transfer")]
+        let file = cb.file().map(Arc::new);
+        #[cfg(feature = "file_transfer")]
+        let is_file = file.is_some();
+        #[cfg(not(feature = "file_transfer"))]
+        let is_file = false;
+        let codec = Codec::new(header.clone(), is_reliable || chunk.is_some() || is_file).set_len_encoding(cb.len_encoding()).set_compress(cb.compress());
+        #[cfg(any(feature = "encrypt_aes_gcm", feature = "encrypt_chacha20"))]
+        let codec = codec.set_encrypt(cb.encrypt());
+        let hello = cb.negotiate().then(|| codec.hello(is_reliable, chunk.is_some(), is_file));
+        let resync = cb.resync();
+        let codec = Arc::new(codec);
+        let chunk_receiver = chunk.clone().map(|conf| ChunkReceiver::new(conf, "client_"));
+        let chunk_sender = chunk.map(|conf| {
+            let progress_cb = cb.clone();
+            ChunkSender::new(conf, Box::new(move |progress, _| {
+                let cb = progress_cb.clone();
+                Box::pin(async move { cb.chunk_send_progress(progress).await })
+            })).into()
+        });
+        Self {
+            cb,
+            header,
+            conn_codec: Arc::new(Mutex::new(codec.clone())),
+            codec,
+            hello,
+            capabilities: Arc::default(),
+            hello_pending: AtomicBool::default(),
+            resync,
+            resync_stats: Arc::default(),
+            auth: Arc::default(),
+            outbox,
+            reliable,
+            chunk_sender,
+            chunk_receiver,
+            #[cfg(feature = "file_transfer")]
+            file_sender: file.clone().map(|conf| FileSender::new(conf).into()),
+            #[cfg(feature = "file_transfer")]
+            file_receiver: file.map(FileReceiver::new),
+            tcp_client: Weak::new(),
+        }
+    }
+
+    /// set tcp client, used to send authentication frame and flush outbox
+    pub fn set_tcp_client(mut self, tcp_client: Weak<TcpClient>) -> Self {
+        self.tcp_client = tcp_client;
+        self
+    }
+
+    /// get the codec of current connection
+    fn get_codec(&self) -> Arc<Codec> {
+        self.conn_codec.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// send hello frame, the connection is ready after the hello reply is received, see [Self::recv_hello]
+    async fn send_hello(&self, hello: &Hello) {
+        let Some(tcp_client) = self.tcp_client.upgrade() else { return; };
+
+        // the hello reply is the first frame of the cbsk server
+        self.hello_pending.store(true, Ordering::Release);
+        if let Err(e) = self.codec.send_hello(hello, tcp_client.as_ref()).await {
+            log::error!("{} send hello frame error: {e:?}",tcp_client.get_log_head());
+        }
+    }
+
+    /// the cbsk server replies the hello frame, apply the negotiated codec and the connection is ready<br />
+    /// only the first frame after the hello frame is sent is checked, the cbsk server that does not negotiate uses plain cbsk frame<br />
+    /// return true if the frame is consumed, the frame is the hello frame or negotiation failed
+    async fn recv_hello(&self, frame: &[u8]) -> bool {
+        let Some(hello) = self.hello.as_ref() else { return false; };
+        if !self.hello_pending.swap(false, Ordering::AcqRel) { return false; }
+
+        // None if the cbsk server does not negotiate
+        let capabilities = Hello::decode(frame).map(|peer| hello.negotiate(&peer));
+        match self.codec.negotiate(capabilities.map_or(0, |capabilities| capabilities.features)) {
+            Ok(codec) => { *self.conn_codec.lock().unwrap_or_else(PoisonError::into_inner) = codec.into(); }
+            Err(e) => {
+                let Some(tcp_client) = self.tcp_client.upgrade() else { return true; };
+                log::error!("{} negotiate error: {e:?}",tcp_client.get_log_head());
+                tcp_client.re_conn().await;
+                return true;
+            }
+        }
+        if let Some(capabilities) = capabilities {
+            *self.capabilities.lock().unwrap_or_else(PoisonError::into_inner) = Some(capabilities);
+            self.cb.negotiated(capabilities).await;
+        }
+
+        self.conn_ready().await;
+        capabilities.is_some()
+    }
+
+    /// the connection is ready, send authentication frame, resume reliable session, flush outbox and call conn
+    async fn conn_ready(&self) {
+        self.send_auth().await;
+        self.resume_reliable().await;
+        self.flush_outbox();
+        self.cb.conn().await;
+    }
+
+    /// send authentication frame if set
+    async fn send_auth(&self) {
+        let Some(auth) = self.auth.read().await.clone() else { return; };
+        let Some(tcp_client) = self.tcp_client.upgrade() else { return; };
+
+        if let Err(e) = self.get_codec().send_data(auth, tcp_client.as_ref()).await {
+            log::error!("{} send authentication frame error: {e:?}",tcp_client.get_log_head());
+        }
+    }
+
+    /// send reliable session id and retransmit unacked data, skipped if the cbsk server does not support acknowledged send mode
+    async fn resume_reliable(&self) {
+        let Some(session) = self.reliable.as_ref() else { return; };
+        let Some(tcp_client) = self.tcp_client.upgrade() else { return; };
+        let codec = self.get_codec();
+        if let Err(e) = codec.check_feature(FEATURE_RELIABLE, "acknowledged send mode") {
+            log::warn!("{} resume reliable session error: {e:?}",tcp_client.get_log_head());
+            return;
+        }
+
+        if let Err(e) = codec.send_message(Message::Session(session.id), tcp_client.as_ref()).await {
+            log::error!("{} send reliable session error: {e:?}",tcp_client.get_log_head());
+            return;
+        }
+        session.resend(tcp_client.as_ref(), &codec).await;
+    }
+
+    /// recv a chunk of large payload
+    async fn recv_chunk(&self, id: u64, offset: u64, total: u64, data: Vec<u8>) {
+        let Some(receiver) = self.chunk_receiver.as_ref() else { return; };
+        let recv = match receiver.recv(id, offset, total, data).await {
+            Ok(Some(recv)) => { recv }
+            Ok(None) => { return; }
+            Err(e) => { return self.cb.chunk_failed(id, e).await; }
+        };
+
+        self.cb.chunk_recv_progress(recv.progress).await;
+        if let Some(data) = recv.data { self.cb.chunk_data(data).await; }
+        if let Some(payload) = recv.payload { self.cb.chunk_recv(id, payload).await; }
+    }
+
+    /// recv file transfer message
+    #[cfg(feature = "file_transfer")]
+    async fn recv_file(&self, message: Message) {
+        let Some(tcp_client) = self.tcp_client.upgrade() else { return; };
+        let (Some(sender), Some(receiver)) = (self.file_sender.as_ref(), self.file_receiver.as_ref()) else { return; };
+
+        let codec = self.get_codec();
+        let recv = match message {
+            Message::FileOffer { id, size, checksum, name } => {
+                receiver.offer(FileMeta { id, name, size, checksum }, tcp_client.as_ref(), &codec).await
+            }
+            Message::FileData { id, offset, data } => { receiver.data(id, offset, data, tcp_client.as_ref(), &codec).await }
+            Message::FileAck { id, offset } => { return sender.ack(id, offset); }
+            Message::FileDone { id, ok } => { return sender.done(id, ok); }
+            _ => { return; }
+        };
+        let FileRecv::Verify(verify) = recv else { return Self::file_result(self.cb.as_ref(), recv).await; };
+        // the whole file is read to verify, so that the verification does not block the receive path
+        let cb = self.cb.clone();
+        tokio::spawn(async move {
+            let recv = verify.run(tcp_client.as_ref(), codec.as_ref()).await;
+            Self::file_result(cb.as_ref(), recv).await
+        });
+    }
+
+    /// call the file callback by the result of receiving a file message
+    #[cfg(feature = "file_transfer")]
+    async fn file_result(cb: &C, recv: FileRecv) {
+        match recv {
+            FileRecv::Pending | FileRecv::Verify(_) => {}
+            FileRecv::Finished(meta, path) => { cb.file_recv(meta, path).await }
+            FileRecv::Failed(meta, e) => { cb.file_failed(meta, e).await }
+        }
+    }
+
+    /// recv file transfer message, file transfer is disabled
+    #[cfg(not(feature = "file_transfer"))]
+    async fn recv_file(&self, message: Message) {
+        let _ = message;
+        log::warn!("file transfer is disabled, file transfer message is discarded");
+    }
+
+    /// recv a data frame, handle reliable data, ack, session, chunk and file message
+    async fn recv_frame(&self, frame: Vec<u8>) {
+        if self.recv_hello(frame.as_slice()).await { return; }
+
+        let codec = self.get_codec();
+        let message = match codec.decode(frame) {
+            Ok(message) => { message }
+            #[cfg(any(feature = "encrypt_aes_gcm", feature = "encrypt_chacha20"))]
+            Err(DecodeError::Decrypt(frame)) => {
+                self.cb.decrypt_failed(frame).await;
+                return;
+            }
+            Err(DecodeError::Invalid(frame)) => {
+                self.cb.error_frame(frame).await;
+                return;
+            }
+        };
+
+        match message {
+            Message::Data(data) => { self.cb.recv(data).await }
+            Message::Reliable { seq, data } => {
+                let Some(session) = self.reliable.as_ref() else { return self.cb.recv(data).await; };
+                // duplicate data is still acked, the previous ack may have been lost
+                if session.recv(seq) { self.cb.recv(data).await; }
+                let Some(tcp_client) = self.tcp_client.upgrade() else { return; };
+                codec.send_ack(seq, tcp_client.as_ref()).await;
+            }
+            Message::Ack(seq) => {
+                if let Some(session) = self.reliable.as_ref() { session.ack(seq).await; }
+            }
+            Message::Session(id) => {
+                // the server has created a new session, example: the server has restarted
+                if let Some(session) = self.reliable.as_ref().filter(|session| session.id == id) { session.reset_recv(); }
+            }
+            Message::Chunk { id, offset, total, data } => { self.recv_chunk(id, offset, total, data).await }
+            message => { self.recv_file(message).await }
+        }
+    }
+
+    /// record and deliver the resync event
+    async fn recv_resync(&self, event: ResyncEvent) {
+        self.resync_stats.add(&event);
+        self.cb.resynced(event).await;
+    }
+
+    /// flush outbox in background, frames sent in conn will be queued after the outbox frames
+    fn flush_outbox(&self) {
+        let Some(outbox) = self.outbox.clone() else { return; };
+        let Some(tcp_client) = self.tcp_client.upgrade() else { return; };
+
+        let codec = self.get_codec();
+        tokio::spawn(async move { outbox.flush(tcp_client.as_ref(), codec.as_ref()).await });
     }
 }
 
@@ -34,10 +327,21 @@ impl<C: CbskClientCallBack> CbskClientBusiness<C> {
 #[async_trait]
 impl<C: CbskClientCallBack> TcpClientCallBack for CbskClientBusiness<C> {
     async fn conn(&self) {
-        self.cb.conn().await;
+        // if negotiation is enabled, the connection is ready after the hello exchange
+        match self.hello.as_ref() {
+            Some(hello) => { self.send_hello(hello).await }
+            None => { self.conn_ready().await }
+        }
     }
 
     async fn dis_conn(&self) {
+        *self.capabilities.lock().unwrap_or_else(PoisonError::into_inner) = None;
+        *self.conn_codec.lock().unwrap_or_else(PoisonError::into_inner) = self.codec.clone();
+        self.hello_pending.store(false, Ordering::Release);
+        if let Some(session) = self.reliable.as_ref() { session.lost().await; }
+        if let Some(receiver) = self.chunk_receiver.as_ref() { receiver.clear().await; }
+        #[cfg(feature = "file_transfer")]
+        if let Some(receiver) = self.file_receiver.as_ref() { receiver.clear().await; }
         self.cb.dis_conn().await;
     }
 
@@ -45,60 +349,27 @@ impl<C: CbskClientCallBack> TcpClientCallBack for CbskClientBusiness<C> {
         self.cb.re_conn(num).await
     }
 
-    async fn recv(&self, mut bytes: Vec<u8>) -> Vec<u8> {
-        // TODO can the following code be optimized? There are too many if and loop
-        loop {
-            let mut verify_data = business::verify(bytes, &self.header);
-            if !verify_data.error_frame.is_empty() {
-                self.cb.error_frame(verify_data.error_frame).await;
-            }
-
-            // if has too short frame, wait next tcp read
-            if !verify_data.too_short_frame.is_empty() {
-                return verify_data.too_short_frame;
-            }
-
-            // verify success, perform data analysis
-            if !verify_data.data_frame.is_empty() {
-                loop {
-                    let analysis_data = business::analysis(verify_data.data_frame, &self.header);
-
-                    if let Some(too_long) = analysis_data.too_long_byte {
-                        self.cb.too_long_frame(too_long).await;
-                    }
-
-                    // analysis success, call cb.recv
-                    if !analysis_data.data_frame.is_empty() {
-                        self.cb.recv(analysis_data.data_frame).await;
-                    }
-
-                    // if has next verify, change verify_data.next_verify_frame and break current loop
-                    if !analysis_data.next_verify_frame.is_empty() {
-                        verify_data.next_verify_frame = analysis_data.next_verify_frame;
-                        break;
-                    }
-
-                    // if has too short frame, wait next tcp read
-                    if !analysis_data.too_short_frame.is_empty() {
-                        return analysis_data.too_short_frame;
-                    }
-
-                    // analysis logic over, break current loop
-                    break;
+    async fn recv(&self, bytes: Vec<u8>) -> Vec<u8> {
+        // the length after the header frame is invalid or too long, the header frame may be spurious
+        let (decoded, too_short_frame) = business::decode_with_check(bytes, &self.header, self.codec.len_encoding, |data_frame| {
+            self.resync.check(&self.header, data_frame, self.codec.len_encoding)
+        });
+        for decoded in decoded {
+            match decoded {
+                Decoded::ErrorFrame(error_frame) => {
+                    self.recv_resync(self.resync.error_frame(error_frame.as_slice())).await;
+                    self.cb.error_frame(error_frame).await;
                 }
+                Decoded::TooLong(byte) => { self.cb.too_long_frame(byte).await; }
+                Decoded::Rejected(event) => {
+                    if let ResyncReason::TooLong(byte) = event.reason { self.cb.too_long_frame(byte).await; }
+                    self.recv_resync(event).await;
+                }
+                Decoded::Data(frame) => { self.recv_frame(frame).await; }
             }
-
-            // if has next verify, change bytes and go to next loop
-            if !verify_data.next_verify_frame.is_empty() {
-                bytes = verify_data.next_verify_frame;
-                continue;
-            }
-
-            // verify logic over, break loop
-            break;
         }
 
-        // default return empty data
-        Vec::new()
+        // if has too short frame, wait next tcp read
+        too_short_frame
     }
 }
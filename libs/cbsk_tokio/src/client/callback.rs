@@ -1,5 +1,21 @@
 use std::future::Future;
-use cbsk_base::log;
+#[cfg(feature = "file_transfer")]
+use std::path::PathBuf;
+use cbsk::data::len_encoding::LenEncoding;
+use cbsk::hello::Hello;
+use cbsk_base::{anyhow, log};
+use crate::business::chunk::{ChunkData, ChunkPayload, ChunkProgress};
+use crate::business::chunk::config::ChunkConfig;
+use crate::business::compress::config::CompressConfig;
+use crate::business::resync::ResyncEvent;
+use crate::business::resync::config::ResyncConfig;
+#[cfg(any(feature = "encrypt_aes_gcm", feature = "encrypt_chacha20"))]
+use crate::business::encrypt::config::EncryptConfig;
+#[cfg(feature = "file_transfer")]
+use crate::business::file::FileMeta;
+#[cfg(feature = "file_transfer")]
+use crate::business::file::config::FileConfig;
+use crate::client::outbox::config::OutboxConfig;
 
 /// cbsk connect and read data callback
 pub trait CbskClientCallBack: Send + Sync + 'static {
@@ -28,12 +44,161 @@ pub trait CbskClientCallBack: Send + Sync + 'static {
         async {}
     }
 
+    /// the stream resynchronisation config, see [ResyncConfig]<br />
+    /// only be called once when the cbsk client is created
+    fn resync(&self) -> ResyncConfig {
+        ResyncConfig::default()
+    }
+
+    /// the stream is resynchronised, the bytes before the next header frame or a spurious header frame are discarded<br />
+    /// if the hex dump is enabled, the event carries the hex dump of the bytes at the resync point
+    fn resynced(&self, event: ResyncEvent) -> impl Future<Output=()> + Send {
+        log::debug!("stream resync: {:?}, discarded {} bytes, dump: {:?}",event.reason,event.discarded,event.dump);
+        async {}
+    }
+
     /// data frame first byte is too long
     fn too_long_frame(&self, byte: u8) -> impl Future<Output=()> + Send {
         log::warn!("received cbsk frame, but first byte[{byte}] is too long");
         async {}
     }
 
+    /// the offline outbox config, frames will be queued while disconnected,
+    /// and flushed in order after the next successful connection<br />
+    /// only be called once when the cbsk client is created, default is None, outbox is disabled
+    fn outbox(&self) -> Option<OutboxConfig> {
+        None
+    }
+
+    /// the frame is dropped because the outbox is full,
+    /// or the frame restored from the outbox file exceeds the limit, called by the next send or connection<br />
+    /// bytes: the dropped frame data
+    fn outbox_dropped(&self, bytes: Vec<u8>) -> impl Future<Output=()> + Send {
+        log::warn!("outbox is full, frame of length {} is dropped",bytes.len());
+        async {}
+    }
+
+    /// enable acknowledged send mode, see [crate::client::CbskClient::try_send_reliable]<br />
+    /// reliable data carries a sequence number and is acked by the server automatically,
+    /// unacked data is retransmitted after reconnect and duplicate data is suppressed before recv<br />
+    /// the cbsk server must also enable this mode<br />
+    /// only be called once when the cbsk client is created, default is false
+    fn reliable(&self) -> bool {
+        false
+    }
+
+    /// the encoding of the data length after the header frame<br />
+    /// only be called once when the cbsk client is created, default is [LenEncoding::VarLe]<br />
+    /// the cbsk server must use the same length encoding
+    fn len_encoding(&self) -> LenEncoding {
+        LenEncoding::default()
+    }
+
+    /// enable protocol version negotiation, see [crate::client::CbskClient::get_capabilities]<br />
+    /// a hello frame with the protocol version and supported feature bits is sent as the first frame after each connection is successful<br />
+    /// the cbsk server must also enable negotiation, otherwise the hello frame will be treated as a business frame by the cbsk server<br />
+    /// only the first frame received after the hello frame is sent is checked for the hello reply of the cbsk server,
+    /// the authentication frame, reliable session, outbox and conn wait for the hello reply<br />
+    /// the frame layout follows the negotiated feature bits, compression and message features not supported by both sides are disabled,
+    /// the cbsk server that does not negotiate uses plain cbsk frame, if the cbsk server does not support the configured encryption, the connection will be shutdown<br />
+    /// only be called once when the cbsk client is created, default is false
+    fn negotiate(&self) -> bool {
+        false
+    }
+
+    /// the protocol version and feature bits are negotiated with the cbsk server
+    fn negotiated(&self, capabilities: Hello) -> impl Future<Output=()> + Send {
+        log::info!("negotiated protocol version {}, features {:#x}",capabilities.version,capabilities.features);
+        async {}
+    }
+
+    /// the frame compression config, frames larger than the threshold are compressed,
+    /// compressed frames are decompressed transparently before recv<br />
+    /// only be called once when the cbsk client is created, default is None, compression is disabled<br />
+    /// the cbsk server must also enable compression
+    fn compress(&self) -> Option<CompressConfig> {
+        None
+    }
+
+    /// the frame encryption config, each frame is encrypted and authenticated with a random nonce<br />
+    /// only be called once when the cbsk client is created, default is None, encryption is disabled<br />
+    /// the cbsk server must also enable encryption with the same algorithm and key
+    #[cfg(any(feature = "encrypt_aes_gcm", feature = "encrypt_chacha20"))]
+    fn encrypt(&self) -> Option<EncryptConfig> {
+        None
+    }
+
+    /// received cbsk frame can not be decrypted or authenticated, the frame will be discarded and not call recv<br />
+    /// only be called when encryption is enabled, see [Self::encrypt]
+    #[cfg(any(feature = "encrypt_aes_gcm", feature = "encrypt_chacha20"))]
+    fn decrypt_failed(&self, frame: Vec<u8>) -> impl Future<Output=()> + Send {
+        log::warn!("decrypt cbsk frame fail, will be discarded, frame len is {}",frame.len());
+        async {}
+    }
+
+    /// the large payload chunking config, see [crate::client::CbskClient::try_send_chunked]<br />
+    /// only be called once when the cbsk client is created, default is None, chunking is disabled<br />
+    /// the cbsk server must also enable chunking
+    fn chunk(&self) -> Option<ChunkConfig> {
+        None
+    }
+
+    /// a chunk of large payload is sent
+    fn chunk_send_progress(&self, progress: ChunkProgress) -> impl Future<Output=()> + Send {
+        log::trace!("large payload[{}] sent {}/{}",progress.id,progress.done,progress.total);
+        async {}
+    }
+
+    /// a chunk of large payload is received
+    fn chunk_recv_progress(&self, progress: ChunkProgress) -> impl Future<Output=()> + Send {
+        log::trace!("large payload[{}] received {}/{}",progress.id,progress.done,progress.total);
+        async {}
+    }
+
+    /// a chunk of large payload is received,
+    /// only be called if [crate::business::chunk::config::ChunkRecvMode::Stream]
+    fn chunk_data(&self, data: ChunkData) -> impl Future<Output=()> + Send {
+        log::warn!("large payload[{}] chunk of length {} is received, but chunk_data is not implemented",data.id,data.data.len());
+        async {}
+    }
+
+    /// all chunks of large payload are received
+    fn chunk_recv(&self, id: u64, payload: ChunkPayload) -> impl Future<Output=()> + Send {
+        let _ = payload;
+        log::warn!("large payload[{id}] is received, but chunk_recv is not implemented");
+        async {}
+    }
+
+    /// receive large payload failed, the transfer is dropped
+    fn chunk_failed(&self, id: u64, err: anyhow::Error) -> impl Future<Output=()> + Send {
+        log::warn!("receive large payload[{id}] failed: {err:?}");
+        async {}
+    }
+
+    /// the file transfer config, see [crate::client::CbskClient::send_file]<br />
+    /// only be called once when the cbsk client is created, default is None, file transfer is disabled<br />
+    /// the cbsk server must also enable file transfer
+    #[cfg(feature = "file_transfer")]
+    fn file(&self) -> Option<FileConfig> {
+        None
+    }
+
+    /// a file is received and verified<br />
+    /// path: the saved file path
+    #[cfg(feature = "file_transfer")]
+    fn file_recv(&self, meta: FileMeta, path: PathBuf) -> impl Future<Output=()> + Send {
+        log::info!("file[{}] of size {} is received to {path:?}",meta.name,meta.size);
+        async {}
+    }
+
+    /// receive file failed, the saved data will be resumed by the next transfer of the same file,
+    /// unless the integrity verification failed
+    #[cfg(feature = "file_transfer")]
+    fn file_failed(&self, meta: FileMeta, err: anyhow::Error) -> impl Future<Output=()> + Send {
+        log::warn!("receive file[{}] failed: {err:?}",meta.name);
+        async {}
+    }
+
     /// read tcp server data will call this method<br />
     /// bytes: cbsk server bytes<br />
     fn recv(&self, bytes: Vec<u8>) -> impl Future<Output=()> + Send;
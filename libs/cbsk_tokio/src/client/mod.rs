@@ -1,6 +1,11 @@
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, PoisonError};
 use std::time::Duration;
+use cbsk::hello::Hello;
+#[cfg(feature = "file_transfer")]
+use cbsk_base::log;
+use cbsk_base::tokio::io::AsyncRead;
+use cbsk_base::tokio::sync::RwLock;
 use cbsk_base::tokio::task::JoinHandle;
 use cbsk_socket_tokio::cbsk_socket::config::re_conn::SocketReConn;
 use cbsk_socket_tokio::cbsk_socket::tcp::client::config::TcpClientConfig;
@@ -8,10 +13,18 @@ use cbsk_socket_tokio::cbsk_socket::tcp::common::time_trait::TimeTrait;
 use cbsk_socket_tokio::tcp::client::TcpClient;
 use cbsk_socket_tokio::tcp::common::tcp_write_trait::TcpWriteTrait;
 use crate::business::cbsk_write_trait::CbskWriteTrait;
+use crate::business::chunk::ChunkSender;
+use crate::business::codec::Codec;
+#[cfg(feature = "file_transfer")]
+use crate::business::file::{FileMeta, FileSender};
+use crate::business::reliable::ReliableSession;
+use crate::business::resync::ResyncStats;
 use crate::client::business::CbskClientBusiness;
 use crate::client::callback::CbskClientCallBack;
+use crate::client::outbox::Outbox;
 
 pub mod callback;
+pub mod outbox;
 mod business;
 
 /// cbsk client
@@ -20,6 +33,23 @@ pub struct CbskClient {
     tcp_client: Arc<TcpClient>,
     /// cbsk header
     pub header: Arc<Vec<u8>>,
+    /// the negotiated capabilities of current connection, see [CbskClientCallBack::negotiate]
+    capabilities: Arc<Mutex<Option<Hello>>>,
+    /// stream resynchronisation counters
+    resync_stats: Arc<ResyncStats>,
+    /// authentication frame, will be sent automatically after each connection is successful
+    auth: Arc<RwLock<Option<Vec<u8>>>>,
+    /// offline outbox, see [CbskClientCallBack::outbox]
+    outbox: Option<Arc<Outbox>>,
+    /// reliable session, see [CbskClientCallBack::reliable]
+    reliable: Option<Arc<ReliableSession>>,
+    /// large payload sender, see [CbskClientCallBack::chunk]
+    chunk_sender: Option<Arc<ChunkSender<()>>>,
+    /// file sender, see [CbskClientCallBack::file]
+    #[cfg(feature = "file_transfer")]
+    file_sender: Option<Arc<FileSender>>,
+    /// the codec of current connection, see [CbskClientCallBack::negotiate]
+    codec: Arc<Mutex<Arc<Codec>>>,
 }
 
 /// custom method
@@ -51,8 +81,59 @@ impl CbskClient {
     /// use business create cbsk client
     fn new_with_business<C: CbskClientCallBack>(cb: CbskClientBusiness<C>, conf: Arc<TcpClientConfig>, buf_len: usize) -> Self {
         let header = cb.header.clone();
-        let tcp_client = TcpClient::new_with_buf_len(conf, buf_len, cb).into();
-        Self { tcp_client, header }
+        let capabilities = cb.capabilities.clone();
+        let resync_stats = cb.resync_stats.clone();
+        let auth = cb.auth.clone();
+        let outbox = cb.outbox.clone();
+        let reliable = cb.reliable.clone();
+        let chunk_sender = cb.chunk_sender.clone();
+        #[cfg(feature = "file_transfer")]
+        let file_sender = cb.file_sender.clone();
+        let codec = cb.conn_codec.clone();
+        let tcp_client = Arc::new_cyclic(|tcp_client| {
+            TcpClient::new_with_buf_len(conf, buf_len, cb.set_tcp_client(tcp_client.clone()))
+        });
+        Self {
+            tcp_client,
+            header,
+            capabilities,
+            resync_stats,
+            auth,
+            outbox,
+            reliable,
+            chunk_sender,
+            #[cfg(feature = "file_transfer")]
+            file_sender,
+            codec,
+        }
+    }
+
+    /// set authentication frame<br />
+    /// the frame will be sent automatically as the first frame after each connection is successful, before conn is called
+    pub async fn set_auth(&self, auth: Vec<u8>) {
+        *self.auth.write().await = Some(auth);
+    }
+
+    /// remove authentication frame, no longer send after connection is successful
+    pub async fn remove_auth(&self) {
+        *self.auth.write().await = None;
+    }
+
+    /// get the negotiated protocol version and feature bits of current connection<br />
+    /// return None if negotiation is disabled, not finished or the cbsk server does not negotiate<br />
+    /// the feature not in the capabilities is disabled for current connection, see [CbskClientCallBack::negotiate]
+    pub fn get_capabilities(&self) -> Option<Hello> {
+        *self.capabilities.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// get the codec of current connection
+    fn get_codec(&self) -> Arc<Codec> {
+        self.codec.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// get the stream resynchronisation counters
+    pub fn get_resync_stats(&self) -> Arc<ResyncStats> {
+        self.resync_stats.clone()
     }
 
     /// get default tcp config
@@ -97,6 +178,59 @@ impl CbskClient {
     pub fn get_config(&self) -> Arc<TcpClientConfig> {
         self.tcp_client.conf.clone()
     }
+
+    /// get offline outbox, return None if outbox is disabled
+    pub fn get_outbox(&self) -> Option<Arc<Outbox>> {
+        self.outbox.clone()
+    }
+
+    /// get reliable session, return None if acknowledged send mode is disabled
+    pub fn get_reliable(&self) -> Option<Arc<ReliableSession>> {
+        self.reliable.clone()
+    }
+
+    /// send bytes in acknowledged send mode, see [CbskClientCallBack::reliable]<br />
+    /// the data will be kept until the server acks it, and retransmitted after reconnect<br />
+    /// return the sequence number of the data, return Err if acknowledged send mode is disabled
+    pub async fn try_send_reliable(&self, bytes: Vec<u8>) -> std::io::Result<u64> {
+        let Some(reliable) = self.reliable.as_ref() else {
+            return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "acknowledged send mode is disabled"));
+        };
+        reliable.send(bytes, self.tcp_client.as_ref(), self.get_codec().as_ref()).await
+    }
+
+    /// send large payload chunk by chunk, see [CbskClientCallBack::chunk]<br />
+    /// the payload is read from reader, other frames can be sent between chunks<br />
+    /// total: the total length of payload, reader must provide at least this length<br />
+    /// return the transfer id, return Err if chunking is disabled
+    pub async fn try_send_chunked(&self, reader: &mut (impl AsyncRead + Unpin), total: u64) -> std::io::Result<u64> {
+        let Some(chunk_sender) = self.chunk_sender.as_ref() else {
+            return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "large payload chunking is disabled"));
+        };
+        chunk_sender.send(reader, total, self.tcp_client.as_ref(), self.get_codec().as_ref(), ()).await
+    }
+
+    /// send file, the result will be logged, see [Self::try_send_file]
+    #[cfg(feature = "file_transfer")]
+    pub async fn send_file(&self, path: impl AsRef<std::path::Path>) {
+        let path = path.as_ref();
+        match self.try_send_file(path).await {
+            Ok(meta) => { log::info!("{} send file[{path:?}] success, size is {}",self.get_log_head(),meta.size) }
+            Err(e) => { log::error!("{} send file[{path:?}] error: {e:?}",self.get_log_head()) }
+        }
+    }
+
+    /// send file with name, size and checksum, see [CbskClientCallBack::file]<br />
+    /// the file is saved by the server after integrity verification,
+    /// if the transfer is interrupted, send the same file again after reconnect to resume from the last confirmed offset<br />
+    /// return Err if file transfer is disabled, the transfer failed or the integrity verification failed
+    #[cfg(feature = "file_transfer")]
+    pub async fn try_send_file(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<FileMeta> {
+        let Some(file_sender) = self.file_sender.as_ref() else {
+            return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "file transfer is disabled"));
+        };
+        file_sender.send(path.as_ref(), self.tcp_client.as_ref(), self.get_codec().as_ref()).await
+    }
 }
 
 /// support write data to cbsk
@@ -105,9 +239,13 @@ impl CbskWriteTrait for CbskClient {
         self.tcp_client.get_log_head()
     }
 
+    /// if outbox is enabled, the frame will be queued while disconnected
     async fn try_send_bytes(&self, bytes: Vec<u8>) -> std::io::Result<()> {
-        let frame = cbsk::business::frame(bytes, self.header.as_ref());
-        self.tcp_client.try_send_bytes(frame.as_slice()).await
+        if let Some(outbox) = self.outbox.as_ref() {
+            return outbox.send(bytes, self.tcp_client.as_ref(), self.get_codec().as_ref()).await;
+        }
+
+        self.get_codec().send_data(bytes, self.tcp_client.as_ref()).await
     }
 }
 
@@ -0,0 +1,54 @@
+#[cfg(feature = "outbox_file")]
+use std::path::PathBuf;
+
+/// what to do when the outbox is full
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// drop the oldest frames to make room for the new frame
+    #[default]
+    DropOldest,
+    /// drop the new frame
+    DropNewest,
+}
+
+/// offline outbox config
+#[derive(Clone, Debug)]
+pub struct OutboxConfig {
+    /// max number of queued frames
+    pub max_count: usize,
+    /// max bytes of queued frames
+    pub max_bytes: usize,
+    /// what to do when the outbox is full
+    pub overflow: OverflowPolicy,
+    /// if set, queued frames will be saved to this file,
+    /// and will be restored when the cbsk client is created
+    #[cfg(feature = "outbox_file")]
+    pub file: Option<PathBuf>,
+}
+
+/// custom method
+impl OutboxConfig {
+    /// create outbox config, the oldest frames will be dropped if the outbox is full
+    pub fn new(max_count: usize, max_bytes: usize) -> Self {
+        Self {
+            max_count,
+            max_bytes,
+            overflow: OverflowPolicy::default(),
+            #[cfg(feature = "outbox_file")]
+            file: None,
+        }
+    }
+
+    /// set what to do when the outbox is full
+    pub fn set_overflow(mut self, overflow: OverflowPolicy) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// save queued frames to file
+    #[cfg(feature = "outbox_file")]
+    pub fn set_file(mut self, file: impl Into<PathBuf>) -> Self {
+        self.file = Some(file.into());
+        self
+    }
+}
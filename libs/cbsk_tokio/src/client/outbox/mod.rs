@@ -0,0 +1,234 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::PoisonError;
+use cbsk_base::log;
+use cbsk_base::tokio::sync::Mutex;
+use cbsk_socket_tokio::tcp::client::TcpClient;
+use cbsk_socket_tokio::tcp::common::tcp_write_trait::TcpWriteTrait;
+use crate::business::codec::Codec;
+use crate::client::outbox::config::{OutboxConfig, OverflowPolicy};
+
+pub mod config;
+
+/// called when a frame is dropped because the outbox is full
+pub(crate) type DroppedFn = Box<dyn Fn(Vec<u8>) -> Pin<Box<dyn Future<Output=()> + Send>> + Send + Sync>;
+
+/// offline outbox<br />
+/// frames are queued while disconnected, and flushed in order after the next successful connection
+pub struct Outbox {
+    /// outbox config
+    pub conf: OutboxConfig,
+    /// queued frames, also held while sending to keep frames in order
+    queue: Mutex<OutboxQueue>,
+    /// frame dropped callback
+    dropped: DroppedFn,
+    /// the restored frames dropped by the limit, passed to the dropped callback by the next send or flush
+    restore_dropped: std::sync::Mutex<Vec<Vec<u8>>>,
+}
+
+/// queued frames
+#[derive(Default)]
+struct OutboxQueue {
+    /// queued frames data, not including cbsk header
+    frames: VecDeque<Vec<u8>>,
+    /// the bytes of queued frames
+    bytes: usize,
+}
+
+/// custom method
+impl Outbox {
+    /// create outbox, if the outbox file is set, queued frames will be restored from file
+    pub(crate) fn new(conf: OutboxConfig, dropped: DroppedFn) -> Self {
+        let mut queue = OutboxQueue::default();
+        #[cfg(feature = "outbox_file")]
+        queue.restore(&conf);
+        // the limit may have been changed since the file was saved
+        let mut overflow = Vec::new();
+        while queue.frames.len() > conf.max_count || queue.bytes > conf.max_bytes {
+            overflow.extend(queue.pop_front());
+        }
+        if !overflow.is_empty() {
+            log::warn!("outbox restored more frames than the limit, {} frames are dropped",overflow.len());
+            #[cfg(feature = "outbox_file")]
+            queue.save(&conf);
+        }
+
+        Self { conf, queue: Mutex::new(queue), dropped, restore_dropped: overflow.into() }
+    }
+
+    /// pass the restored frames dropped by the limit to the dropped callback
+    async fn call_restore_dropped(&self) {
+        let overflow = std::mem::take(&mut *self.restore_dropped.lock().unwrap_or_else(PoisonError::into_inner));
+        for bytes in overflow {
+            (self.dropped)(bytes).await;
+        }
+    }
+
+    /// get the number of queued frames
+    pub async fn len(&self) -> usize {
+        self.queue.lock().await.frames.len()
+    }
+
+    /// get is the outbox empty
+    pub async fn is_empty(&self) -> bool {
+        self.queue.lock().await.frames.is_empty()
+    }
+
+    /// get the bytes of queued frames
+    pub async fn get_bytes(&self) -> usize {
+        self.queue.lock().await.bytes
+    }
+
+    /// send frame, if disconnected or there are frames queued before, the frame will be queued<br />
+    /// return Ok if the frame is sent or queued, return Err if the frame can not be encoded
+    pub(crate) async fn send(&self, bytes: Vec<u8>, tcp_client: &TcpClient, codec: &Codec) -> io::Result<()> {
+        self.call_restore_dropped().await;
+        // the frame is encoded before it is queued, so that the frame that can never be sent does not block the queue
+        let frame = codec.frame_data(bytes.clone())?;
+        let mut queue = self.queue.lock().await;
+        if queue.frames.is_empty() && tcp_client.is_connected().await {
+            match tcp_client.try_send_bytes(frame.as_slice()).await {
+                Ok(()) => { return Ok(()); }
+                Err(e) => { log::warn!("{} send frame error, the frame will be queued: {e:?}",tcp_client.get_log_head()); }
+            }
+        }
+
+        let dropped = queue.push_back(bytes, &self.conf);
+        #[cfg(feature = "outbox_file")]
+        if dropped.is_empty() { queue.append(&self.conf); } else { queue.save(&self.conf); }
+        drop(queue);
+
+        for bytes in dropped {
+            (self.dropped)(bytes).await;
+        }
+        Ok(())
+    }
+
+    /// send queued frames in order, stop if send fails
+    pub(crate) async fn flush(&self, tcp_client: &TcpClient, codec: &Codec) {
+        self.call_restore_dropped().await;
+        let mut queue = self.queue.lock().await;
+        if queue.frames.is_empty() { return; }
+        log::info!("{} flush {} frames in outbox",tcp_client.get_log_head(),queue.frames.len());
+
+        while let Some(bytes) = queue.frames.front() {
+            let frame =
+                match codec.frame_data(bytes.clone()) {
+                    Ok(frame) => { frame }
+                    Err(e) => {
+                        // the frame can never be sent, such as the frame loaded from the outbox file of the old config
+                        log::error!("{} encode outbox frame error, the frame is dropped: {e:?}",tcp_client.get_log_head());
+                        queue.pop_front();
+                        continue;
+                    }
+                };
+            if let Err(e) = tcp_client.try_send_bytes(frame.as_slice()).await {
+                log::warn!("{} flush outbox error, the remaining frames will be sent after the next connection: {e:?}",tcp_client.get_log_head());
+                break;
+            }
+            queue.pop_front();
+        }
+
+        #[cfg(feature = "outbox_file")]
+        queue.save(&self.conf);
+    }
+}
+
+/// queue logic
+impl OutboxQueue {
+    /// pop the oldest frame
+    fn pop_front(&mut self) -> Option<Vec<u8>> {
+        let bytes = self.frames.pop_front()?;
+        self.bytes = self.bytes.saturating_sub(bytes.len());
+        Some(bytes)
+    }
+
+    /// queue frame, return the dropped frames
+    fn push_back(&mut self, bytes: Vec<u8>, conf: &OutboxConfig) -> Vec<Vec<u8>> {
+        // the frame can never be queued
+        if conf.max_count == 0 || bytes.len() > conf.max_bytes {
+            return vec![bytes];
+        }
+
+        let is_full = |queue: &Self| queue.frames.len() >= conf.max_count || queue.bytes + bytes.len() > conf.max_bytes;
+        if conf.overflow == OverflowPolicy::DropNewest && is_full(self) {
+            return vec![bytes];
+        }
+
+        let mut dropped = Vec::new();
+        while is_full(self) {
+            dropped.extend(self.pop_front());
+        }
+        self.bytes += bytes.len();
+        self.frames.push_back(bytes);
+        dropped
+    }
+
+    /// restore queued frames from the outbox file<br />
+    /// file format: each frame is 4 bytes little endian length and frame data
+    #[cfg(feature = "outbox_file")]
+    fn restore(&mut self, conf: &OutboxConfig) {
+        let Some(file) = conf.file.as_ref() else { return; };
+        if !file.exists() { return; }
+
+        let data = cbsk_file::read_to_vec(file);
+        let mut data = data.as_slice();
+        while let Some((len, remain)) = data.split_first_chunk::<4>() {
+            let len = usize::try_from(u32::from_le_bytes(*len)).unwrap_or(usize::MAX);
+            let Some(bytes) = remain.get(..len) else {
+                log::warn!("outbox file[{file:?}] is incomplete, the last frame is dropped");
+                break;
+            };
+            self.bytes += bytes.len();
+            self.frames.push_back(bytes.to_vec());
+            data = &remain[len..];
+        }
+    }
+
+    /// save all queued frames to the outbox file<br />
+    /// the frames are written to a temporary file and then renamed, so that the outbox file is never partially written
+    #[cfg(feature = "outbox_file")]
+    fn save(&self, conf: &OutboxConfig) {
+        use std::io::Write;
+
+        let Some(file) = conf.file.as_ref() else { return; };
+        let mut data = Vec::with_capacity(self.bytes + self.frames.len() * 4);
+        self.frames.iter().for_each(|bytes| Self::encode(bytes, &mut data));
+
+        let mut tmp = file.clone().into_os_string();
+        tmp.push(".tmp");
+        let tmp = std::path::PathBuf::from(tmp);
+        let result = cbsk_file::recreate_file(tmp.as_path())
+            .and_then(|mut tmp| { tmp.write_all(&data)?; tmp.sync_all() })
+            .and_then(|_| std::fs::rename(tmp.as_path(), file));
+        if let Err(e) = result {
+            log::error!("save outbox file[{file:?}] fail: {e:?}");
+        }
+    }
+
+    /// append the newest queued frame to the outbox file
+    #[cfg(feature = "outbox_file")]
+    fn append(&self, conf: &OutboxConfig) {
+        use std::io::Write;
+
+        let Some(file) = conf.file.as_ref() else { return; };
+        let Some(bytes) = self.frames.back() else { return; };
+        let mut data = Vec::with_capacity(bytes.len() + 4);
+        Self::encode(bytes, &mut data);
+
+        let result = cbsk_file::open_create_file(file).and_then(|mut file| file.write_all(&data));
+        if let Err(e) = result {
+            log::error!("append outbox file[{file:?}] fail: {e:?}");
+        }
+    }
+
+    /// encode frame to the outbox file format
+    #[cfg(feature = "outbox_file")]
+    fn encode(bytes: &[u8], data: &mut Vec<u8>) {
+        let len = u32::try_from(bytes.len()).unwrap_or(u32::MAX);
+        data.extend_from_slice(&len.to_le_bytes());
+        data.extend_from_slice(bytes);
+    }
+}
@@ -0,0 +1,77 @@
+use std::sync::{Mutex, PoisonError};
+use std::sync::atomic::{AtomicU8, Ordering};
+use cbsk_base::tokio::task::AbortHandle;
+
+/// waiting for the client authentication frame
+const PENDING: u8 = 0;
+/// client authentication success
+const SUCCESS: u8 = 1;
+/// client authentication failed or timeout
+const FAILED: u8 = 2;
+
+/// the identity of the authenticated client
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Identity {
+    /// identity id, example: device id or user id
+    pub id: String,
+}
+
+/// custom method
+impl Identity {
+    /// create identity
+    pub fn new(id: impl Into<String>) -> Self {
+        Self { id: id.into() }
+    }
+}
+
+/// client authentication state, saved in session attributes
+#[derive(Default)]
+pub(crate) struct AuthState {
+    /// authentication state, see [PENDING], [SUCCESS] and [FAILED]
+    state: AtomicU8,
+    /// the task waiting for the authentication frame, aborted if the client is authenticated or disconnected
+    time_out_task: Mutex<Option<AbortHandle>>,
+}
+
+/// custom method
+impl AuthState {
+    /// get is waiting for the client authentication frame
+    pub(crate) fn is_pending(&self) -> bool {
+        self.state.load(Ordering::Acquire) == PENDING
+    }
+
+    /// get is the client authentication success
+    pub(crate) fn is_success(&self) -> bool {
+        self.state.load(Ordering::Acquire) == SUCCESS
+    }
+
+    /// set the task waiting for the authentication frame
+    pub(crate) fn set_time_out_task(&self, task: AbortHandle) {
+        *self.time_out_task.lock().unwrap_or_else(PoisonError::into_inner) = Some(task);
+        // the client may be authenticated or disconnected before the task is set
+        if !self.is_pending() { self.abort_time_out_task(); }
+    }
+
+    /// abort the task waiting for the authentication frame
+    fn abort_time_out_task(&self) {
+        if let Some(task) = self.time_out_task.lock().unwrap_or_else(PoisonError::into_inner).take() { task.abort(); }
+    }
+
+    /// mark authentication success, return false if authentication is already finished
+    pub(crate) fn try_success(&self) -> bool {
+        let success = self.state.compare_exchange(PENDING, SUCCESS, Ordering::AcqRel, Ordering::Acquire).is_ok();
+        if success { self.abort_time_out_task(); }
+        success
+    }
+
+    /// mark authentication failed, return false if authentication is already finished
+    pub(crate) fn try_fail(&self) -> bool {
+        self.state.compare_exchange(PENDING, FAILED, Ordering::AcqRel, Ordering::Acquire).is_ok()
+    }
+
+    /// the client is disconnected, no longer wait for the authentication frame
+    pub(crate) fn dis_conn(&self) {
+        self.try_fail();
+        self.abort_time_out_task();
+    }
+}
@@ -1,25 +1,88 @@
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
 use cbsk::{business, data};
+use cbsk::data::decoded::Decoded;
+use cbsk::hello::Hello;
+use cbsk::message::Message;
+use cbsk_base::{anyhow, log, tokio};
 use cbsk_base::async_trait::async_trait;
+use cbsk_socket_tokio::cbsk_socket::config::rate_limit::{LimitPolicy, LimitScope};
+use cbsk_socket_tokio::cbsk_socket::limit::rate_limiter::{self, LimitCheck, RateLimiter};
+use cbsk_socket_tokio::cbsk_socket::session::attr_trait::AttrTrait;
+use cbsk_socket_tokio::tcp::common::tcp_write_trait::TcpWriteTrait;
 use cbsk_socket_tokio::tcp::server::callback::TcpServerCallBack;
 use cbsk_socket_tokio::tcp::server::client::TcpServerClient;
+use crate::business::chunk::{ChunkReceiver, ChunkSender};
+use crate::business::chunk::config::ChunkConfig;
+use crate::business::codec::{Codec, DecodeError};
+#[cfg(feature = "file_transfer")]
+use crate::business::file::{FileMeta, FileReceiver, FileRecv, FileSender};
+#[cfg(feature = "file_transfer")]
+use crate::business::file::config::FileConfig;
+use crate::business::reliable::ReliableSession;
+use crate::business::resync::{ResyncEvent, ResyncReason, ResyncStats};
+use crate::business::resync::config::ResyncConfig;
+use crate::server::auth::{AuthState, Identity};
 use crate::server::callback::CbskServerCallBack;
 use crate::server::client::CbskServerClient;
 
+/// the reliable session key, the identity is None if authentication is disabled
+type SessionKey = (Option<Arc<Identity>>, u64);
+
 /// support tcp server callback
 pub struct CbskServerBusines<C: CbskServerCallBack> {
     /// the cbsk first frame<br />
     /// Used to determine if it is cbsk data
     pub header: Arc<Vec<u8>>,
+    /// cbsk frame codec
+    codec: Arc<Codec>,
     /// business callback
     pub cb: Arc<C>,
+    /// the hello of this side, see [CbskServerCallBack::negotiate]
+    hello: Option<Hello>,
+    /// stream resynchronisation config
+    resync: ResyncConfig,
+    /// the rate limiter shared by all clients
+    server_limiter: Option<Arc<RateLimiter>>,
+    /// reliable sessions, key is the client identity and session id, kept after the client disconnects<br />
+    /// if authentication is enabled, the session can only be resumed by the client with the same identity
+    sessions: Mutex<HashMap<SessionKey, Arc<ReliableSession>>>,
+    /// how long to keep the reliable session of a disconnected client
+    session_time_out: Duration,
+    /// large payload chunking config
+    chunk: Option<Arc<ChunkConfig>>,
+    /// large payload sender, shared by all clients
+    chunk_sender: Option<Arc<ChunkSender<Arc<CbskServerClient>>>>,
+    /// file transfer config
+    #[cfg(feature = "file_transfer")]
+    file: Option<Arc<FileConfig>>,
+}
+
+/// the first frame of the client has not been received, saved in session attributes if negotiation is enabled
+struct HelloPending;
+
+/// conn has been called for the client, saved in session attributes, so that dis_conn is only called for connected clients
+struct Connected;
+
+/// the rate limiter of a single client, saved in session attributes
+struct ClientRateLimiter(RateLimiter);
+
+/// the result of checking a received frame
+enum FrameResult {
+    /// the frame can be received
+    Pass,
+    /// the frame should be discarded
+    Drop,
+    /// the client has been shutdown
+    DisConn,
 }
 
 /// custom method
 impl<C: CbskServerCallBack> CbskServerBusines<C> {
     /// new business
     pub fn new(cb: Arc<C>) -> Self {
-        Self { cb, header: data::default_header().into() }
+        Self::new_with_head(cb, data::default_header())
     }
 
     /// new business, custom header frame
@@ -28,77 +91,436 @@ impl<C: CbskServerCallBack> CbskServerBusines<C> {
         if header.is_empty() {
             header = data::default_header()
         }
-        Self { cb, header: header.into() }
+        let server_limiter = cb.server_rate_limit().map(|limit| RateLimiter::new(limit).into());
+        let header = Arc::new(header);
+        let chunk = cb.chunk().map(Arc::new);
+        #[cfg(feature = "file_transfer")]
+        let file = cb.file().map(Arc::new);
+        #[cfg(feature = "file_transfer")]
+        let is_file = file.is_some();
+        #[cfg(not(feature = "file_transfer"))]
+        let is_file = false;
+        let codec = Codec::new(header.clone(), cb.reliable() || chunk.is_some() || is_file).set_len_encoding(cb.len_encoding()).set_compress(cb.compress());
+        #[cfg(any(feature = "encrypt_aes_gcm", feature = "encrypt_chacha20"))]
+        let codec = codec.set_encrypt(cb.encrypt());
+        let hello = cb.negotiate().then(|| codec.hello(cb.reliable(), chunk.is_some(), is_file));
+        let resync = cb.resync();
+        let codec = codec.into();
+        let session_time_out = cb.reliable_session_time_out();
+        let chunk_sender = chunk.clone().map(|conf| {
+            let progress_cb = cb.clone();
+            ChunkSender::new(conf, Box::new(move |progress, client| {
+                let cb = progress_cb.clone();
+                Box::pin(async move { cb.chunk_send_progress(progress, client).await })
+            })).into()
+        });
+        Self {
+            cb,
+            header,
+            codec,
+            hello,
+            resync,
+            server_limiter,
+            sessions: Mutex::default(),
+            session_time_out,
+            chunk,
+            chunk_sender,
+            #[cfg(feature = "file_transfer")]
+            file,
+        }
     }
-}
 
-/// support tcp server callback
-#[async_trait]
-impl<C: CbskServerCallBack> TcpServerCallBack for CbskServerBusines<C> {
-    async fn conn(&self, client: Arc<TcpServerClient>) {
-        self.cb.conn(CbskServerClient::new(self.header.clone(), client).into()).await;
+    /// check the client and server rate limit for a frame, see [rate_limiter::check_all]<br />
+    /// if the policy is delay, wait here until the tokens are available
+    async fn check_rate_limit(&self, frame_len: usize, client: &Arc<CbskServerClient>) -> FrameResult {
+        let client_limiter = client.get_attr::<ClientRateLimiter>();
+        let limiters = [
+            (LimitScope::Client, client_limiter.as_ref().map(|limiter| &limiter.0)),
+            (LimitScope::Server, self.server_limiter.as_deref()),
+        ];
+
+        match rate_limiter::check_all(&limiters, frame_len) {
+            LimitCheck::Pass(delays) => {
+                let mut wait = Duration::ZERO;
+                for (scope, delay) in delays {
+                    self.cb.rate_limited(scope, LimitPolicy::Delay, frame_len, client.clone()).await;
+                    wait = wait.max(delay);
+                }
+                if !wait.is_zero() { tokio::time::sleep(wait).await; }
+                FrameResult::Pass
+            }
+            LimitCheck::Reject(scope, policy) => {
+                self.cb.rate_limited(scope, policy, frame_len, client.clone()).await;
+                if policy == LimitPolicy::DisConn {
+                    client.shutdown().await;
+                    return FrameResult::DisConn;
+                }
+                FrameResult::Drop
+            }
+        }
     }
 
-    async fn dis_conn(&self, client: Arc<TcpServerClient>) {
-        self.cb.dis_conn(CbskServerClient::new(self.header.clone(), client).into()).await;
+    /// the first frame of a negotiating client is the hello frame, reply the server hello frame and apply the negotiated codec<br />
+    /// the client that does not negotiate uses plain cbsk frame, its first frame is received as a business frame<br />
+    /// return Drop if the frame is the hello frame, return DisConn if negotiation failed
+    async fn recv_hello(&self, frame: &[u8], client: &Arc<CbskServerClient>) -> FrameResult {
+        let Some(hello) = self.hello.as_ref() else { return FrameResult::Pass; };
+        if client.remove_attr::<HelloPending>().is_none() { return FrameResult::Pass; }
+
+        // the client does not negotiate
+        let Some(peer) = Hello::decode(frame) else {
+            if !Self::negotiated(&self.codec, self.cb.as_ref(), self.is_auth_enabled(), None, client).await { return FrameResult::DisConn; }
+            return FrameResult::Pass;
+        };
+
+        let write = client.get_tcp_server_client();
+        if let Err(e) = self.codec.send_hello(hello, write).await {
+            log::warn!("{} send hello frame error: {e:?}",write.get_log_head());
+        }
+        if !Self::negotiated(&self.codec, self.cb.as_ref(), self.is_auth_enabled(), Some(hello.negotiate(&peer)), client).await {
+            return FrameResult::DisConn;
+        }
+        FrameResult::Drop
+    }
+
+    /// apply the negotiated codec to the client, the client that does not negotiate is negotiated with no feature bits, see [Codec::negotiate]<br />
+    /// if authentication is disabled, conn is called after negotiation<br />
+    /// return false if negotiation failed and the client has been shutdown
+    async fn negotiated(codec: &Codec, cb: &C, auth_enabled: bool, capabilities: Option<Hello>, client: &Arc<CbskServerClient>) -> bool {
+        match codec.negotiate(capabilities.map_or(0, |capabilities| capabilities.features)) {
+            Ok(codec) => { client.set_attr(codec); }
+            Err(e) => {
+                log::error!("{} negotiate error: {e:?}",client.get_tcp_server_client().get_log_head());
+                client.shutdown().await;
+                return false;
+            }
+        }
+        if let Some(capabilities) = capabilities {
+            client.set_attr(capabilities);
+            cb.negotiated(capabilities, client.clone()).await;
+        }
+
+        // if authentication is enabled, conn will be called after authentication is successful
+        if !auth_enabled { Self::conn_client(cb, client.clone()).await; }
+        true
+    }
+
+    /// wait for the hello frame of the client, if timeout, the client is treated as not negotiating
+    fn hello_time_out_spawn(&self, client: Arc<CbskServerClient>) {
+        let (codec, cb, auth_enabled, time_out) = (self.codec.clone(), self.cb.clone(), self.is_auth_enabled(), self.cb.hello_time_out());
+        tokio::spawn(async move {
+            tokio::time::sleep(time_out).await;
+            if !client.get_tcp_server_client().is_connected() { return; }
+            if client.remove_attr::<HelloPending>().is_none() { return; }
+            Self::negotiated(&codec, cb.as_ref(), auth_enabled, None, &client).await;
+        });
+    }
+
+    /// mark the client connected and call conn
+    async fn conn_client(cb: &C, client: Arc<CbskServerClient>) {
+        client.set_attr(Connected);
+        cb.conn(client).await
+    }
+
+    /// get is authentication enabled, see [CbskServerCallBack::auth_time_out]
+    fn is_auth_enabled(&self) -> bool {
+        self.cb.auth_time_out().is_some()
+    }
+
+    /// get is the client authenticated, always true if authentication is disabled<br />
+    /// if authentication is enabled, the client without authentication state is not authenticated
+    fn is_authenticated(&self, client: &CbskServerClient) -> bool {
+        !self.is_auth_enabled() || client.get_attr::<AuthState>().is_some_and(|auth| auth.is_success())
+    }
+
+    /// record and deliver the resync event
+    async fn recv_resync(&self, event: ResyncEvent, client: Arc<CbskServerClient>) {
+        if let Some(stats) = client.get_resync_stats() { stats.add(&event); }
+        self.cb.resynced(event, client).await;
+    }
+
+    /// wait for the client authentication frame, if timeout, the client will be shutdown<br />
+    /// the task is aborted if the client is authenticated or disconnected, see [AuthState::dis_conn]
+    fn auth_time_out_spawn(&self, auth: Arc<AuthState>, time_out: Duration, client: Arc<CbskServerClient>) {
+        let cb = self.cb.clone();
+        let task_auth = auth.clone();
+        let task = tokio::spawn(async move {
+            tokio::time::sleep(time_out).await;
+            // the client has disconnected, dis_conn will stop waiting
+            if !client.get_tcp_server_client().is_connected() { return; }
+            if !task_auth.try_fail() { return; }
+
+            cb.auth_failed(anyhow::anyhow!("wait authentication frame timeout after {time_out:?}"), client.clone()).await;
+            client.shutdown().await;
+        });
+        auth.set_time_out_task(task.abort_handle());
+    }
+
+    /// get or create the reliable session of the identity, remove the sessions of disconnected clients that have timed out<br />
+    /// return true if the session is created
+    fn get_session(&self, identity: Option<Arc<Identity>>, id: u64) -> (Arc<ReliableSession>, bool) {
+        let mut sessions = self.sessions.lock().unwrap_or_else(PoisonError::into_inner);
+        // the session is only held by sessions if no client is using it
+        sessions.retain(|_, session| Arc::strong_count(session) > 1 || session.get_idle_time() < self.session_time_out);
+
+        let key: SessionKey = (identity, id);
+        if let Some(session) = sessions.get(&key) {
+            session.touch();
+            return (session.clone(), false);
+        }
+        let session = Arc::new(ReliableSession::new(id));
+        sessions.insert(key, session.clone());
+        (session, true)
+    }
+
+    /// the client sends its session id after connected, resume the session and retransmit unacked data
+    async fn resume_session(&self, id: u64, client: &CbskServerClient) {
+        // the session can only be resumed by authenticated client
+        if !self.is_authenticated(client) { return; }
+
+        // the identity is None if authentication is disabled
+        let (session, is_new) = self.get_session(client.get_identity(), id);
+        client.get_attrs().set_arc(session.clone());
+
+        let (write, codec) = (client.get_tcp_server_client(), client.get_codec());
+        if is_new {
+            // notify the client that the received sequence number starts over
+            if let Err(e) = codec.send_message(Message::Session(id), write).await {
+                log::warn!("{} send reliable session error: {e:?}",write.get_log_head());
+            }
+        }
+        session.resend(write, &codec).await;
+    }
+
+    /// recv a chunk of large payload, the chunk from unauthenticated client will be discarded
+    async fn recv_chunk(&self, id: u64, offset: u64, total: u64, data: Vec<u8>, client: Arc<CbskServerClient>) {
+        if !self.is_authenticated(&client) { return; }
+        let Some(receiver) = client.get_attr::<ChunkReceiver>() else { return; };
+        let recv = match receiver.recv(id, offset, total, data).await {
+            Ok(Some(recv)) => { recv }
+            Ok(None) => { return; }
+            Err(e) => { return self.cb.chunk_failed(id, e, client).await; }
+        };
+
+        self.cb.chunk_recv_progress(recv.progress, client.clone()).await;
+        if let Some(data) = recv.data { self.cb.chunk_data(data, client.clone()).await; }
+        if let Some(payload) = recv.payload { self.cb.chunk_recv(id, payload, client).await; }
     }
 
-    async fn recv(&self, mut bytes: Vec<u8>, client: Arc<TcpServerClient>) -> Vec<u8> {
-        let cbsk_server_client = Arc::new(CbskServerClient::new(self.header.clone(), client));
+    /// recv file transfer message, the message from unauthenticated client will be discarded
+    #[cfg(feature = "file_transfer")]
+    async fn recv_file(&self, message: Message, client: Arc<CbskServerClient>) {
+        if !self.is_authenticated(&client) { return; }
+        let (Some(sender), Some(receiver)) = (client.get_attr::<FileSender>(), client.get_attr::<FileReceiver>()) else { return; };
 
-        // TODO can the following code be optimized? There are too many if and loop
-        loop {
-            let mut verify_data = business::verify(bytes, &self.header);
-            if !verify_data.error_frame.is_empty() {
-                self.cb.error_frame(verify_data.error_frame, cbsk_server_client.clone()).await;
+        let (write, codec) = (client.get_tcp_server_client(), client.get_codec());
+        let recv = match message {
+            Message::FileOffer { id, size, checksum, name } => {
+                receiver.offer(FileMeta { id, name, size, checksum }, write, &codec).await
             }
+            Message::FileData { id, offset, data } => { receiver.data(id, offset, data, write, &codec).await }
+            Message::FileAck { id, offset } => { return sender.ack(id, offset); }
+            Message::FileDone { id, ok } => { return sender.done(id, ok); }
+            _ => { return; }
+        };
+        let FileRecv::Verify(verify) = recv else { return Self::file_result(self.cb.as_ref(), recv, client).await; };
+        // the whole file is read to verify, so that the verification does not block the receive path
+        let cb = self.cb.clone();
+        tokio::spawn(async move {
+            let recv = verify.run(client.get_tcp_server_client(), client.get_codec().as_ref()).await;
+            Self::file_result(cb.as_ref(), recv, client).await
+        });
+    }
+
+    /// call the file callback by the result of receiving a file message
+    #[cfg(feature = "file_transfer")]
+    async fn file_result(cb: &C, recv: FileRecv, client: Arc<CbskServerClient>) {
+        match recv {
+            FileRecv::Pending | FileRecv::Verify(_) => {}
+            FileRecv::Finished(meta, path) => { cb.file_recv(meta, path, client).await }
+            FileRecv::Failed(meta, e) => { cb.file_failed(meta, e, client).await }
+        }
+    }
+
+    /// recv file transfer message, file transfer is disabled
+    #[cfg(not(feature = "file_transfer"))]
+    async fn recv_file(&self, message: Message, client: Arc<CbskServerClient>) {
+        let _ = message;
+        log::warn!("{} file transfer is disabled, file transfer message is discarded",client.get_tcp_server_client().get_log_head());
+    }
+
+    /// recv a data frame, handle reliable data, ack, session, chunk and file message<br />
+    /// return false: the client is not authenticated or has been shutdown, the remaining data should be discarded
+    async fn recv_frame(&self, frame: Vec<u8>, client: Arc<CbskServerClient>) -> bool {
+        match self.check_rate_limit(frame.len(), &client).await {
+            FrameResult::Pass => {}
+            FrameResult::Drop => { return true; }
+            FrameResult::DisConn => { return false; }
+        }
+        match self.recv_hello(frame.as_slice(), &client).await {
+            FrameResult::Pass => {}
+            FrameResult::Drop => { return true; }
+            FrameResult::DisConn => { return false; }
+        }
 
-            // if has too short frame, wait next tcp read
-            if !verify_data.too_short_frame.is_empty() {
-                return verify_data.too_short_frame;
+        let codec = client.get_codec();
+        let message = match codec.decode(frame) {
+            Ok(message) => { message }
+            #[cfg(any(feature = "encrypt_aes_gcm", feature = "encrypt_chacha20"))]
+            Err(DecodeError::Decrypt(frame)) => {
+                self.cb.decrypt_failed(frame, client).await;
+                return true;
             }
+            Err(DecodeError::Invalid(frame)) => {
+                self.cb.error_frame(frame, client).await;
+                return true;
+            }
+        };
 
-            // verify success, perform data analysis
-            if !verify_data.data_frame.is_empty() {
-                loop {
-                    let analysis_data = business::analysis(verify_data.data_frame, &self.header);
+        match message {
+            Message::Data(data) => { self.recv_data(data, client).await }
+            Message::Reliable { seq, data } => {
+                // duplicate data is still acked, the previous ack may have been lost
+                let is_new = client.get_reliable().is_none_or(|session| session.recv(seq));
+                if is_new && !self.recv_data(data, client.clone()).await { return false; }
+                codec.send_ack(seq, client.get_tcp_server_client()).await;
+                true
+            }
+            Message::Ack(seq) => {
+                if let Some(session) = client.get_reliable() { session.ack(seq).await; }
+                true
+            }
+            Message::Session(id) => {
+                self.resume_session(id, &client).await;
+                true
+            }
+            Message::Chunk { id, offset, total, data } => {
+                self.recv_chunk(id, offset, total, data, client).await;
+                true
+            }
+            message => {
+                self.recv_file(message, client).await;
+                true
+            }
+        }
+    }
 
-                    if let Some(too_long) = analysis_data.too_long_byte {
-                        self.cb.too_long_frame(too_long, cbsk_server_client.clone()).await;
-                    }
+    /// recv business data, authenticate the client if authentication is pending<br />
+    /// return false: the client is not authenticated or has been shutdown, the remaining data should be discarded
+    async fn recv_data(&self, frame: Vec<u8>, client: Arc<CbskServerClient>) -> bool {
+        // authentication is disabled
+        if !self.is_auth_enabled() {
+            self.cb.recv(frame, client).await;
+            return true;
+        }
 
-                    // analysis success, call cb.recv
-                    if !analysis_data.data_frame.is_empty() {
-                        self.cb.recv(analysis_data.data_frame, cbsk_server_client.clone()).await;
-                    }
+        // the authentication state is set up before the read starts, the client without it is never authenticated
+        let Some(auth) = client.get_attr::<AuthState>() else { return false; };
 
-                    // if has next verify, change verify_data.next_verify_frame and break current loop
-                    if !analysis_data.next_verify_frame.is_empty() {
-                        verify_data.next_verify_frame = analysis_data.next_verify_frame;
-                        break;
-                    }
+        if auth.is_success() {
+            self.cb.recv(frame, client).await;
+            return true;
+        }
 
-                    // if has too short frame, wait next tcp read
-                    if !analysis_data.too_short_frame.is_empty() {
-                        return analysis_data.too_short_frame;
-                    }
+        // authentication failed or timeout
+        if !auth.is_pending() { return false; }
 
-                    // analysis logic over, break current loop
-                    break;
+        // the first frame, authenticate the client
+        match self.cb.authenticate(frame, client.clone()).await {
+            Ok(identity) => {
+                // authentication may timeout while waiting authenticate
+                if !auth.try_success() { return false; }
+                client.set_attr(identity);
+                Self::conn_client(self.cb.as_ref(), client).await;
+                true
+            }
+            Err(e) => {
+                if auth.try_fail() {
+                    self.cb.auth_failed(e, client.clone()).await;
+                    client.shutdown().await;
                 }
+                false
             }
+        }
+    }
+}
 
-            // if has next verify, change bytes and go to next loop
-            if !verify_data.next_verify_frame.is_empty() {
-                bytes = verify_data.next_verify_frame;
-                continue;
-            }
+/// support tcp server callback
+#[async_trait]
+impl<C: CbskServerCallBack> TcpServerCallBack for CbskServerBusines<C> {
+    async fn setup(&self, client: Arc<TcpServerClient>) {
+        let cbsk_server_client = Arc::new(CbskServerClient::new(self.codec.clone(), self.chunk_sender.clone(), client));
+        cbsk_server_client.set_attr(ResyncStats::default());
+        if self.hello.is_some() {
+            cbsk_server_client.set_attr(HelloPending);
+            self.hello_time_out_spawn(cbsk_server_client.clone());
+        }
+        if let Some(limit) = self.cb.client_rate_limit() {
+            cbsk_server_client.set_attr(ClientRateLimiter(RateLimiter::new(limit)));
+        }
+        if let Some(conf) = self.chunk.clone() {
+            cbsk_server_client.set_attr(ChunkReceiver::new(conf, format!("{}_", cbsk_server_client.get_conn_id())));
+        }
+        #[cfg(feature = "file_transfer")]
+        if let Some(conf) = self.file.clone() {
+            cbsk_server_client.set_attr(FileSender::new(conf.clone()));
+            cbsk_server_client.set_attr(FileReceiver::new(conf));
+        }
+
+        if let Some(time_out) = self.cb.auth_time_out() {
+            let auth = Arc::new(AuthState::default());
+            cbsk_server_client.get_attrs().set_arc(auth.clone());
+            self.auth_time_out_spawn(auth, time_out, cbsk_server_client);
+        }
+    }
 
-            // verify logic over, break loop
-            break;
+    async fn conn(&self, client: Arc<TcpServerClient>) {
+        // if authentication is enabled, conn will be called after authentication is successful
+        // if negotiation is enabled, conn will be called after the hello exchange, see [CbskServerBusines::negotiated]
+        if self.is_auth_enabled() || self.hello.is_some() { return; }
+        let cbsk_server_client = Arc::new(CbskServerClient::new(self.codec.clone(), self.chunk_sender.clone(), client));
+        Self::conn_client(self.cb.as_ref(), cbsk_server_client).await
+    }
+
+    async fn dis_conn(&self, client: Arc<TcpServerClient>) {
+        let cbsk_server_client = Arc::new(CbskServerClient::new(self.codec.clone(), self.chunk_sender.clone(), client));
+        if let Some(session) = cbsk_server_client.get_reliable() { session.lost().await; }
+        if let Some(receiver) = cbsk_server_client.get_attr::<ChunkReceiver>() { receiver.clear().await; }
+        if let Some(auth) = cbsk_server_client.get_attr::<AuthState>() { auth.dis_conn(); }
+        // the client that is not authenticated or has not finished negotiation has not called conn, so dis_conn is also not called
+        if cbsk_server_client.get_attr::<Connected>().is_some() {
+            self.cb.dis_conn(cbsk_server_client).await;
+        }
+    }
+
+    async fn recv(&self, bytes: Vec<u8>, client: Arc<TcpServerClient>) -> Vec<u8> {
+        let cbsk_server_client = Arc::new(CbskServerClient::new(self.codec.clone(), self.chunk_sender.clone(), client));
+
+        // the length after the header frame is invalid or too long, the header frame may be spurious
+        let (decoded, too_short_frame) = business::decode_with_check(bytes, &self.header, self.codec.len_encoding, |data_frame| {
+            self.resync.check(&self.header, data_frame, self.codec.len_encoding)
+        });
+        for decoded in decoded {
+            match decoded {
+                Decoded::ErrorFrame(error_frame) => {
+                    self.recv_resync(self.resync.error_frame(error_frame.as_slice()), cbsk_server_client.clone()).await;
+                    self.cb.error_frame(error_frame, cbsk_server_client.clone()).await;
+                }
+                Decoded::TooLong(byte) => { self.cb.too_long_frame(byte, cbsk_server_client.clone()).await; }
+                Decoded::Rejected(event) => {
+                    if let ResyncReason::TooLong(byte) = event.reason { self.cb.too_long_frame(byte, cbsk_server_client.clone()).await; }
+                    self.recv_resync(event, cbsk_server_client.clone()).await;
+                }
+                Decoded::Data(frame) => {
+                    if !self.recv_frame(frame, cbsk_server_client.clone()).await {
+                        // the client is not authenticated, discard the remaining data
+                        return Vec::new();
+                    }
+                }
+            }
         }
 
-        // default return empty data
-        Vec::new()
+        // if has too short frame, wait next tcp read
+        too_short_frame
     }
 }
@@ -1,18 +1,217 @@
 use std::future::Future;
+#[cfg(feature = "file_transfer")]
+use std::path::PathBuf;
 use std::sync::Arc;
-use cbsk_base::log;
+use std::time::Duration;
+use cbsk::data::len_encoding::LenEncoding;
+use cbsk::hello::Hello;
+use cbsk_base::{anyhow, log};
+use cbsk_socket_tokio::cbsk_socket::config::rate_limit::{LimitPolicy, LimitScope, RateLimit};
 use crate::business::cbsk_write_trait::CbskWriteTrait;
+use crate::business::chunk::{ChunkData, ChunkPayload, ChunkProgress};
+use crate::business::chunk::config::ChunkConfig;
+use crate::business::compress::config::CompressConfig;
+use crate::business::resync::ResyncEvent;
+use crate::business::resync::config::ResyncConfig;
+#[cfg(any(feature = "encrypt_aes_gcm", feature = "encrypt_chacha20"))]
+use crate::business::encrypt::config::EncryptConfig;
+#[cfg(feature = "file_transfer")]
+use crate::business::file::FileMeta;
+#[cfg(feature = "file_transfer")]
+use crate::business::file::config::FileConfig;
+use crate::server::auth::Identity;
 use crate::server::client::CbskServerClient;
 
 /// cbsk connect and read data callback
 pub trait CbskServerCallBack: Send + Sync + 'static {
-    /// a new tcp client come in
+    /// the time to wait for the client authentication frame<br />
+    /// if return Some, the first frame of each client will be passed to [Self::authenticate],
+    /// conn and recv will not be called until authentication is successful<br />
+    /// default is None, authentication is disabled
+    fn auth_time_out(&self) -> Option<Duration> {
+        None
+    }
+
+    /// authenticate the client by the first frame, only called if [Self::auth_time_out] return Some<br />
+    /// return Identity: authentication success, can be obtained by [CbskServerClient::get_identity]<br />
+    /// return Err: authentication failed, the client will be shutdown and [Self::auth_failed] will be called
+    fn authenticate(&self, first_frame: Vec<u8>, client: Arc<CbskServerClient>) -> impl Future<Output=anyhow::Result<Identity>> + Send {
+        let _ = (first_frame, client);
+        async { Err(anyhow::anyhow!("authenticate is not implemented")) }
+    }
+
+    /// the tcp client authentication failed or timeout, the client will be shutdown
+    fn auth_failed(&self, err: anyhow::Error, client: Arc<CbskServerClient>) -> impl Future<Output=()> + Send {
+        log::warn!("{} tcp client authentication failed: {err:?}",client.get_log_head());
+        async {}
+    }
+
+    /// the rate limit of each client, checked for each received frame<br />
+    /// default is None, unlimited
+    fn client_rate_limit(&self) -> Option<RateLimit> {
+        None
+    }
+
+    /// the rate limit shared by all clients, checked for each received frame<br />
+    /// only be called once when the cbsk server is created, default is None, unlimited
+    fn server_rate_limit(&self) -> Option<RateLimit> {
+        None
+    }
+
+    /// the tcp client exceeded the rate limit<br />
+    /// scope: the client limiter or the server limiter is exceeded<br />
+    /// policy: the policy that will be applied, the frame will be delayed, dropped, or the client will be shutdown<br />
+    /// frame_len: the length of the limited frame
+    fn rate_limited(&self, scope: LimitScope, policy: LimitPolicy, frame_len: usize, client: Arc<CbskServerClient>) -> impl Future<Output=()> + Send {
+        log::warn!("{} tcp client exceeded the {scope:?} rate limit, frame length is {frame_len}, policy is {policy:?}",client.get_log_head());
+        async {}
+    }
+
+    /// enable acknowledged send mode, see [CbskServerClient::try_send_reliable]<br />
+    /// reliable data carries a sequence number and is acked by the client automatically,
+    /// unacked data is retransmitted after the client reconnects and duplicate data is suppressed before recv<br />
+    /// if authentication is enabled, the session can only be resumed by the client with the same identity<br />
+    /// the cbsk client must also enable this mode<br />
+    /// only be called once when the cbsk server is created, default is false
+    fn reliable(&self) -> bool {
+        false
+    }
+
+    /// how long to keep the reliable session of a disconnected client, waiting for the client to reconnect<br />
+    /// only be called once when the cbsk server is created, default is 10 minutes
+    fn reliable_session_time_out(&self) -> Duration {
+        Duration::from_secs(600)
+    }
+
+    /// the encoding of the data length after the header frame<br />
+    /// only be called once when the cbsk server is created, default is [LenEncoding::VarLe]<br />
+    /// the cbsk client must use the same length encoding
+    fn len_encoding(&self) -> LenEncoding {
+        LenEncoding::default()
+    }
+
+    /// enable protocol version negotiation, see [CbskServerClient::get_capabilities]<br />
+    /// if the first frame of the client is a hello frame, the server hello frame will be replied,
+    /// only the first frame of the client is checked for the hello frame<br />
+    /// the frame layout of each client follows the negotiated feature bits, compression and message features not supported by both sides are disabled,
+    /// the client that does not negotiate uses plain cbsk frame, the client that does not support the configured encryption will be shutdown<br />
+    /// conn is called after the hello exchange, so that the hello reply is the first frame sent to the client<br />
+    /// only be called once when the cbsk server is created, default is false
+    fn negotiate(&self) -> bool {
+        false
+    }
+
+    /// the time to wait for the hello frame of the client, only used if [Self::negotiate] return true<br />
+    /// the client that does not send any frame in time is treated as not negotiating and conn is called,
+    /// so that the server can send the first frame to the client that does not negotiate<br />
+    /// default is 3 seconds
+    fn hello_time_out(&self) -> Duration {
+        Duration::from_secs(3)
+    }
+
+    /// the protocol version and feature bits are negotiated with the client
+    fn negotiated(&self, capabilities: Hello, client: Arc<CbskServerClient>) -> impl Future<Output=()> + Send {
+        log::info!("{} negotiated protocol version {}, features {:#x}",client.get_log_head(),capabilities.version,capabilities.features);
+        async {}
+    }
+
+    /// the frame compression config, frames larger than the threshold are compressed,
+    /// compressed frames are decompressed transparently before recv<br />
+    /// only be called once when the cbsk server is created, default is None, compression is disabled<br />
+    /// the cbsk client must also enable compression
+    fn compress(&self) -> Option<CompressConfig> {
+        None
+    }
+
+    /// the frame encryption config, each frame is encrypted and authenticated with a random nonce<br />
+    /// only be called once when the cbsk server is created, default is None, encryption is disabled<br />
+    /// the cbsk client must also enable encryption with the same algorithm and key
+    #[cfg(any(feature = "encrypt_aes_gcm", feature = "encrypt_chacha20"))]
+    fn encrypt(&self) -> Option<EncryptConfig> {
+        None
+    }
+
+    /// received cbsk frame can not be decrypted or authenticated, the frame will be discarded and not call recv<br />
+    /// only be called when encryption is enabled, see [Self::encrypt]
+    #[cfg(any(feature = "encrypt_aes_gcm", feature = "encrypt_chacha20"))]
+    fn decrypt_failed(&self, frame: Vec<u8>, client: Arc<CbskServerClient>) -> impl Future<Output=()> + Send {
+        log::warn!("{} decrypt cbsk frame fail, will be discarded, frame len is {}",client.get_log_head(),frame.len());
+        async {}
+    }
+
+    /// the large payload chunking config, see [CbskServerClient::try_send_chunked]<br />
+    /// only be called once when the cbsk server is created, default is None, chunking is disabled<br />
+    /// the cbsk client must also enable chunking
+    fn chunk(&self) -> Option<ChunkConfig> {
+        None
+    }
+
+    /// a chunk of large payload is sent
+    fn chunk_send_progress(&self, progress: ChunkProgress, client: Arc<CbskServerClient>) -> impl Future<Output=()> + Send {
+        log::trace!("{} large payload[{}] sent {}/{}",client.get_log_head(),progress.id,progress.done,progress.total);
+        async {}
+    }
+
+    /// a chunk of large payload is received
+    fn chunk_recv_progress(&self, progress: ChunkProgress, client: Arc<CbskServerClient>) -> impl Future<Output=()> + Send {
+        log::trace!("{} large payload[{}] received {}/{}",client.get_log_head(),progress.id,progress.done,progress.total);
+        async {}
+    }
+
+    /// a chunk of large payload is received,
+    /// only be called if [crate::business::chunk::config::ChunkRecvMode::Stream]
+    fn chunk_data(&self, data: ChunkData, client: Arc<CbskServerClient>) -> impl Future<Output=()> + Send {
+        log::warn!("{} large payload[{}] chunk of length {} is received, but chunk_data is not implemented",client.get_log_head(),data.id,data.data.len());
+        async {}
+    }
+
+    /// all chunks of large payload are received
+    fn chunk_recv(&self, id: u64, payload: ChunkPayload, client: Arc<CbskServerClient>) -> impl Future<Output=()> + Send {
+        let _ = payload;
+        log::warn!("{} large payload[{id}] is received, but chunk_recv is not implemented",client.get_log_head());
+        async {}
+    }
+
+    /// receive large payload failed, the transfer is dropped
+    fn chunk_failed(&self, id: u64, err: anyhow::Error, client: Arc<CbskServerClient>) -> impl Future<Output=()> + Send {
+        log::warn!("{} receive large payload[{id}] failed: {err:?}",client.get_log_head());
+        async {}
+    }
+
+    /// the file transfer config, see [CbskServerClient::send_file]<br />
+    /// only be called once when the cbsk server is created, default is None, file transfer is disabled<br />
+    /// the cbsk client must also enable file transfer
+    #[cfg(feature = "file_transfer")]
+    fn file(&self) -> Option<FileConfig> {
+        None
+    }
+
+    /// a file is received and verified<br />
+    /// path: the saved file path
+    #[cfg(feature = "file_transfer")]
+    fn file_recv(&self, meta: FileMeta, path: PathBuf, client: Arc<CbskServerClient>) -> impl Future<Output=()> + Send {
+        log::info!("{} file[{}] of size {} is received to {path:?}",client.get_log_head(),meta.name,meta.size);
+        async {}
+    }
+
+    /// receive file failed, the saved data will be resumed by the next transfer of the same file,
+    /// unless the integrity verification failed
+    #[cfg(feature = "file_transfer")]
+    fn file_failed(&self, meta: FileMeta, err: anyhow::Error, client: Arc<CbskServerClient>) -> impl Future<Output=()> + Send {
+        log::warn!("{} receive file[{}] failed: {err:?}",client.get_log_head(),meta.name);
+        async {}
+    }
+
+    /// a new tcp client come in<br />
+    /// if authentication is enabled, will be called after authentication is successful,
+    /// if negotiation is enabled, will be called after the hello exchange
     fn conn(&self, client: Arc<CbskServerClient>) -> impl Future<Output=()> + Send {
         log::info!("{} tcp client connected",client.get_log_head());
         async {}
     }
 
-    /// the tcp client disconnected
+    /// the tcp client disconnected<br />
+    /// only be called for the clients that conn has been called
     fn dis_conn(&self, client: Arc<CbskServerClient>) -> impl Future<Output=()> + Send {
         log::info!("{} tcp client disconnect", client.get_log_head());
         async {}
@@ -24,6 +223,19 @@ pub trait CbskServerCallBack: Send + Sync + 'static {
         async {}
     }
 
+    /// the stream resynchronisation config, see [ResyncConfig]<br />
+    /// only be called once when the cbsk server is created
+    fn resync(&self) -> ResyncConfig {
+        ResyncConfig::default()
+    }
+
+    /// the stream is resynchronised, the bytes before the next header frame or a spurious header frame are discarded<br />
+    /// if the hex dump is enabled, the event carries the hex dump of the bytes at the resync point
+    fn resynced(&self, event: ResyncEvent, client: Arc<CbskServerClient>) -> impl Future<Output=()> + Send {
+        log::debug!("{} stream resync: {:?}, discarded {} bytes, dump: {:?}",client.get_log_head(),event.reason,event.discarded,event.dump);
+        async {}
+    }
+
     /// data frame first byte is too long
     fn too_long_frame(&self, byte: u8, client: Arc<CbskServerClient>) -> impl Future<Output=()> + Send {
         log::warn!("{} received cbsk frame, but first byte[{byte}] is too long",client.get_log_head());
@@ -1,16 +1,34 @@
 use std::net::SocketAddr;
 use std::sync::Arc;
-use cbsk::business;
+use cbsk::hello::Hello;
+#[cfg(feature = "file_transfer")]
+use cbsk_base::log;
+use cbsk_base::tokio::io::AsyncRead;
+use cbsk_socket_tokio::cbsk_socket::metrics::conn_metrics::ConnMetrics;
+use cbsk_socket_tokio::cbsk_socket::session::attr_trait::AttrTrait;
+use cbsk_socket_tokio::cbsk_socket::session::attrs::SessionAttrs;
 use cbsk_socket_tokio::cbsk_socket::tcp::common::time_trait::TimeTrait;
 use cbsk_socket_tokio::tcp::common::tcp_write_trait::TcpWriteTrait;
 use cbsk_socket_tokio::tcp::server::client::TcpServerClient;
 use crate::business::cbsk_write_trait::CbskWriteTrait;
+use crate::business::chunk::ChunkSender;
+use crate::business::codec::Codec;
+#[cfg(feature = "file_transfer")]
+use crate::business::file::{FileMeta, FileSender};
+use crate::business::reliable::ReliableSession;
+use crate::business::resync::ResyncStats;
+use crate::server::auth::{AuthState, Identity};
 
 /// cbsk server client
+#[derive(Clone)]
 pub struct CbskServerClient {
     /// the cbsk first frame<br />
     /// Used to determine if it is cbsk data
     pub header: Arc<Vec<u8>>,
+    /// cbsk frame codec, replaced by the negotiated codec saved in session attributes, see [Self::get_codec]
+    codec: Arc<Codec>,
+    /// large payload sender, see [crate::server::callback::CbskServerCallBack::chunk]
+    chunk_sender: Option<Arc<ChunkSender<Arc<CbskServerClient>>>>,
     /// tcp server client
     tcp_server_client: Arc<TcpServerClient>,
 }
@@ -18,14 +36,107 @@ pub struct CbskServerClient {
 /// custom method
 impl CbskServerClient {
     /// create cbsk server client
-    pub(crate) fn new(header: Arc<Vec<u8>>, tcp_server_client: Arc<TcpServerClient>) -> Self {
-        Self { header, tcp_server_client }
+    pub(crate) fn new(codec: Arc<Codec>, chunk_sender: Option<Arc<ChunkSender<Arc<CbskServerClient>>>>, tcp_server_client: Arc<TcpServerClient>) -> Self {
+        Self { header: codec.header.clone(), codec, chunk_sender, tcp_server_client }
+    }
+
+    /// get tcp server client
+    pub(crate) fn get_tcp_server_client(&self) -> &TcpServerClient {
+        self.tcp_server_client.as_ref()
+    }
+
+    /// get the codec of this client, the negotiated codec if negotiation is finished
+    pub(crate) fn get_codec(&self) -> Arc<Codec> {
+        self.get_attr().unwrap_or_else(|| self.codec.clone())
     }
 
     /// get client addr
     pub fn get_addr(&self) -> SocketAddr {
         self.tcp_server_client.addr
     }
+
+    /// get client counters
+    pub fn get_metrics(&self) -> Arc<ConnMetrics> {
+        self.tcp_server_client.metrics.clone()
+    }
+
+    /// get the identity returned by authenticate<br />
+    /// return None if authentication is disabled or not yet successful
+    pub fn get_identity(&self) -> Option<Arc<Identity>> {
+        self.get_attr()
+    }
+
+    /// get is the client authenticated<br />
+    /// always true if authentication is disabled
+    pub fn is_authenticated(&self) -> bool {
+        self.get_attr::<AuthState>().is_none_or(|auth| auth.is_success())
+    }
+
+    /// get the negotiated protocol version and feature bits<br />
+    /// return None if negotiation is disabled, not finished or the client does not negotiate<br />
+    /// the feature not in the capabilities is disabled for this client, see [crate::server::callback::CbskServerCallBack::negotiate]
+    pub fn get_capabilities(&self) -> Option<Hello> {
+        self.get_attr::<Hello>().map(|hello| *hello)
+    }
+
+    /// get the stream resynchronisation counters of this client
+    pub fn get_resync_stats(&self) -> Option<Arc<ResyncStats>> {
+        self.get_attr()
+    }
+
+    /// get reliable session, the session is resumed after the client sends its session id<br />
+    /// return None if acknowledged send mode is disabled or the session is not yet resumed
+    pub fn get_reliable(&self) -> Option<Arc<ReliableSession>> {
+        self.get_attr()
+    }
+
+    /// send bytes in acknowledged send mode, see [crate::server::callback::CbskServerCallBack::reliable]<br />
+    /// the data will be kept until the client acks it, and retransmitted after the client reconnects<br />
+    /// return the sequence number of the data, return Err if the reliable session is not available
+    pub async fn try_send_reliable(&self, bytes: Vec<u8>) -> std::io::Result<u64> {
+        let Some(reliable) = self.get_reliable() else {
+            return Err(std::io::Error::new(std::io::ErrorKind::NotConnected, "reliable session is not available"));
+        };
+        reliable.send(bytes, self.tcp_server_client.as_ref(), self.get_codec().as_ref()).await
+    }
+
+    /// send large payload chunk by chunk, see [crate::server::callback::CbskServerCallBack::chunk]<br />
+    /// the payload is read from reader, other frames can be sent between chunks<br />
+    /// total: the total length of payload, reader must provide at least this length<br />
+    /// return the transfer id, return Err if chunking is disabled
+    pub async fn try_send_chunked(&self, reader: &mut (impl AsyncRead + Unpin), total: u64) -> std::io::Result<u64> {
+        let Some(chunk_sender) = self.chunk_sender.as_ref() else {
+            return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "large payload chunking is disabled"));
+        };
+        chunk_sender.send(reader, total, self.tcp_server_client.as_ref(), self.get_codec().as_ref(), Arc::new(self.clone())).await
+    }
+
+    /// send file, the result will be logged, see [Self::try_send_file]
+    #[cfg(feature = "file_transfer")]
+    pub async fn send_file(&self, path: impl AsRef<std::path::Path>) {
+        let path = path.as_ref();
+        match self.try_send_file(path).await {
+            Ok(meta) => { log::info!("{} send file[{path:?}] success, size is {}",self.get_log_head(),meta.size) }
+            Err(e) => { log::error!("{} send file[{path:?}] error: {e:?}",self.get_log_head()) }
+        }
+    }
+
+    /// send file with name, size and checksum, see [crate::server::callback::CbskServerCallBack::file]<br />
+    /// the file is saved by the client after integrity verification,
+    /// if the transfer is interrupted, send the same file again after the client reconnects to resume from the last confirmed offset<br />
+    /// return Err if file transfer is disabled, the transfer failed or the integrity verification failed
+    #[cfg(feature = "file_transfer")]
+    pub async fn try_send_file(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<FileMeta> {
+        let Some(file_sender) = self.get_attr::<FileSender>() else {
+            return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "file transfer is disabled"));
+        };
+        file_sender.send(path.as_ref(), self.tcp_server_client.as_ref(), self.get_codec().as_ref()).await
+    }
+
+    /// shutdown the client connection, dis_conn will be called
+    pub async fn shutdown(&self) {
+        self.tcp_server_client.shutdown().await
+    }
 }
 
 /// support cbsk write trait
@@ -35,8 +146,18 @@ impl CbskWriteTrait for CbskServerClient {
     }
 
     async fn try_send_bytes(&self, bytes: Vec<u8>) -> std::io::Result<()> {
-        let frame = business::frame(bytes, self.header.as_slice());
-        self.tcp_server_client.try_send_bytes(frame.as_slice()).await
+        self.get_codec().send_data(bytes, self.tcp_server_client.as_ref()).await
+    }
+}
+
+/// support session attr trait
+impl AttrTrait for CbskServerClient {
+    fn get_conn_id(&self) -> u64 {
+        self.tcp_server_client.get_conn_id()
+    }
+
+    fn get_attrs(&self) -> &SessionAttrs {
+        self.tcp_server_client.get_attrs()
     }
 }
 
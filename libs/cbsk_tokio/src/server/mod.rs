@@ -1,6 +1,7 @@
 use std::net::SocketAddr;
 use std::sync::Arc;
 use cbsk_base::tokio::task::JoinHandle;
+use cbsk_socket_tokio::cbsk_socket::metrics::server_metrics::ServerMetrics;
 use cbsk_socket_tokio::cbsk_socket::tcp::server::config::TcpServerConfig;
 use cbsk_socket_tokio::tcp::server::TcpServer;
 use crate::server::business::CbskServerBusines;
@@ -8,6 +9,7 @@ use crate::server::callback::CbskServerCallBack;
 
 pub mod client;
 pub mod callback;
+pub mod auth;
 mod business;
 
 /// cbsk server
@@ -67,4 +69,16 @@ impl CbskServer {
     pub fn get_config(&self) -> Arc<TcpServerConfig> {
         self.tcp_server.conf.clone()
     }
+
+    /// get server counters
+    pub fn get_metrics(&self) -> Arc<ServerMetrics> {
+        self.tcp_server.metrics.clone()
+    }
+
+    /// start prometheus metrics http server in join handle<br />
+    /// addr: metrics http server bind addr, serve /metrics and /health
+    #[cfg(feature = "metrics")]
+    pub fn start_metrics_in_handle(&self, addr: SocketAddr) -> JoinHandle<()> {
+        self.tcp_server.start_metrics_in_handle(addr)
+    }
 }
@@ -1,4 +1,7 @@
+use std::convert::Infallible;
 use crate::data::analysis_data::AnalysisData;
+use crate::data::decoded::Decoded;
+use crate::data::len_encoding::{LenDecode, LenEncoding};
 use crate::data::verify_data::VerifyData;
 
 /// check verify data is too short
@@ -10,7 +13,8 @@ macro_rules! verify_too_short {
     };
 }
 
-/// verify if bytes is cbsk frame
+/// verify if bytes is cbsk frame<br />
+/// the bytes before the header are error frame, a header split by tcp read is kept as too short frame
 pub fn verify(mut bytes: Vec<u8>, header: &[u8]) -> VerifyData {
     verify_too_short!(bytes,header,Vec::new());
 
@@ -26,8 +30,20 @@ pub fn verify(mut bytes: Vec<u8>, header: &[u8]) -> VerifyData {
     }
 
     // if the bytes not has header, return fail
+    // but keep the tail that may be the beginning of a header split by tcp read
     if index == bytes.len() {
-        return VerifyData::fail(bytes);
+        let keep = (1..header.len()).rev().find(|len| bytes.ends_with(&header[..*len])).unwrap_or_default();
+        if keep == 0 {
+            return VerifyData::fail(bytes);
+        }
+        let too_short_frame = bytes.split_off(bytes.len() - keep);
+        return VerifyData::too_short(bytes, too_short_frame);
+    }
+
+    // has header but no data after the header, wait next tcp read
+    if index + header.len() == bytes.len() {
+        let too_short_frame = bytes.split_off(index);
+        return VerifyData::too_short(bytes, too_short_frame);
     }
 
     // has header, change bytes to error frame
@@ -45,70 +61,100 @@ macro_rules! analysis_min_len {
     };
 }
 
-/// check analysis data gt max len
-macro_rules! analysis_max_len {
-    ($len:expr,$max_len:expr,$block:expr) => {
-        if $len > $max_len {
-            return $block;
-        }
-    };
+/// data analysis, the data length is encoded by [LenEncoding::VarLe]
+pub fn analysis(bytes: Vec<u8>, header: &[u8]) -> AnalysisData {
+    analysis_with_len(bytes, header, LenEncoding::VarLe)
 }
 
-/// data analysis
-pub fn analysis(mut bytes: Vec<u8>, header: &[u8]) -> AnalysisData {
-    // limit the minimum length to 3
-    analysis_min_len!(bytes.len(),3,AnalysisData::next_verify(bytes));
-
-    // get bytes of description length
-    let len = usize::from(bytes[0]);
-    // limit description length to 8, and check too short
-    analysis_max_len!(len,8,AnalysisData::too_long(bytes[0],bytes.drain(1..).collect()));
-    analysis_min_len!(bytes.len(),len + 1,AnalysisData::too_short(build_analysis_too_short(bytes,header.to_vec())));
-
-    // obtain the actual data length based on the description length
-    let data_len = &bytes[1..len + 1].iter().enumerate().map(|(i, v)| {
-        256_usize.pow(i.try_into().unwrap_or_default()) * usize::from(*v)
-    }).sum::<usize>();
-    let all_len = data_len + len + 1;
+/// data analysis, the data length is encoded by len_encoding<br />
+/// there is no fixed minimum length, if the length description or the data is incomplete,
+/// the bytes are returned as too short frame with the header added back
+pub fn analysis_with_len(mut bytes: Vec<u8>, header: &[u8], len_encoding: LenEncoding) -> AnalysisData {
+    // obtain the actual data length based on the length encoding
+    let (width, data_len) = match len_encoding.decode(bytes.as_slice()) {
+        LenDecode::Len { width, data_len } => { (width, data_len) }
+        LenDecode::TooShort => { return AnalysisData::too_short(build_analysis_too_short(bytes, header.to_vec())); }
+        LenDecode::TooLong(byte) => { return AnalysisData::too_long(byte, bytes.drain(1..).collect()); }
+    };
+    let all_len = width.saturating_add(data_len);
     analysis_min_len!(bytes.len(),all_len,AnalysisData::too_short(build_analysis_too_short(bytes,header.to_vec())));
 
     // normal data length, obtain real data and next verify data
-    let data = bytes.drain(len + 1..all_len).collect::<Vec<u8>>();
-    bytes.drain(..len + 1);// not, bytes is verify data
+    let data = bytes.drain(width..all_len).collect::<Vec<u8>>();
+    bytes.drain(..width);// not, bytes is verify data
 
     AnalysisData::success(data, bytes)
 }
 
+/// decode all complete cbsk frames in the received bytes, the data length is encoded by len_encoding<br />
+/// return the decoded items in order and the incomplete bytes, the incomplete bytes should be put before the next received bytes
+pub fn decode(bytes: Vec<u8>, header: &[u8], len_encoding: LenEncoding) -> (Vec<Decoded<Infallible>>, Vec<u8>) {
+    decode_with_check(bytes, header, len_encoding, |_| None)
+}
+
+/// decode all complete cbsk frames in the received bytes, see [decode]<br />
+/// check is called with the data frame after each header frame before analysis,
+/// return Some with the event and the next verify frame to reject the header frame, example: resynchronise the stream
+pub fn decode_with_check<E>(mut bytes: Vec<u8>, header: &[u8], len_encoding: LenEncoding, mut check: impl FnMut(&[u8]) -> Option<(E, Vec<u8>)>) -> (Vec<Decoded<E>>, Vec<u8>) {
+    let mut decoded = Vec::new();
+    loop {
+        let verify_data = verify(bytes, header);
+        if !verify_data.error_frame.is_empty() {
+            decoded.push(Decoded::ErrorFrame(verify_data.error_frame));
+        }
+
+        // if has too short frame, wait next read
+        if !verify_data.too_short_frame.is_empty() {
+            return (decoded, verify_data.too_short_frame);
+        }
+
+        let mut next_verify_frame = verify_data.next_verify_frame;
+        if let Some((event, next)) = check(verify_data.data_frame.as_slice()) {
+            decoded.push(Decoded::Rejected(event));
+            next_verify_frame = next;
+        } else if !verify_data.data_frame.is_empty() {
+            // verify success, perform data analysis
+            let analysis_data = analysis_with_len(verify_data.data_frame, header, len_encoding);
+            if let Some(byte) = analysis_data.too_long_byte {
+                decoded.push(Decoded::TooLong(byte));
+            }
+            if !analysis_data.data_frame.is_empty() {
+                decoded.push(Decoded::Data(analysis_data.data_frame));
+            }
+
+            // if has too short frame, wait next read
+            if !analysis_data.too_short_frame.is_empty() {
+                return (decoded, analysis_data.too_short_frame);
+            }
+            next_verify_frame = analysis_data.next_verify_frame;
+        }
+
+        // if has next verify, go to next loop
+        if next_verify_frame.is_empty() {
+            return (decoded, Vec::new());
+        }
+        bytes = next_verify_frame;
+    }
+}
+
 /// build analysis too short data, will add header to data, used for next data reception and verification
 fn build_analysis_too_short(mut bytes: Vec<u8>, mut header: Vec<u8>) -> Vec<u8> {
     header.append(&mut bytes);
     header
 }
 
-/// encapsulation of data before sending
-pub fn frame(mut bytes: Vec<u8>, header: &[u8]) -> Vec<u8> {
-    let mut list = header.to_vec();
-    list.append(&mut calc_data_len(&bytes));
-    list.append(&mut bytes);
-
-    list
+/// encapsulation of data before sending, the data length is encoded by [LenEncoding::VarLe]
+pub fn frame(bytes: Vec<u8>, header: &[u8]) -> Vec<u8> {
+    frame_with_len(bytes, header, LenEncoding::VarLe).unwrap_or_default()
 }
 
-/// calc data len
-fn calc_data_len(bytes: &[u8]) -> Vec<u8> {
-    let mut len = bytes.len();
-    let mut list = Vec::new();
-
-    while len > 255 {
-        list.push(u8::try_from(len % 256).unwrap_or_default());
-        len /= 256;
-    }
+/// encapsulation of data before sending, the data length is encoded by len_encoding<br />
+/// return Err with the source data if the data length can not be represented by len_encoding
+pub fn frame_with_len(mut bytes: Vec<u8>, header: &[u8], len_encoding: LenEncoding) -> Result<Vec<u8>, Vec<u8>> {
+    let Some(mut len) = len_encoding.encode(bytes.len()) else { return Err(bytes); };
+    let mut list = header.to_vec();
+    list.append(&mut len);
+    list.append(&mut bytes);
 
-    // if last len gt zero, add to len list
-    if len > 0 {
-        list.push(u8::try_from(len).unwrap_or_default());
-    }
-    // add first bytes is length of data length
-    list.insert(0, u8::try_from(list.len()).unwrap_or_default());
-    list
+    Ok(list)
 }
@@ -0,0 +1,52 @@
+/// crc32 (ieee) lookup table
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// crc32 (ieee) checksum, can be updated chunk by chunk
+#[derive(Clone, Copy, Debug)]
+pub struct Crc32 {
+    /// current crc value
+    crc: u32,
+}
+
+/// support default
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self { crc: u32::MAX }
+    }
+}
+
+/// custom method
+impl Crc32 {
+    /// update checksum with bytes
+    pub fn update(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.crc = CRC32_TABLE[((self.crc ^ u32::from(*b)) & 0xFF) as usize] ^ (self.crc >> 8);
+        }
+    }
+
+    /// get checksum of the bytes updated so far
+    pub fn finish(&self) -> u32 {
+        !self.crc
+    }
+}
+
+/// get crc32 (ieee) checksum of bytes
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = Crc32::default();
+    crc.update(bytes);
+    crc.finish()
+}
@@ -0,0 +1,12 @@
+/// an item decoded from the received bytes, see [crate::business::decode]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Decoded<E> {
+    /// the bytes before the header frame are not cbsk frame
+    ErrorFrame(Vec<u8>),
+    /// the first byte of the data length description is too long
+    TooLong(u8),
+    /// the data frame after the header frame is rejected by the check, carries the event of the check
+    Rejected(E),
+    /// analysis success data frame, empty data frame is not returned
+    Data(Vec<u8>),
+}
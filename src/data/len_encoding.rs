@@ -0,0 +1,140 @@
+/// the encoding of the data length after the header frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LenEncoding {
+    /// the first byte is the width of the length, followed by the little endian length bytes, the width is up to 8
+    #[default]
+    VarLe,
+    /// fixed u16 big endian
+    U16Be,
+    /// fixed u16 little endian
+    U16Le,
+    /// fixed u32 big endian
+    U32Be,
+    /// fixed u32 little endian
+    U32Le,
+    /// fixed u64 big endian
+    U64Be,
+    /// fixed u64 little endian
+    U64Le,
+    /// LEB128 unsigned varint, up to 10 bytes
+    Leb128,
+}
+
+/// the result of decoding the data length
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LenDecode {
+    /// width: the byte count of the length, data_len: the length of data
+    Len { width: usize, data_len: usize },
+    /// the bytes are not enough to decode the length
+    TooShort,
+    /// the length is invalid or exceeds the limit, carries the first byte
+    TooLong(u8),
+}
+
+/// custom method
+impl LenEncoding {
+    /// encode data length<br />
+    /// return None if the length can not be represented by this encoding
+    pub fn encode(&self, len: usize) -> Option<Vec<u8>> {
+        let bytes = match self {
+            Self::VarLe => { var_le_encode(len) }
+            Self::U16Be => { u16::try_from(len).ok()?.to_be_bytes().to_vec() }
+            Self::U16Le => { u16::try_from(len).ok()?.to_le_bytes().to_vec() }
+            Self::U32Be => { u32::try_from(len).ok()?.to_be_bytes().to_vec() }
+            Self::U32Le => { u32::try_from(len).ok()?.to_le_bytes().to_vec() }
+            Self::U64Be => { u64::try_from(len).ok()?.to_be_bytes().to_vec() }
+            Self::U64Le => { u64::try_from(len).ok()?.to_le_bytes().to_vec() }
+            Self::Leb128 => { leb128_encode(u64::try_from(len).ok()?) }
+        };
+        Some(bytes)
+    }
+
+    /// decode data length from the bytes after the header frame
+    pub fn decode(&self, bytes: &[u8]) -> LenDecode {
+        match self {
+            Self::VarLe => { var_le_decode(bytes) }
+            Self::U16Be => { fixed_decode(bytes, |b: [u8; 2]| u64::from(u16::from_be_bytes(b))) }
+            Self::U16Le => { fixed_decode(bytes, |b: [u8; 2]| u64::from(u16::from_le_bytes(b))) }
+            Self::U32Be => { fixed_decode(bytes, |b: [u8; 4]| u64::from(u32::from_be_bytes(b))) }
+            Self::U32Le => { fixed_decode(bytes, |b: [u8; 4]| u64::from(u32::from_le_bytes(b))) }
+            Self::U64Be => { fixed_decode(bytes, u64::from_be_bytes) }
+            Self::U64Le => { fixed_decode(bytes, u64::from_le_bytes) }
+            Self::Leb128 => { leb128_decode(bytes) }
+        }
+    }
+}
+
+/// the first byte is the width of the length, followed by the little endian length bytes
+fn var_le_encode(mut len: usize) -> Vec<u8> {
+    let mut list = Vec::new();
+
+    while len > 255 {
+        list.push(u8::try_from(len % 256).unwrap_or_default());
+        len /= 256;
+    }
+
+    // if last len gt zero, add to len list
+    if len > 0 {
+        list.push(u8::try_from(len).unwrap_or_default());
+    }
+    // add first bytes is length of data length
+    list.insert(0, u8::try_from(list.len()).unwrap_or_default());
+    list
+}
+
+/// decode the variable little endian length
+fn var_le_decode(bytes: &[u8]) -> LenDecode {
+    let Some(first) = bytes.first().copied() else { return LenDecode::TooShort; };
+    let len = usize::from(first);
+    // limit description length to 8
+    if len > 8 { return LenDecode::TooLong(first); }
+    if bytes.len() < len + 1 { return LenDecode::TooShort; }
+
+    // obtain the actual data length based on the description length
+    let data_len = bytes[1..len + 1].iter().enumerate().map(|(i, v)| {
+        256_usize.pow(i.try_into().unwrap_or_default()) * usize::from(*v)
+    }).sum::<usize>();
+    LenDecode::Len { width: len + 1, data_len }
+}
+
+/// decode the fixed width length
+fn fixed_decode<const N: usize>(bytes: &[u8], from: impl FnOnce([u8; N]) -> u64) -> LenDecode {
+    let Some(len) = bytes.first_chunk::<N>() else { return LenDecode::TooShort; };
+    match usize::try_from(from(*len)) {
+        Ok(data_len) => { LenDecode::Len { width: N, data_len } }
+        Err(_) => { LenDecode::TooLong(len[0]) }
+    }
+}
+
+/// LEB128 unsigned varint, 7 bits per byte, the high bit means more bytes follow
+fn leb128_encode(mut len: u64) -> Vec<u8> {
+    let mut list = Vec::new();
+    loop {
+        let byte = u8::try_from(len & 0x7F).unwrap_or_default();
+        len >>= 7;
+        if len == 0 {
+            list.push(byte);
+            return list;
+        }
+        list.push(byte | 0x80);
+    }
+}
+
+/// decode LEB128 unsigned varint
+fn leb128_decode(bytes: &[u8]) -> LenDecode {
+    let mut value = 0_u64;
+    for (i, b) in bytes.iter().enumerate() {
+        // the 10th byte can only carry the highest bit of u64
+        if i == 9 && *b > 1 { return LenDecode::TooLong(bytes[0]); }
+
+        value |= u64::from(b & 0x7F) << (i * 7);
+        if b & 0x80 != 0 { continue; }
+
+        return match usize::try_from(value) {
+            Ok(data_len) => { LenDecode::Len { width: i + 1, data_len } }
+            Err(_) => { LenDecode::TooLong(bytes[0]) }
+        };
+    }
+
+    LenDecode::TooShort
+}
@@ -1,5 +1,7 @@
 pub mod verify_data;
 pub mod analysis_data;
+pub mod len_encoding;
+pub mod decoded;
 
 /// cbsk default header
 pub fn default_header() -> Vec<u8> {
@@ -0,0 +1,70 @@
+/// the hello frame data header<br />
+/// 0xFF is not a message kind or a compression flag, so the hello frame can be distinguished from other frames
+pub const HELLO_HEADER: [u8; 4] = [0xFF, b'c', b'b', b'h'];
+/// the hello frame data length, header + version(u16) + features(u64)
+pub const HELLO_LEN: usize = HELLO_HEADER.len() + 2 + 8;
+/// current protocol version
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// acknowledged send mode
+pub const FEATURE_RELIABLE: u64 = 1;
+/// chunked large payload transfer
+pub const FEATURE_CHUNK: u64 = 1 << 1;
+/// file transfer
+pub const FEATURE_FILE: u64 = 1 << 2;
+/// zstd frame compression
+pub const FEATURE_COMPRESS_ZSTD: u64 = 1 << 3;
+/// lz4 frame compression
+pub const FEATURE_COMPRESS_LZ4: u64 = 1 << 4;
+/// aes-256-gcm frame encryption
+pub const FEATURE_ENCRYPT_AES_GCM: u64 = 1 << 5;
+/// chacha20-poly1305 frame encryption
+pub const FEATURE_ENCRYPT_CHACHA20: u64 = 1 << 6;
+
+/// protocol hello, advertise the protocol version and supported feature bits<br />
+/// the hello frame data is not compressed, encrypted or message encoded, so any peer can read it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Hello {
+    /// protocol version
+    pub version: u16,
+    /// supported feature bits, see FEATURE_*
+    pub features: u64,
+}
+
+/// custom method
+impl Hello {
+    /// create hello with current protocol version
+    pub fn new(features: u64) -> Self {
+        Self { version: PROTOCOL_VERSION, features }
+    }
+
+    /// is the feature supported
+    pub fn has(&self, feature: u64) -> bool {
+        self.features & feature == feature
+    }
+
+    /// agree on the lower protocol version and the intersection of feature bits
+    pub fn negotiate(&self, peer: &Self) -> Self {
+        Self { version: self.version.min(peer.version), features: self.features & peer.features }
+    }
+
+    /// encode hello to cbsk frame data
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HELLO_LEN);
+        bytes.extend_from_slice(&HELLO_HEADER);
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.extend_from_slice(&self.features.to_le_bytes());
+        bytes
+    }
+
+    /// decode hello from cbsk frame data<br />
+    /// return None if the data is not a hello frame data
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != HELLO_LEN || !bytes.starts_with(&HELLO_HEADER) { return None; }
+
+        let (version, features) = bytes[HELLO_HEADER.len()..].split_at(2);
+        let version = u16::from_le_bytes(version.try_into().ok()?);
+        let features = u64::from_le_bytes(features.try_into().ok()?);
+        Some(Self { version, features })
+    }
+}
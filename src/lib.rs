@@ -1,2 +1,5 @@
 pub mod business;
-pub mod data;
\ No newline at end of file
+pub mod checksum;
+pub mod data;
+pub mod hello;
+pub mod message;
\ No newline at end of file
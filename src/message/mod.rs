@@ -0,0 +1,119 @@
+/// plain business data
+pub const KIND_DATA: u8 = 0;
+/// business data with sequence number, the peer should reply ack
+pub const KIND_RELIABLE: u8 = 1;
+/// acknowledge all reliable data up to the sequence number
+pub const KIND_ACK: u8 = 2;
+/// reliable session id, sent after each connection is successful
+pub const KIND_SESSION: u8 = 3;
+/// a chunk of large payload
+pub const KIND_CHUNK: u8 = 4;
+/// file transfer offer, carries file metadata
+pub const KIND_FILE_OFFER: u8 = 5;
+/// file transfer confirmed offset
+pub const KIND_FILE_ACK: u8 = 6;
+/// file transfer data
+pub const KIND_FILE_DATA: u8 = 7;
+/// file transfer finished, carries integrity verification result
+pub const KIND_FILE_DONE: u8 = 8;
+
+/// cbsk message<br />
+/// if message mode is enabled, the first byte of each cbsk frame data is the message kind
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    /// plain business data
+    Data(Vec<u8>),
+    /// business data with sequence number
+    Reliable { seq: u64, data: Vec<u8> },
+    /// acknowledge all reliable data up to the sequence number
+    Ack(u64),
+    /// reliable session id
+    Session(u64),
+    /// a chunk of large payload<br />
+    /// id: transfer id, offset: the offset of data in payload, total: the total length of payload
+    Chunk { id: u64, offset: u64, total: u64, data: Vec<u8> },
+    /// file transfer offer<br />
+    /// id: transfer id, size: file size, checksum: crc32 of the file, name: file name
+    FileOffer { id: u64, size: u64, checksum: u32, name: String },
+    /// file transfer confirmed offset, the data before offset has been saved by the receiver
+    FileAck { id: u64, offset: u64 },
+    /// file transfer data
+    FileData { id: u64, offset: u64, data: Vec<u8> },
+    /// file transfer finished, ok is the integrity verification result
+    FileDone { id: u64, ok: bool },
+}
+
+/// custom method
+impl Message {
+    /// encode message to cbsk frame data
+    pub fn encode(self) -> Vec<u8> {
+        match self {
+            Self::Data(data) => { encode_u64s(KIND_DATA, &[], data) }
+            Self::Reliable { seq, data } => { encode_u64s(KIND_RELIABLE, &[seq], data) }
+            Self::Ack(seq) => { encode_u64s(KIND_ACK, &[seq], Vec::new()) }
+            Self::Session(id) => { encode_u64s(KIND_SESSION, &[id], Vec::new()) }
+            Self::Chunk { id, offset, total, data } => { encode_u64s(KIND_CHUNK, &[id, offset, total], data) }
+            Self::FileOffer { id, size, checksum, name } => {
+                encode_u64s(KIND_FILE_OFFER, &[id, size, u64::from(checksum)], name.into_bytes())
+            }
+            Self::FileAck { id, offset } => { encode_u64s(KIND_FILE_ACK, &[id, offset], Vec::new()) }
+            Self::FileData { id, offset, data } => { encode_u64s(KIND_FILE_DATA, &[id, offset], data) }
+            Self::FileDone { id, ok } => { encode_u64s(KIND_FILE_DONE, &[id], vec![u8::from(ok)]) }
+        }
+    }
+
+    /// decode message from cbsk frame data<br />
+    /// return Err with the source data if the message kind is unknown or the data is incomplete
+    pub fn decode(bytes: Vec<u8>) -> Result<Self, Vec<u8>> {
+        let Some(kind) = bytes.first().copied() else { return Err(bytes); };
+        match kind {
+            KIND_DATA => { decode_u64s::<0>(bytes).map(|([], data)| Self::Data(data)) }
+            KIND_RELIABLE => { decode_u64s(bytes).map(|([seq], data)| Self::Reliable { seq, data }) }
+            KIND_ACK => { decode_u64s(bytes).map(|([seq], _)| Self::Ack(seq)) }
+            KIND_SESSION => { decode_u64s(bytes).map(|([id], _)| Self::Session(id)) }
+            KIND_CHUNK => { decode_u64s(bytes).map(|([id, offset, total], data)| Self::Chunk { id, offset, total, data }) }
+            KIND_FILE_OFFER => {
+                let ([id, size, checksum], name) = decode_u64s(bytes)?;
+                match (u32::try_from(checksum), String::from_utf8(name)) {
+                    (Ok(checksum), Ok(name)) => { Ok(Self::FileOffer { id, size, checksum, name }) }
+                    (_, name) => {
+                        let name = name.map_or_else(|e| e.into_bytes(), String::into_bytes);
+                        Err(encode_u64s(KIND_FILE_OFFER, &[id, size, checksum], name))
+                    }
+                }
+            }
+            KIND_FILE_ACK => { decode_u64s(bytes).map(|([id, offset], _)| Self::FileAck { id, offset }) }
+            KIND_FILE_DATA => { decode_u64s(bytes).map(|([id, offset], data)| Self::FileData { id, offset, data }) }
+            KIND_FILE_DONE => { decode_u64s(bytes).map(|([id], ok)| Self::FileDone { id, ok: ok.first() == Some(&1) }) }
+            _ => { Err(bytes) }
+        }
+    }
+}
+
+/// encode message kind, little endian u64 values and data
+fn encode_u64s(kind: u8, values: &[u64], mut data: Vec<u8>) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(1 + values.len() * 8 + data.len());
+    bytes.push(kind);
+    for value in values {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    bytes.append(&mut data);
+    bytes
+}
+
+/// decode little endian u64 values after the message kind, and the remaining data<br />
+/// return Err with the source data if the data is incomplete
+fn decode_u64s<const N: usize>(mut bytes: Vec<u8>) -> Result<([u64; N], Vec<u8>), Vec<u8>> {
+    let data_start = 1 + N * 8;
+    if bytes.len() < data_start { return Err(bytes); }
+
+    let mut values = [0; N];
+    for (i, value) in values.iter_mut().enumerate() {
+        let start = 1 + i * 8;
+        let mut le = [0; 8];
+        le.copy_from_slice(&bytes[start..start + 8]);
+        *value = u64::from_le_bytes(le);
+    }
+    let data = bytes.split_off(data_start);
+    Ok((values, data))
+}
@@ -0,0 +1,80 @@
+use cbsk::business;
+use cbsk::data::decoded::Decoded;
+use cbsk::data::len_encoding::LenEncoding;
+
+/// the default cbsk header
+pub const HEADER: &[u8] = b"cbsk";
+
+/// the frames received by [Receiver]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Received {
+    /// non-empty data frames, empty data frames are not delivered by cbsk_tokio and cbsk_rayon
+    pub frames: Vec<Vec<u8>>,
+    /// the bytes reported as error frame
+    pub error_frames: Vec<Vec<u8>>,
+    /// the bytes reported as too long length
+    pub too_long: Vec<u8>,
+}
+
+/// receive like cbsk_tokio and cbsk_rayon, the returned bytes are prepended to the next read
+pub struct Receiver {
+    /// the cbsk header
+    header: Vec<u8>,
+    /// the length encoding
+    len_encoding: LenEncoding,
+    /// the bytes waiting for the next read
+    buf: Vec<u8>,
+    /// received frames
+    pub received: Received,
+}
+
+impl Receiver {
+    pub fn new(header: &[u8], len_encoding: LenEncoding) -> Self {
+        Self { header: header.to_vec(), len_encoding, buf: Vec::new(), received: Received::default() }
+    }
+
+    /// receive all chunks, return the received frames and the remaining bytes
+    pub fn recv_all<'a>(mut self, chunks: impl IntoIterator<Item=&'a [u8]>) -> (Received, Vec<u8>) {
+        for chunk in chunks {
+            self.read(chunk);
+        }
+        (self.received, self.buf)
+    }
+
+    /// a tcp read
+    pub fn read(&mut self, chunk: &[u8]) {
+        let mut bytes = std::mem::take(&mut self.buf);
+        bytes.extend_from_slice(chunk);
+        self.buf = self.recv(bytes);
+    }
+
+    /// the receive loop, decoded by [business::decode]
+    fn recv(&mut self, bytes: Vec<u8>) -> Vec<u8> {
+        let (decoded, too_short_frame) = business::decode(bytes, &self.header, self.len_encoding);
+        for decoded in decoded {
+            match decoded {
+                Decoded::ErrorFrame(error_frame) => { self.received.error_frames.push(error_frame); }
+                Decoded::TooLong(byte) => { self.received.too_long.push(byte); }
+                Decoded::Data(data) => { self.received.frames.push(data); }
+            }
+        }
+        too_short_frame
+    }
+}
+
+/// all length encodings
+pub const LEN_ENCODINGS: [LenEncoding; 8] = [
+    LenEncoding::VarLe,
+    LenEncoding::U16Be,
+    LenEncoding::U16Le,
+    LenEncoding::U32Be,
+    LenEncoding::U32Le,
+    LenEncoding::U64Be,
+    LenEncoding::U64Le,
+    LenEncoding::Leb128,
+];
+
+/// frame all payloads and concat
+pub fn frames(payloads: &[Vec<u8>], header: &[u8], len_encoding: LenEncoding) -> Vec<u8> {
+    payloads.iter().flat_map(|payload| business::frame_with_len(payload.clone(), header, len_encoding).unwrap()).collect()
+}
@@ -0,0 +1,69 @@
+use cbsk::data::len_encoding::{LenDecode, LenEncoding};
+use common::{HEADER, LEN_ENCODINGS, Receiver, frames};
+use proptest::prelude::*;
+
+mod common;
+
+fn len_encoding() -> impl Strategy<Value=LenEncoding> {
+    proptest::sample::select(LEN_ENCODINGS.to_vec())
+}
+
+/// split bytes at the given cut points
+fn chunk(bytes: &[u8], cuts: &[usize]) -> Vec<Vec<u8>> {
+    let mut cuts = cuts.iter().map(|cut| cut % (bytes.len() + 1)).collect::<Vec<_>>();
+    cuts.sort_unstable();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    for cut in cuts.into_iter().chain([bytes.len()]) {
+        chunks.push(bytes[start..cut].to_vec());
+        start = cut;
+    }
+    chunks
+}
+
+proptest! {
+    #[test]
+    fn len_round_trip(len_encoding in len_encoding(), len in any::<usize>()) {
+        match len_encoding.encode(len) {
+            Some(prefix) => {
+                prop_assert_eq!(len_encoding.decode(prefix.as_slice()), LenDecode::Len { width: prefix.len(), data_len: len });
+            }
+            None => {
+                prop_assert!(matches!(len_encoding, LenEncoding::U16Be | LenEncoding::U16Le | LenEncoding::U32Be | LenEncoding::U32Le));
+            }
+        }
+    }
+
+    #[test]
+    fn frames_round_trip_with_random_chunking(
+        len_encoding in len_encoding(),
+        payloads in proptest::collection::vec(proptest::collection::vec(any::<u8>(), 0..600), 1..8),
+        cuts in proptest::collection::vec(any::<usize>(), 0..16),
+    ) {
+        let bytes = frames(&payloads, HEADER, len_encoding);
+        let chunks = chunk(&bytes, &cuts);
+        let (received, remaining) = Receiver::new(HEADER, len_encoding).recv_all(chunks.iter().map(Vec::as_slice));
+
+        let expected = payloads.into_iter().filter(|payload| !payload.is_empty()).collect::<Vec<_>>();
+        prop_assert_eq!(received.frames, expected);
+        prop_assert!(received.error_frames.is_empty());
+        prop_assert!(received.too_long.is_empty());
+        prop_assert!(remaining.is_empty());
+    }
+
+    #[test]
+    fn garbage_before_frames_is_reported(
+        len_encoding in len_encoding(),
+        garbage in proptest::collection::vec(any::<u8>().prop_filter("not header start", |b| *b != HEADER[0]), 1..64),
+        payloads in proptest::collection::vec(proptest::collection::vec(any::<u8>(), 1..200), 1..4),
+        cuts in proptest::collection::vec(any::<usize>(), 0..8),
+    ) {
+        let bytes = [garbage.clone(), frames(&payloads, HEADER, len_encoding)].concat();
+        let chunks = chunk(&bytes, &cuts);
+        let (received, remaining) = Receiver::new(HEADER, len_encoding).recv_all(chunks.iter().map(Vec::as_slice));
+
+        prop_assert_eq!(received.frames, payloads);
+        prop_assert_eq!(received.error_frames.concat(), garbage);
+        prop_assert!(remaining.is_empty());
+    }
+}
@@ -0,0 +1,278 @@
+use cbsk::business;
+use cbsk::data::decoded::Decoded;
+use cbsk::data::len_encoding::{LenDecode, LenEncoding};
+use common::{HEADER, LEN_ENCODINGS, Received, Receiver, frames};
+
+mod common;
+
+/// a frame test vector from vectors/frame.txt
+struct Vector {
+    line: usize,
+    len_encoding: LenEncoding,
+    payload: Vec<u8>,
+    prefix: Vec<u8>,
+}
+
+fn parse_hex(hex: &str) -> Vec<u8> {
+    hex.split_whitespace().map(|b| u8::from_str_radix(b, 16).unwrap()).collect()
+}
+
+fn parse_payload(payload: &str) -> Vec<u8> {
+    if payload == "-" { return Vec::new(); }
+    match payload.split_once('*') {
+        Some((count, byte)) => { vec![u8::from_str_radix(byte, 16).unwrap(); count.parse().unwrap()] }
+        None => { parse_hex(payload) }
+    }
+}
+
+fn parse_len_encoding(name: &str) -> LenEncoding {
+    match name {
+        "var_le" => { LenEncoding::VarLe }
+        "u16_be" => { LenEncoding::U16Be }
+        "u16_le" => { LenEncoding::U16Le }
+        "u32_be" => { LenEncoding::U32Be }
+        "u32_le" => { LenEncoding::U32Le }
+        "u64_be" => { LenEncoding::U64Be }
+        "u64_le" => { LenEncoding::U64Le }
+        "leb128" => { LenEncoding::Leb128 }
+        _ => { panic!("unknown length encoding {name}") }
+    }
+}
+
+fn vectors() -> Vec<Vector> {
+    include_str!("vectors/frame.txt").lines().enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| {
+            let columns = line.split('|').map(str::trim).collect::<Vec<_>>();
+            assert_eq!(columns.len(), 3, "line {}", i + 1);
+            Vector {
+                line: i + 1,
+                len_encoding: parse_len_encoding(columns[0]),
+                payload: parse_payload(columns[1]),
+                prefix: parse_hex(columns[2]),
+            }
+        })
+        .collect()
+}
+
+fn frame_of(vector: &Vector) -> Vec<u8> {
+    [HEADER, vector.prefix.as_slice(), vector.payload.as_slice()].concat()
+}
+
+#[test]
+fn vectors_encode() {
+    for vector in vectors() {
+        let frame = business::frame_with_len(vector.payload.clone(), HEADER, vector.len_encoding).unwrap();
+        assert_eq!(frame, frame_of(&vector), "line {}", vector.line);
+        if vector.len_encoding == LenEncoding::VarLe {
+            assert_eq!(business::frame(vector.payload.clone(), HEADER), frame, "line {}", vector.line);
+        }
+    }
+}
+
+#[test]
+fn vectors_decode_len() {
+    for vector in vectors() {
+        let decode = vector.len_encoding.decode(vector.prefix.as_slice());
+        assert_eq!(decode, LenDecode::Len { width: vector.prefix.len(), data_len: vector.payload.len() }, "line {}", vector.line);
+        // any shorter prefix needs more bytes
+        for len in 0..vector.prefix.len() {
+            assert_eq!(vector.len_encoding.decode(&vector.prefix[..len]), LenDecode::TooShort, "line {}", vector.line);
+        }
+    }
+}
+
+#[test]
+fn vectors_decode_frame() {
+    for vector in vectors() {
+        let (received, remaining) = Receiver::new(HEADER, vector.len_encoding).recv_all([frame_of(&vector).as_slice()]);
+        let expected = if vector.payload.is_empty() { vec![] } else { vec![vector.payload.clone()] };
+        assert_eq!(received, Received { frames: expected, ..Received::default() }, "line {}", vector.line);
+        assert!(remaining.is_empty(), "line {}", vector.line);
+    }
+}
+
+#[test]
+fn verify_and_analysis() {
+    let verify_data = business::verify(b"xxcbsk\x01\x02hi".to_vec(), HEADER);
+    assert_eq!(verify_data.error_frame, b"xx");
+    assert_eq!(verify_data.data_frame, b"\x01\x02hi");
+
+    let analysis_data = business::analysis(verify_data.data_frame, HEADER);
+    assert_eq!(analysis_data.data_frame, b"hi");
+    assert!(analysis_data.next_verify_frame.is_empty());
+    assert!(analysis_data.too_short_frame.is_empty());
+    assert_eq!(analysis_data.too_long_byte, None);
+
+    // not enough data, the header is added back for the next read
+    let analysis_data = business::analysis(b"\x01\x05hel".to_vec(), HEADER);
+    assert_eq!(analysis_data.too_short_frame, b"cbsk\x01\x05hel");
+
+    // the length description is longer than 8 bytes
+    let analysis_data = business::analysis(b"\x09abc".to_vec(), HEADER);
+    assert_eq!(analysis_data.too_long_byte, Some(9));
+    assert_eq!(analysis_data.next_verify_frame, b"abc");
+}
+
+#[test]
+fn analysis_short_length_description() {
+    // a zero length frame is complete after the one byte length description
+    let analysis_data = business::analysis(b"\x00".to_vec(), HEADER);
+    assert!(analysis_data.data_frame.is_empty());
+    assert!(analysis_data.too_short_frame.is_empty());
+    assert!(analysis_data.next_verify_frame.is_empty());
+
+    let analysis_data = business::analysis(b"\x00cbsk\x01\x01a".to_vec(), HEADER);
+    assert!(analysis_data.data_frame.is_empty());
+    assert_eq!(analysis_data.next_verify_frame, b"cbsk\x01\x01a");
+
+    // the length description or the data is incomplete, wait next read
+    for bytes in [b"\x01".as_slice(), b"\x01\x01", b"\x02\x00"] {
+        let analysis_data = business::analysis(bytes.to_vec(), HEADER);
+        assert_eq!(analysis_data.too_short_frame, [HEADER, bytes].concat(), "{bytes:?}");
+    }
+    for len_encoding in LEN_ENCODINGS {
+        let analysis_data = business::analysis_with_len(Vec::new(), HEADER, len_encoding);
+        assert_eq!(analysis_data.too_short_frame, HEADER, "{len_encoding:?}");
+    }
+}
+
+#[test]
+fn split_reads() {
+    for len_encoding in LEN_ENCODINGS {
+        let frame = frames(&[b"hello world".to_vec()], HEADER, len_encoding);
+        for at in 0..=frame.len() {
+            let (first, second) = frame.split_at(at);
+            let (received, remaining) = Receiver::new(HEADER, len_encoding).recv_all([first, second]);
+            assert_eq!(received.frames, vec![b"hello world".to_vec()], "{len_encoding:?} split at {at}");
+            assert!(received.error_frames.is_empty(), "{len_encoding:?} split at {at}");
+            assert!(remaining.is_empty(), "{len_encoding:?} split at {at}");
+        }
+
+        // one byte per read
+        let (received, _) = Receiver::new(HEADER, len_encoding).recv_all(frame.chunks(1));
+        assert_eq!(received.frames, vec![b"hello world".to_vec()], "{len_encoding:?}");
+    }
+}
+
+#[test]
+fn multiple_frames_per_read() {
+    let payloads = vec![b"a".to_vec(), b"cbsk inside payload".to_vec(), vec![0; 300], b"z".to_vec()];
+    for len_encoding in LEN_ENCODINGS {
+        let bytes = frames(&payloads, HEADER, len_encoding);
+        let (received, remaining) = Receiver::new(HEADER, len_encoding).recv_all([bytes.as_slice()]);
+        assert_eq!(received, Received { frames: payloads.clone(), ..Received::default() }, "{len_encoding:?}");
+        assert!(remaining.is_empty());
+    }
+}
+
+#[test]
+fn garbage_before_header() {
+    let bytes = [b"garbage".as_slice(), &frames(&[b"one".to_vec()], HEADER, LenEncoding::VarLe), b"xy", &frames(&[b"two".to_vec()], HEADER, LenEncoding::VarLe)].concat();
+    let (received, remaining) = Receiver::new(HEADER, LenEncoding::VarLe).recv_all([bytes.as_slice()]);
+    assert_eq!(received.frames, vec![b"one".to_vec(), b"two".to_vec()]);
+    assert_eq!(received.error_frames, vec![b"garbage".to_vec(), b"xy".to_vec()]);
+    assert!(remaining.is_empty());
+
+    // no header at all
+    let (received, remaining) = Receiver::new(HEADER, LenEncoding::VarLe).recv_all([b"no header here".as_slice()]);
+    assert_eq!(received.error_frames, vec![b"no header here".to_vec()]);
+    assert!(remaining.is_empty());
+}
+
+#[test]
+fn header_split_by_read() {
+    // the tail may be the beginning of a header, only the bytes before it are error frame
+    for (bytes, error_frame, too_short_frame) in [
+        (b"xxxxc".as_slice(), b"xxxx".as_slice(), b"c".as_slice()),
+        (b"xxxxcb", b"xxxx", b"cb"),
+        (b"xxxxcbs", b"xxxx", b"cbs"),
+        (b"xxxxcbsk", b"xxxx", b"cbsk"),
+        (b"xxxxcbx", b"xxxxcbx", b""),
+    ] {
+        let verify_data = business::verify(bytes.to_vec(), HEADER);
+        assert_eq!(verify_data.error_frame, error_frame, "{bytes:?}");
+        assert_eq!(verify_data.too_short_frame, too_short_frame, "{bytes:?}");
+        assert!(verify_data.data_frame.is_empty(), "{bytes:?}");
+    }
+
+    let frame = frames(&[b"one".to_vec()], HEADER, LenEncoding::VarLe);
+    for at in 1..HEADER.len() {
+        let first = [b"garbage".as_slice(), &frame[..at]].concat();
+        let (received, remaining) = Receiver::new(HEADER, LenEncoding::VarLe).recv_all([first.as_slice(), &frame[at..]]);
+        assert_eq!(received.error_frames, vec![b"garbage".to_vec()], "split at {at}");
+        assert_eq!(received.frames, vec![b"one".to_vec()], "split at {at}");
+        assert!(remaining.is_empty(), "split at {at}");
+    }
+}
+
+#[test]
+fn decode_with_check() {
+    // reject the data length greater than 10, and skip one byte to resynchronise
+    let mut checked = Vec::new();
+    let check = |data_frame: &[u8]| {
+        checked.push(data_frame.to_vec());
+        match LenEncoding::VarLe.decode(data_frame) {
+            LenDecode::Len { data_len, .. } if data_len > 10 => { Some((data_len, [HEADER, data_frame].concat()[1..].to_vec())) }
+            _ => { None }
+        }
+    };
+    let bytes = [b"xx".as_slice(), b"cbsk\x01\x64", &frames(&[b"hi".to_vec()], HEADER, LenEncoding::VarLe), b"cbsk\x01\x05he"].concat();
+    let (decoded, remaining) = business::decode_with_check(bytes, HEADER, LenEncoding::VarLe, check);
+    assert_eq!(decoded, vec![
+        Decoded::ErrorFrame(b"xx".to_vec()),
+        Decoded::Rejected(100),
+        Decoded::ErrorFrame(b"bsk\x01\x64".to_vec()),
+        Decoded::Data(b"hi".to_vec()),
+    ]);
+    assert_eq!(remaining, b"cbsk\x01\x05he");
+    assert_eq!(checked, vec![b"\x01\x64cbsk\x01\x02hicbsk\x01\x05he".to_vec(), b"\x01\x02hicbsk\x01\x05he".to_vec(), b"\x01\x05he".to_vec()]);
+
+    // without check, the same as the receive loop
+    let bytes = frames(&[b"one".to_vec(), Vec::new(), b"two".to_vec()], HEADER, LenEncoding::U16Be);
+    let (decoded, remaining) = business::decode(bytes, HEADER, LenEncoding::U16Be);
+    assert_eq!(decoded, vec![Decoded::Data(b"one".to_vec()), Decoded::Data(b"two".to_vec())]);
+    assert!(remaining.is_empty());
+}
+
+#[test]
+fn too_long_length_byte() {
+    let bytes = [b"cbsk\x09".as_slice(), &frames(&[b"next".to_vec()], HEADER, LenEncoding::VarLe)].concat();
+    let (received, _) = Receiver::new(HEADER, LenEncoding::VarLe).recv_all([bytes.as_slice()]);
+    assert_eq!(received.too_long, vec![9]);
+    assert_eq!(received.frames, vec![b"next".to_vec()]);
+
+    // LEB128 longer than 10 bytes
+    let bytes = [b"cbsk".as_slice(), &[0xff; 10], &frames(&[b"next".to_vec()], HEADER, LenEncoding::Leb128)].concat();
+    let (received, _) = Receiver::new(HEADER, LenEncoding::Leb128).recv_all([bytes.as_slice()]);
+    assert_eq!(received.too_long, vec![0xff]);
+    assert_eq!(received.frames, vec![b"next".to_vec()]);
+}
+
+#[test]
+fn zero_length_payloads() {
+    for len_encoding in LEN_ENCODINGS {
+        let payloads = vec![vec![], b"one".to_vec(), vec![], vec![], b"two".to_vec(), vec![]];
+        let bytes = frames(&payloads, HEADER, len_encoding);
+        for chunk_len in [1, 2, 3, 5, bytes.len()] {
+            let (received, remaining) = Receiver::new(HEADER, len_encoding).recv_all(bytes.chunks(chunk_len));
+            assert_eq!(received, Received { frames: vec![b"one".to_vec(), b"two".to_vec()], ..Received::default() }, "{len_encoding:?} {chunk_len}");
+            assert!(remaining.is_empty(), "{len_encoding:?} {chunk_len}");
+        }
+    }
+}
+
+#[test]
+fn custom_header() {
+    let header = [0xAA, 0x55];
+    let bytes = frames(&[b"custom".to_vec()], &header, LenEncoding::U16Be);
+    assert_eq!(bytes, [0xAA, 0x55, 0x00, 0x06, b'c', b'u', b's', b't', b'o', b'm']);
+    let (received, _) = Receiver::new(&header, LenEncoding::U16Be).recv_all(bytes.chunks(3));
+    assert_eq!(received.frames, vec![b"custom".to_vec()]);
+}
+
+#[test]
+fn unrepresentable_length() {
+    assert_eq!(business::frame_with_len(vec![0; 65536], HEADER, LenEncoding::U16Be), Err(vec![0; 65536]));
+    assert_eq!(LenEncoding::U16Le.encode(65536), None);
+}